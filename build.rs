@@ -1,10 +1,22 @@
 fn main() {
+    println!("cargo:rustc-check-cfg=cfg(gs_install_apps_job)");
+
     if std::env::var_os("CARGO_FEATURE_LEGACY_FFI").is_some() {
         match pkg_config::Config::new()
             .atleast_version("46")
             .probe("gnome-software")
         {
             Ok(library) => {
+                // gs_plugin_job_install_apps_new and
+                // gs_plugin_job_uninstall_apps_new are new in 47.
+                let major = library
+                    .version
+                    .split('.')
+                    .next()
+                    .and_then(|major| major.parse::<u32>().ok());
+                if major.is_some_and(|major| major >= 47) {
+                    println!("cargo:rustc-cfg=gs_install_apps_job");
+                }
                 for link_path in library.link_paths {
                     println!(
                         "cargo:rustc-link-arg=-Wl,-rpath,{}",
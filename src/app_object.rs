@@ -0,0 +1,75 @@
+use std::cell::{Cell, RefCell};
+
+use glib::prelude::*;
+use glib::subclass::prelude::*;
+use glib::Object;
+
+use crate::plugins::MergedApp;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::AppObject)]
+    pub struct AppObject {
+        #[property(get, construct_only)]
+        pub app_id: RefCell<String>,
+        #[property(get, construct_only)]
+        pub name: RefCell<String>,
+        #[property(get, construct_only)]
+        pub summary: RefCell<String>,
+        #[property(get, construct_only)]
+        pub installed: Cell<bool>,
+        /// Position in the popular list; lower is more popular.
+        #[property(get, construct_only)]
+        pub popularity: Cell<u32>,
+        /// The app with every source, set once after construction.
+        pub app: RefCell<Option<MergedApp>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for AppObject {
+        const NAME: &'static str = "InstallGridApp";
+        type Type = super::AppObject;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for AppObject {}
+}
+
+glib::wrapper! {
+    /// Immutable snapshot of a [`MergedApp`] for list models. Changed apps
+    /// are replaced by a new object rather than modified.
+    pub struct AppObject(ObjectSubclass<imp::AppObject>);
+}
+
+impl AppObject {
+    pub fn new(app: &MergedApp, popularity: u32) -> Self {
+        let object = Object::builder::<Self>()
+            .property("app-id", app.app_id())
+            .property("name", &app.primary.name)
+            .property("summary", &app.primary.summary)
+            .property("installed", app.primary.installed)
+            .property("popularity", popularity)
+            .build();
+        object.imp().app.replace(Some(app.clone()));
+        object
+    }
+
+    pub fn app(&self) -> MergedApp {
+        self.imp()
+            .app
+            .borrow()
+            .clone()
+            .expect("AppObject is constructed with an app")
+    }
+
+    /// Whether any source of the app comes from `plugin`.
+    pub fn has_plugin(&self, plugin: &str) -> bool {
+        self.imp()
+            .app
+            .borrow()
+            .as_ref()
+            .is_some_and(|app| app.sources.iter().any(|source| source.plugin == plugin))
+    }
+}
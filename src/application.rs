@@ -24,6 +24,8 @@ mod imp {
     impl ObjectImpl for InstallGridApplication {
         fn signals() -> &'static [Signal] {
             static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+                // Emitted with a `RepositoryObject` for each repository a
+                // refresh found added, removed or changed.
                 vec![Signal::builder("repository-changed")
                     .param_types([Type::OBJECT])
                     .action()
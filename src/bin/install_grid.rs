@@ -1,67 +1,165 @@
-use install_grid::host::{AppStoreService, HostError, PluginHostBuilder};
-use install_grid::plugins::{LegacyPluginAdapter, NativeMockPlugin};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use clap::Parser;
+use install_grid::cache::{CacheStore, MemoryCacheStore, SqliteCacheStore};
+use install_grid::cli::{self, Cli, Command};
+use install_grid::host::{AppStoreService, PluginHostBuilder};
+use install_grid::helper::{self, OutOfProcessPlugin};
+use install_grid::plugins::{
+    AppStreamPlugin, DesktopPlugin, LegacyPluginAdapter, NativeMockPlugin, PluginBackend,
+};
 use install_grid::ui;
 
 fn main() {
-    let host = PluginHostBuilder::new()
-        .with_backend(LegacyPluginAdapter::new("flatpak"))
-        .with_backend(NativeMockPlugin::new("native::mock").with_delay(
-            std::time::Duration::from_millis(120),
-        ))
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.iter().any(|arg| arg == helper::HELPER_FLAG) {
+        run_helper(&args);
+    }
+    let cli = Cli::parse();
+
+    let legacy = LegacyPluginAdapter::new("flatpak");
+    let builder = if std::env::var_os("INSTALLGRID_PLUGIN_HELPERS").is_some() {
+        match OutOfProcessPlugin::for_current_exe(legacy.descriptor().clone()) {
+            Ok(isolated) => PluginHostBuilder::new().with_backend(isolated),
+            Err(err) => {
+                eprintln!("InstallGrid: cannot locate own executable for plugin helpers: {err}");
+                PluginHostBuilder::new().with_backend(legacy)
+            }
+        }
+    } else {
+        PluginHostBuilder::new().with_backend(legacy)
+    };
+
+    let mut builder = builder
+        .with_backend(appstream_backend())
+        .with_backend(desktop_backend());
+    if std::env::var_os("INSTALLGRID_MOCK_PLUGIN").is_some() {
+        builder = builder.with_backend(mock_backend());
+    }
+    if let Ok(priority) = std::env::var("INSTALLGRID_PLUGIN_PRIORITY") {
+        builder = builder.with_priority(
+            priority
+                .split(',')
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(str::to_owned),
+        );
+    }
+
+    let host = builder
+        .with_default_timeout(std::time::Duration::from_secs(10))
+        .with_timeout("legacy::flatpak", std::time::Duration::from_secs(60))
         .build()
         .expect("failed to initialise plugin host");
 
-    let service = AppStoreService::new(host);
+    let cache_path = SqliteCacheStore::default_path();
+    let (store, cache_path): (Arc<dyn CacheStore>, _) = match SqliteCacheStore::open(&cache_path) {
+        Ok(store) => (Arc::new(store), Some(cache_path)),
+        Err(err) => {
+            eprintln!(
+                "InstallGrid: unable to open cache at {}: {err}; falling back to memory",
+                cache_path.display()
+            );
+            (Arc::new(MemoryCacheStore::new()), None)
+        }
+    };
+
+    let mut service = AppStoreService::new(host, store);
+    if let Some(max_age) = std::env::var("INSTALLGRID_CACHE_MAX_AGE")
+        .ok()
+        .and_then(|secs| secs.trim().parse::<u64>().ok())
+    {
+        service = service.with_max_age(std::time::Duration::from_secs(max_age));
+    }
 
     let env_display =
         std::env::var_os("DISPLAY").is_some() || std::env::var_os("WAYLAND_DISPLAY").is_some();
     let force_headless = std::env::var_os("INSTALLGRID_HEADLESS").is_some();
     let display_available = env_display && !force_headless;
 
-    if !display_available {
-        eprintln!("InstallGrid: no DISPLAY/WAYLAND_DISPLAY found, running in headless mode");
-        let runtime = tokio::runtime::Builder::new_current_thread()
-            .enable_all()
-            .build()
-            .expect("failed to initialise headless runtime");
-
-        match runtime.block_on(service.refresh_popular()) {
-            Ok(outcome) => {
-                println!("Fetched {} applications:", outcome.apps.len());
-                for app in outcome.apps.iter() {
-                    println!("- {} ({}) :: {}", app.name, app.app_id, app.source);
-                }
-                if !outcome.warnings.is_empty() {
-                    eprintln!("Warnings:");
-                    for warning in outcome.warnings {
-                        eprintln!("  {}: {}", warning.plugin, warning.kind);
-                    }
-                }
-            }
-            Err(err) => {
-                eprintln!("Failed to refresh apps: {}", describe_host_error(err));
-                std::process::exit(1);
-            }
+    let command = match cli.command {
+        Some(command) => command,
+        None if display_available => {
+            let _exit = ui::run(service);
+            return;
+        }
+        None => {
+            eprintln!("InstallGrid: no DISPLAY/WAYLAND_DISPLAY found, running in headless mode");
+            Command::List { cached: false }
         }
-        return;
+    };
+
+    let status = cli::run(&service, command, cli.format, cache_path.as_deref());
+    std::process::exit(status.code());
+}
+
+/// Reads `INSTALLGRID_APPSTREAM_DIRS` (a `:`-separated path list) or the
+/// well-known catalogue locations.
+fn appstream_backend() -> AppStreamPlugin {
+    let plugin = AppStreamPlugin::new("native::appstream");
+    match std::env::var_os("INSTALLGRID_APPSTREAM_DIRS") {
+        Some(dirs) => plugin.with_dirs(std::env::split_paths(&dirs)),
+        None => plugin.with_dirs(AppStreamPlugin::default_dirs()),
     }
+}
 
-    let _exit = ui::run(service);
+/// Reads `INSTALLGRID_DESKTOP_DIRS` (a `:`-separated path list) or the XDG
+/// `applications` directories.
+fn desktop_backend() -> DesktopPlugin {
+    let plugin = DesktopPlugin::new("native::desktop");
+    match std::env::var_os("INSTALLGRID_DESKTOP_DIRS") {
+        Some(dirs) => plugin.with_dirs(std::env::split_paths(&dirs)),
+        None => plugin.with_dirs(DesktopPlugin::default_dirs()),
+    }
 }
 
-fn describe_host_error(err: HostError) -> String {
-    match err {
-        HostError::AllFailed(failures) => {
-            if failures.is_empty() {
-                "All plugins failed without detailed errors".to_string()
-            } else {
-                failures
-                    .into_iter()
-                    .map(|failure| format!("{}: {}", failure.plugin, failure.kind))
-                    .collect::<Vec<_>>()
-                    .join("\n")
-            }
+/// Canned apps for trying the UI without real backends; registered only when
+/// `INSTALLGRID_MOCK_PLUGIN` is set.
+fn mock_backend() -> NativeMockPlugin {
+    NativeMockPlugin::new("native::mock").with_delay(std::time::Duration::from_millis(120))
+}
+
+/// Serves a single backend for a parent InstallGrid process; see
+/// `install_grid::helper`.
+fn run_helper(args: &[String]) -> ! {
+    let value_of = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|index| args.get(index + 1))
+    };
+
+    let (Some(plugin_id), Some(socket)) =
+        (value_of(helper::HELPER_FLAG), value_of(helper::SOCKET_FLAG))
+    else {
+        eprintln!(
+            "usage: install_grid {} <plugin-id> {} <path>",
+            helper::HELPER_FLAG,
+            helper::SOCKET_FLAG
+        );
+        std::process::exit(2);
+    };
+
+    let backend: Arc<dyn PluginBackend> = match plugin_id.as_str() {
+        "legacy::flatpak" => Arc::new(LegacyPluginAdapter::new("flatpak")),
+        "native::appstream" => Arc::new(appstream_backend()),
+        "native::desktop" => Arc::new(desktop_backend()),
+        "native::mock" => Arc::new(mock_backend()),
+        other => {
+            eprintln!("InstallGrid helper: unknown plugin {other}");
+            std::process::exit(2);
+        }
+    };
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("failed to initialise helper runtime");
+    match runtime.block_on(helper::serve(backend, &PathBuf::from(socket))) {
+        Ok(()) => std::process::exit(0),
+        Err(err) => {
+            eprintln!("InstallGrid helper for {plugin_id}: {err}");
+            std::process::exit(1);
         }
-        HostError::RuntimeUnavailable => "Background runtime unavailable".to_string(),
     }
 }
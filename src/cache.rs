@@ -0,0 +1,618 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use parking_lot::{Mutex, RwLock};
+use rusqlite::{params, Connection, OptionalExtension};
+use thiserror::Error;
+
+use crate::plugins::{AppDetails, AppSummary, AppUpdate, MergedApp, PluginFailure, Repository};
+
+/// Bumped whenever `SCHEMA` changes incompatibly. Caches from any other
+/// version are dropped and rebuilt on the next refresh.
+const SCHEMA_VERSION: i64 = 1;
+
+const DROP_SCHEMA: &str = "
+DROP TABLE IF EXISTS apps;
+DROP TABLE IF EXISTS plugin_warnings;
+DROP TABLE IF EXISTS app_details;
+DROP TABLE IF EXISTS plugin_refreshes;
+DROP TABLE IF EXISTS repositories;
+DROP TABLE IF EXISTS app_updates;
+DROP TABLE IF EXISTS update_checks;
+";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS apps (
+    position INTEGER NOT NULL,
+    app_id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    source TEXT NOT NULL,
+    installed INTEGER NOT NULL DEFAULT 0,
+    sources TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS plugin_warnings (
+    position INTEGER PRIMARY KEY NOT NULL,
+    plugin TEXT NOT NULL,
+    failure TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS app_details (
+    app_id TEXT PRIMARY KEY NOT NULL,
+    details TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS plugin_refreshes (
+    plugin TEXT PRIMARY KEY NOT NULL,
+    refreshed_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS repositories (
+    position INTEGER NOT NULL,
+    plugin TEXT NOT NULL,
+    id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    url TEXT,
+    enabled INTEGER NOT NULL,
+    PRIMARY KEY (plugin, id)
+);
+CREATE TABLE IF NOT EXISTS app_updates (
+    position INTEGER NOT NULL,
+    plugin TEXT NOT NULL,
+    app_id TEXT NOT NULL,
+    update_json TEXT NOT NULL,
+    PRIMARY KEY (plugin, app_id)
+);
+CREATE TABLE IF NOT EXISTS update_checks (
+    id INTEGER PRIMARY KEY CHECK (id = 0),
+    checked_at INTEGER NOT NULL
+);
+";
+
+#[derive(Debug, Error)]
+pub enum CacheError {
+    #[error("cache database error: {0}")]
+    Database(#[from] rusqlite::Error),
+    #[error("cache serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("cache I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Storage backing the `AppStoreService` cache.
+///
+/// Calls are synchronous. The service writes through after every refresh
+/// from whichever thread polls it, usually the GTK main thread, so a slow
+/// disk stalls the UI for as long as a write takes.
+pub trait CacheStore: Send + Sync {
+    fn load_apps(&self) -> Result<Vec<MergedApp>, CacheError>;
+    fn store_apps(&self, apps: &[MergedApp]) -> Result<(), CacheError>;
+    fn load_warnings(&self) -> Result<Vec<PluginFailure>, CacheError>;
+    /// Replaces the stored warnings with the latest failure of each plugin.
+    fn store_warnings(&self, warnings: &[PluginFailure]) -> Result<(), CacheError>;
+    fn load_details(&self, app_id: &str) -> Result<Option<AppDetails>, CacheError>;
+    /// Inserts or replaces the details stored for `details.app.app_id`.
+    fn store_details(&self, details: &AppDetails) -> Result<(), CacheError>;
+    /// When each plugin's cached apps were fetched.
+    fn load_refresh_times(&self) -> Result<HashMap<String, SystemTime>, CacheError>;
+    /// Replaces every stored refresh time.
+    fn store_refresh_times(&self, times: &HashMap<String, SystemTime>) -> Result<(), CacheError>;
+    fn load_repositories(&self) -> Result<Vec<Repository>, CacheError>;
+    /// Replaces every stored repository.
+    fn store_repositories(&self, repositories: &[Repository]) -> Result<(), CacheError>;
+    /// Updates found by the last check, and when it ran; `None` if never.
+    fn load_updates(&self) -> Result<(Vec<AppUpdate>, Option<SystemTime>), CacheError>;
+    /// Replaces every stored update. `checked_at` is `None` to forget the
+    /// last check.
+    fn store_updates(
+        &self,
+        updates: &[AppUpdate],
+        checked_at: Option<SystemTime>,
+    ) -> Result<(), CacheError>;
+}
+
+/// Volatile store used when no persistent cache is configured.
+#[derive(Default)]
+pub struct MemoryCacheStore {
+    apps: RwLock<Vec<MergedApp>>,
+    warnings: RwLock<Vec<PluginFailure>>,
+    details: RwLock<HashMap<String, AppDetails>>,
+    refresh_times: RwLock<HashMap<String, SystemTime>>,
+    repositories: RwLock<Vec<Repository>>,
+    updates: RwLock<(Vec<AppUpdate>, Option<SystemTime>)>,
+}
+
+impl MemoryCacheStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CacheStore for MemoryCacheStore {
+    fn load_apps(&self) -> Result<Vec<MergedApp>, CacheError> {
+        Ok(self.apps.read().clone())
+    }
+
+    fn store_apps(&self, apps: &[MergedApp]) -> Result<(), CacheError> {
+        *self.apps.write() = apps.to_vec();
+        Ok(())
+    }
+
+    fn load_warnings(&self) -> Result<Vec<PluginFailure>, CacheError> {
+        Ok(self.warnings.read().clone())
+    }
+
+    fn store_warnings(&self, warnings: &[PluginFailure]) -> Result<(), CacheError> {
+        *self.warnings.write() = warnings.to_vec();
+        Ok(())
+    }
+
+    fn load_details(&self, app_id: &str) -> Result<Option<AppDetails>, CacheError> {
+        Ok(self.details.read().get(app_id).cloned())
+    }
+
+    fn store_details(&self, details: &AppDetails) -> Result<(), CacheError> {
+        self.details
+            .write()
+            .insert(details.app.app_id.clone(), details.clone());
+        Ok(())
+    }
+
+    fn load_refresh_times(&self) -> Result<HashMap<String, SystemTime>, CacheError> {
+        Ok(self.refresh_times.read().clone())
+    }
+
+    fn store_refresh_times(&self, times: &HashMap<String, SystemTime>) -> Result<(), CacheError> {
+        *self.refresh_times.write() = times.clone();
+        Ok(())
+    }
+
+    fn load_repositories(&self) -> Result<Vec<Repository>, CacheError> {
+        Ok(self.repositories.read().clone())
+    }
+
+    fn store_repositories(&self, repositories: &[Repository]) -> Result<(), CacheError> {
+        *self.repositories.write() = repositories.to_vec();
+        Ok(())
+    }
+
+    fn load_updates(&self) -> Result<(Vec<AppUpdate>, Option<SystemTime>), CacheError> {
+        Ok(self.updates.read().clone())
+    }
+
+    fn store_updates(
+        &self,
+        updates: &[AppUpdate],
+        checked_at: Option<SystemTime>,
+    ) -> Result<(), CacheError> {
+        *self.updates.write() = (updates.to_vec(), checked_at);
+        Ok(())
+    }
+}
+
+/// SQLite-backed store so the last refresh survives restarts.
+pub struct SqliteCacheStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteCacheStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, CacheError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, CacheError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    /// `$XDG_CACHE_HOME/install-grid/cache.sqlite3`.
+    pub fn default_path() -> PathBuf {
+        glib::user_cache_dir()
+            .join("install-grid")
+            .join("cache.sqlite3")
+    }
+
+    fn from_connection(conn: Connection) -> Result<Self, CacheError> {
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version != SCHEMA_VERSION {
+            conn.execute_batch(DROP_SCHEMA)?;
+            conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        }
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+impl CacheStore for SqliteCacheStore {
+    fn load_apps(&self) -> Result<Vec<MergedApp>, CacheError> {
+        let conn = self.conn.lock();
+        let mut statement = conn.prepare(
+            "SELECT app_id, name, summary, source, installed, sources FROM apps ORDER BY position",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                AppSummary {
+                    app_id: row.get(0)?,
+                    name: row.get(1)?,
+                    summary: row.get(2)?,
+                    source: row.get(3)?,
+                    installed: row.get(4)?,
+                },
+                row.get::<_, String>(5)?,
+            ))
+        })?;
+
+        let mut apps = Vec::new();
+        for row in rows {
+            let (primary, sources) = row?;
+            apps.push(MergedApp {
+                primary,
+                sources: serde_json::from_str(&sources)?,
+            });
+        }
+        Ok(apps)
+    }
+
+    fn store_apps(&self, apps: &[MergedApp]) -> Result<(), CacheError> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM apps", [])?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT OR REPLACE INTO apps
+                     (position, app_id, name, summary, source, installed, sources)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for (position, app) in apps.iter().enumerate() {
+                insert.execute(params![
+                    position as i64,
+                    app.primary.app_id,
+                    app.primary.name,
+                    app.primary.summary,
+                    app.primary.source,
+                    app.primary.installed,
+                    serde_json::to_string(&app.sources)?
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn load_warnings(&self) -> Result<Vec<PluginFailure>, CacheError> {
+        let conn = self.conn.lock();
+        let mut statement =
+            conn.prepare("SELECT failure FROM plugin_warnings ORDER BY position")?;
+        let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+
+        let mut warnings = Vec::new();
+        for row in rows {
+            warnings.push(serde_json::from_str(&row?)?);
+        }
+        Ok(warnings)
+    }
+
+    fn store_warnings(&self, warnings: &[PluginFailure]) -> Result<(), CacheError> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM plugin_warnings", [])?;
+        {
+            // A plugin can fail more than once in a refresh, so every
+            // warning gets its own row.
+            let mut insert = tx.prepare(
+                "INSERT INTO plugin_warnings (position, plugin, failure) VALUES (?1, ?2, ?3)",
+            )?;
+            for (position, warning) in warnings.iter().enumerate() {
+                insert.execute(params![
+                    position as i64,
+                    warning.plugin,
+                    serde_json::to_string(warning)?
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn load_details(&self, app_id: &str) -> Result<Option<AppDetails>, CacheError> {
+        let conn = self.conn.lock();
+        let details = conn
+            .query_row(
+                "SELECT details FROM app_details WHERE app_id = ?1",
+                params![app_id],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        Ok(details.map(|json| serde_json::from_str(&json)).transpose()?)
+    }
+
+    fn store_details(&self, details: &AppDetails) -> Result<(), CacheError> {
+        let conn = self.conn.lock();
+        conn.execute(
+            "INSERT OR REPLACE INTO app_details (app_id, details) VALUES (?1, ?2)",
+            params![details.app.app_id, serde_json::to_string(details)?],
+        )?;
+        Ok(())
+    }
+
+    fn load_refresh_times(&self) -> Result<HashMap<String, SystemTime>, CacheError> {
+        let conn = self.conn.lock();
+        let mut statement = conn.prepare("SELECT plugin, refreshed_at FROM plugin_refreshes")?;
+        let rows = statement.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?;
+
+        let mut times = HashMap::new();
+        for row in rows {
+            let (plugin, secs) = row?;
+            times.insert(plugin, UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64));
+        }
+        Ok(times)
+    }
+
+    fn store_refresh_times(&self, times: &HashMap<String, SystemTime>) -> Result<(), CacheError> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM plugin_refreshes", [])?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT OR REPLACE INTO plugin_refreshes (plugin, refreshed_at) VALUES (?1, ?2)",
+            )?;
+            for (plugin, at) in times {
+                let secs = at
+                    .duration_since(UNIX_EPOCH)
+                    .map(|since| since.as_secs() as i64)
+                    .unwrap_or(0);
+                insert.execute(params![plugin, secs])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn load_repositories(&self) -> Result<Vec<Repository>, CacheError> {
+        let conn = self.conn.lock();
+        let mut statement = conn.prepare(
+            "SELECT id, name, url, enabled, plugin FROM repositories ORDER BY position",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok(Repository {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                url: row.get(2)?,
+                enabled: row.get(3)?,
+                plugin: row.get(4)?,
+            })
+        })?;
+
+        let mut repositories = Vec::new();
+        for row in rows {
+            repositories.push(row?);
+        }
+        Ok(repositories)
+    }
+
+    fn store_repositories(&self, repositories: &[Repository]) -> Result<(), CacheError> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM repositories", [])?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT OR REPLACE INTO repositories (position, plugin, id, name, url, enabled)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for (position, repository) in repositories.iter().enumerate() {
+                insert.execute(params![
+                    position as i64,
+                    repository.plugin,
+                    repository.id,
+                    repository.name,
+                    repository.url,
+                    repository.enabled
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn load_updates(&self) -> Result<(Vec<AppUpdate>, Option<SystemTime>), CacheError> {
+        let conn = self.conn.lock();
+        let checked_at = conn
+            .query_row("SELECT checked_at FROM update_checks WHERE id = 0", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .optional()?
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64));
+
+        let mut statement =
+            conn.prepare("SELECT update_json FROM app_updates ORDER BY position")?;
+        let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+        let mut updates = Vec::new();
+        for row in rows {
+            updates.push(serde_json::from_str(&row?)?);
+        }
+        Ok((updates, checked_at))
+    }
+
+    fn store_updates(
+        &self,
+        updates: &[AppUpdate],
+        checked_at: Option<SystemTime>,
+    ) -> Result<(), CacheError> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM app_updates", [])?;
+        tx.execute("DELETE FROM update_checks", [])?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT OR REPLACE INTO app_updates (position, plugin, app_id, update_json)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (position, update) in updates.iter().enumerate() {
+                insert.execute(params![
+                    position as i64,
+                    update.plugin,
+                    update.app.app_id,
+                    serde_json::to_string(update)?
+                ])?;
+            }
+        }
+        if let Some(at) = checked_at {
+            let secs = at
+                .duration_since(UNIX_EPOCH)
+                .map(|since| since.as_secs() as i64)
+                .unwrap_or(0);
+            tx.execute(
+                "INSERT INTO update_checks (id, checked_at) VALUES (0, ?1)",
+                params![secs],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::{AppSource, PluginExecutionError, PluginFailureKind, PluginKind};
+
+    fn app(app_id: &str, plugins: &[&str]) -> MergedApp {
+        MergedApp {
+            primary: AppSummary {
+                app_id: app_id.to_string(),
+                name: format!("{app_id} name"),
+                summary: format!("{app_id} summary"),
+                source: format!("{}::source", plugins[0]),
+                installed: app_id.ends_with("Installed"),
+            },
+            sources: plugins
+                .iter()
+                .map(|plugin| AppSource {
+                    plugin: plugin.to_string(),
+                    source: format!("{plugin}::source"),
+                })
+                .collect(),
+        }
+    }
+
+    fn warning(plugin: &str, kind: PluginFailureKind) -> PluginFailure {
+        PluginFailure {
+            plugin: plugin.to_string(),
+            kind,
+            plugin_kind: PluginKind::Native,
+        }
+    }
+
+    fn details(app_id: &str, version: &str) -> AppDetails {
+        AppDetails {
+            description: Some(format!("<p>{app_id} description</p>")),
+            version: Some(version.to_string()),
+            license: Some("GPL-3.0-or-later".to_string()),
+            screenshots: vec!["https://example.org/1.png".to_string()],
+            download_size: Some(1024),
+            ..AppDetails::from_summary(app(app_id, &["native::first"]).primary)
+        }
+    }
+
+    fn loaded_details(store: &dyn CacheStore, app_id: &str) -> Option<serde_json::Value> {
+        let details = store.load_details(app_id).unwrap()?;
+        Some(serde_json::to_value(details).unwrap())
+    }
+
+    fn assert_round_trips(store: &dyn CacheStore) {
+        let apps = vec![
+            app("org.example.Zed", &["native::first", "native::second"]),
+            app("org.example.Installed", &["native::second"]),
+        ];
+        let warnings = vec![
+            warning(
+                "native::first",
+                PluginExecutionError::Operation("listing failed".to_string()).into(),
+            ),
+            warning("native::first", PluginFailureKind::Panic),
+            warning("legacy::flatpak", PluginFailureKind::Quarantined),
+        ];
+        let times = HashMap::from([
+            ("native::first".to_string(), UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            ("native::second".to_string(), UNIX_EPOCH + Duration::from_secs(1_700_000_600)),
+        ]);
+        let zed = details("org.example.Zed", "1.0");
+
+        assert!(store.load_details("org.example.Zed").unwrap().is_none());
+        store.store_apps(&apps).unwrap();
+        store.store_warnings(&warnings).unwrap();
+        store.store_refresh_times(&times).unwrap();
+        store.store_details(&zed).unwrap();
+        assert_eq!(store.load_apps().unwrap(), apps);
+        assert_eq!(store.load_warnings().unwrap(), warnings);
+        assert_eq!(store.load_refresh_times().unwrap(), times);
+        assert_eq!(
+            loaded_details(store, "org.example.Zed"),
+            Some(serde_json::to_value(&zed).unwrap())
+        );
+
+        // Storing again replaces rather than appends.
+        let newer = details("org.example.Zed", "2.0");
+        store.store_apps(&apps[1..]).unwrap();
+        store.store_warnings(&[]).unwrap();
+        store.store_refresh_times(&HashMap::new()).unwrap();
+        store.store_details(&newer).unwrap();
+        assert_eq!(store.load_apps().unwrap(), apps[1..]);
+        assert!(store.load_warnings().unwrap().is_empty());
+        assert!(store.load_refresh_times().unwrap().is_empty());
+        assert_eq!(
+            loaded_details(store, "org.example.Zed"),
+            Some(serde_json::to_value(&newer).unwrap())
+        );
+        assert!(store.load_details("org.example.Installed").unwrap().is_none());
+    }
+
+    #[test]
+    fn memory_store_round_trips() {
+        assert_round_trips(&MemoryCacheStore::new());
+    }
+
+    #[test]
+    fn sqlite_store_round_trips() {
+        assert_round_trips(&SqliteCacheStore::open_in_memory().unwrap());
+    }
+
+    #[test]
+    fn caches_from_another_schema_version_are_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.sqlite3");
+        let apps = vec![app("org.example.App", &["native::first"])];
+
+        let store = SqliteCacheStore::open(&path).unwrap();
+        store.store_apps(&apps).unwrap();
+        drop(store);
+        assert_eq!(SqliteCacheStore::open(&path).unwrap().load_apps().unwrap(), apps);
+
+        // A cache from before versioning whose tables no longer match the schema.
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "DROP TABLE plugin_warnings;
+             CREATE TABLE plugin_warnings (plugin TEXT PRIMARY KEY NOT NULL, failure TEXT NOT NULL);",
+        )
+        .unwrap();
+        conn.pragma_update(None, "user_version", 0).unwrap();
+        drop(conn);
+
+        let store = SqliteCacheStore::open(&path).unwrap();
+        assert!(store.load_apps().unwrap().is_empty());
+        let warnings = vec![warning("native::first", PluginFailureKind::Panic)];
+        store.store_warnings(&warnings).unwrap();
+        assert_eq!(store.load_warnings().unwrap(), warnings);
+        let version: i64 = store
+            .conn
+            .lock()
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+    }
+}
@@ -0,0 +1,116 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+
+/// Cloneable token used to abort an in-flight host operation.
+///
+/// Every clone observes the same state, so the UI can keep one copy while the
+/// host hands others to each backend. Native backends await
+/// [`CancellationHandle::cancelled`]; the legacy bridge forwards it to a
+/// `GCancellable`.
+#[derive(Clone, Default)]
+pub struct CancellationHandle {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        if !self.inner.cancelled.swap(true, Ordering::SeqCst) {
+            self.inner.notify.notify_waiters();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once [`CancellationHandle::cancel`] has been called on any clone.
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.inner.notify.notified();
+            tokio::pin!(notified);
+            // Register before re-checking the flag so a concurrent `cancel`
+            // cannot slip between the check and the await.
+            notified.as_mut().enable();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+impl std::fmt::Debug for CancellationHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CancellationHandle")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use futures::task::noop_waker;
+    use futures::FutureExt;
+
+    use super::*;
+
+    #[test]
+    fn every_clone_sees_the_cancel() {
+        let handle = CancellationHandle::new();
+        let clone = handle.clone();
+        assert!(!clone.is_cancelled());
+        handle.cancel();
+        handle.cancel();
+        assert!(clone.is_cancelled());
+        assert!(clone.cancelled().now_or_never().is_some());
+    }
+
+    #[test]
+    fn waiter_registered_before_the_cancel_is_woken() {
+        let handle = CancellationHandle::new();
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut cancelled = Box::pin(handle.cancelled());
+        assert!(cancelled.as_mut().poll(&mut cx).is_pending());
+
+        handle.cancel();
+        assert_eq!(cancelled.as_mut().poll(&mut cx), Poll::Ready(()));
+    }
+
+    #[test]
+    fn cancel_racing_the_check_is_not_lost() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_time()
+            .build()
+            .unwrap();
+        for _ in 0..1000 {
+            let handle = CancellationHandle::new();
+            let waiter = runtime.spawn({
+                let handle = handle.clone();
+                async move { handle.cancelled().await }
+            });
+            // Lands before, during or after the waiter's check of the flag.
+            handle.cancel();
+            let woken = runtime
+                .block_on(async { tokio::time::timeout(Duration::from_secs(5), waiter).await });
+            assert!(woken.is_ok(), "cancellation was lost");
+        }
+    }
+}
@@ -0,0 +1,807 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+
+use crate::cancellation::CancellationHandle;
+use crate::host::{AppStoreService, HostError, RefreshOutcome, RepositoryListing, SearchOutcome};
+use crate::plugins::{AppDetails, MergedApp, PluginFailure, RepositoryAction};
+use crate::registry::PluginHealth;
+
+#[derive(Debug, Parser)]
+#[command(name = "install_grid", about = "InstallGrid app store")]
+pub struct Cli {
+    /// Output format for headless commands.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table, global = true)]
+    pub format: OutputFormat,
+    /// Runs headless; without a subcommand the GTK window is opened when a
+    /// display is available.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Pretty-printed JSON on stdout.
+    Json,
+    /// Aligned columns with a header row.
+    Table,
+    /// Tab-separated values without a header.
+    Plain,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// List popular apps, refreshing them from every plugin once the cache
+    /// is older than the max age.
+    List {
+        /// Print the cached list without querying plugins.
+        #[arg(long)]
+        cached: bool,
+    },
+    /// Search every plugin.
+    Search {
+        #[arg(required = true)]
+        query: Vec<String>,
+    },
+    /// Show full details for one app.
+    Show { app_id: String },
+    /// Report plugin health.
+    Plugins {
+        /// Refresh once first so the report reflects a live call.
+        #[arg(long)]
+        probe: bool,
+    },
+    /// Inspect or clear the local cache.
+    Cache {
+        #[arg(long)]
+        clear: bool,
+    },
+    /// Refresh popular apps and report what each plugin returned.
+    Refresh,
+    /// List or manage software repositories.
+    Repos {
+        #[command(subcommand)]
+        command: RepoCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RepoCommand {
+    /// List the repositories every plugin reports.
+    List,
+    /// Add the repository described by a `.flatpakrepo` file.
+    Add {
+        file: PathBuf,
+        /// Plugin to add it through; defaults to the first that supports it.
+        #[arg(long)]
+        plugin: Option<String>,
+    },
+    Enable(RepoTarget),
+    Disable(RepoTarget),
+    Remove(RepoTarget),
+}
+
+#[derive(Debug, Args)]
+pub struct RepoTarget {
+    id: String,
+    /// Plugin that lists the repository, when several list the same id.
+    #[arg(long)]
+    plugin: Option<String>,
+}
+
+/// Process exit codes of headless commands. Scripts rely on these values,
+/// and on the JSON output, staying stable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ExitStatus {
+    Success = 0,
+    /// Every plugin failed (`HostError::AllFailed`).
+    AllFailed = 1,
+    /// Invalid command line (reported by the argument parser).
+    Usage = 2,
+    /// `HostError::RuntimeUnavailable`.
+    RuntimeUnavailable = 3,
+    /// The command succeeded but at least one plugin reported a warning.
+    PartialFailure = 4,
+    Cancelled = 5,
+    /// The requested app, source, plugin or repository is not known.
+    NotFound = 6,
+    /// The single plugin the request was routed to failed.
+    PluginFailed = 7,
+    /// The local cache could not be read or written.
+    CacheError = 8,
+    /// No plugin implements the requested operation.
+    Unsupported = 9,
+}
+
+impl ExitStatus {
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+
+    fn for_warnings(warnings: &[PluginFailure]) -> Self {
+        if warnings.is_empty() {
+            ExitStatus::Success
+        } else {
+            ExitStatus::PartialFailure
+        }
+    }
+
+    fn for_error(err: &HostError) -> Self {
+        match err {
+            HostError::AllFailed(_) => ExitStatus::AllFailed,
+            HostError::RuntimeUnavailable => ExitStatus::RuntimeUnavailable,
+            HostError::Cancelled => ExitStatus::Cancelled,
+            HostError::UnknownApp(_)
+            | HostError::UnknownPlugin(_)
+            | HostError::UnknownRepository(_) => ExitStatus::NotFound,
+            HostError::Unsupported => ExitStatus::Unsupported,
+            HostError::Plugin(_) => ExitStatus::PluginFailed,
+        }
+    }
+}
+
+/// Runs `command` to completion on a private runtime.
+pub fn run(
+    service: &AppStoreService,
+    command: Command,
+    format: OutputFormat,
+    cache_path: Option<&Path>,
+) -> ExitStatus {
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(err) => {
+            eprintln!("InstallGrid: failed to initialise headless runtime: {err}");
+            return ExitStatus::RuntimeUnavailable;
+        }
+    };
+    let cancel = CancellationHandle::new();
+
+    let result = match command {
+        Command::List { cached: true } => {
+            let outcome = service.cache_snapshot();
+            print_apps(format, &outcome);
+            Ok(ExitStatus::Success)
+        }
+        Command::List { cached: false } => runtime
+            .block_on(service.load_popular(&cancel))
+            .map(|outcome| {
+                print_apps(format, &outcome);
+                ExitStatus::for_warnings(&outcome.warnings)
+            }),
+        Command::Search { query } => runtime
+            .block_on(service.search(&query.join(" "), &cancel))
+            .map(|outcome| {
+                print_search(format, &outcome);
+                ExitStatus::for_warnings(&outcome.warnings)
+            }),
+        Command::Show { app_id } => runtime
+            .block_on(async {
+                // Plugins failing the refresh for a cache miss count like
+                // they would for `list`.
+                let warnings = match service.cached_app(&app_id) {
+                    Some(_) => Vec::new(),
+                    None => service.refresh_popular(&cancel).await?.warnings,
+                };
+                let details = service.app_details(&app_id, &cancel).await?;
+                Ok((details, warnings))
+            })
+            .map(|(details, warnings)| {
+                print_details(format, &details);
+                ExitStatus::for_warnings(&warnings)
+            }),
+        Command::Plugins { probe } => {
+            let refreshed = if probe {
+                Some(runtime.block_on(service.refresh_popular(&cancel)))
+            } else {
+                None
+            };
+            let health = service.plugin_health();
+            print_health(format, &health);
+            match refreshed {
+                Some(Ok(outcome)) => Ok(ExitStatus::for_warnings(&outcome.warnings)),
+                Some(Err(err)) => Err(err),
+                None => Ok(ExitStatus::Success),
+            }
+        }
+        Command::Cache { clear: true } => {
+            return match service.clear_cache() {
+                Ok(()) => {
+                    print_cache(format, service, cache_path);
+                    ExitStatus::Success
+                }
+                Err(err) => {
+                    eprintln!("InstallGrid: failed to clear the cache: {err}");
+                    ExitStatus::CacheError
+                }
+            };
+        }
+        Command::Cache { clear: false } => {
+            print_cache(format, service, cache_path);
+            Ok(ExitStatus::Success)
+        }
+        Command::Refresh => runtime
+            .block_on(service.refresh_popular(&cancel))
+            .map(|outcome| {
+                print_refresh(format, &outcome);
+                ExitStatus::for_warnings(&outcome.warnings)
+            }),
+        Command::Repos { command } => runtime.block_on(run_repos(service, command, format, &cancel)),
+    };
+
+    match result {
+        Ok(status) => status,
+        Err(err) => {
+            let status = ExitStatus::for_error(&err);
+            print_error(format, err);
+            status
+        }
+    }
+}
+
+async fn run_repos(
+    service: &AppStoreService,
+    command: RepoCommand,
+    format: OutputFormat,
+    cancel: &CancellationHandle,
+) -> Result<ExitStatus, HostError> {
+    let (target, action) = match command {
+        RepoCommand::List => {
+            let listing = service.refresh_repositories(cancel).await?;
+            print_repositories(format, &listing);
+            return Ok(ExitStatus::for_warnings(&listing.warnings));
+        }
+        RepoCommand::Add { file, plugin } => {
+            let repository = service
+                .add_repository(&file, plugin.as_deref(), cancel)
+                .await?;
+            print_repositories(
+                format,
+                &RepositoryListing {
+                    repositories: vec![repository],
+                    warnings: Vec::new(),
+                },
+            );
+            return Ok(ExitStatus::Success);
+        }
+        RepoCommand::Enable(target) => (target, RepositoryAction::Enable),
+        RepoCommand::Disable(target) => (target, RepositoryAction::Disable),
+        RepoCommand::Remove(target) => (target, RepositoryAction::Remove),
+    };
+
+    service.refresh_repositories(cancel).await?;
+    let repository = service.find_repository(&target.id, target.plugin.as_deref())?;
+    service.manage_repository(&repository, action, cancel).await?;
+    let repositories = match action {
+        RepositoryAction::Remove => Vec::new(),
+        _ => vec![service.find_repository(&repository.id, Some(&repository.plugin))?],
+    };
+    print_repositories(
+        format,
+        &RepositoryListing {
+            repositories,
+            warnings: Vec::new(),
+        },
+    );
+    Ok(ExitStatus::Success)
+}
+
+pub fn describe_host_error(err: &HostError) -> String {
+    match err {
+        HostError::AllFailed(failures) => {
+            if failures.is_empty() {
+                "All plugins failed without detailed errors".to_string()
+            } else {
+                failures
+                    .iter()
+                    .map(|failure| format!("{}: {}", failure.plugin, failure.kind))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        }
+        HostError::RuntimeUnavailable => "Background runtime unavailable".to_string(),
+        HostError::Cancelled => "Operation cancelled".to_string(),
+        HostError::UnknownApp(app_id) => format!("Unknown application {app_id}"),
+        HostError::UnknownPlugin(plugin) => format!("Unknown plugin {plugin}"),
+        HostError::UnknownRepository(id) => format!("Unknown repository {id}"),
+        HostError::Unsupported => "No plugin supports this operation".to_string(),
+        HostError::Plugin(failure) => format!("{}: {}", failure.plugin, failure.kind),
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorReport {
+    error: String,
+    failures: Vec<PluginFailure>,
+}
+
+fn print_error(format: OutputFormat, err: HostError) {
+    let message = describe_host_error(&err);
+    match format {
+        OutputFormat::Json => {
+            let failures = match err {
+                HostError::AllFailed(failures) => failures,
+                HostError::Plugin(failure) => vec![failure],
+                _ => Vec::new(),
+            };
+            print_json(&ErrorReport {
+                error: message,
+                failures,
+            });
+        }
+        OutputFormat::Table | OutputFormat::Plain => eprintln!("InstallGrid: {message}"),
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{json}"),
+        Err(err) => eprintln!("InstallGrid: failed to serialize output: {err}"),
+    }
+}
+
+fn print_apps(format: OutputFormat, outcome: &RefreshOutcome) {
+    match format {
+        OutputFormat::Json => print_json(outcome),
+        _ => {
+            print_rows(format, &["APP ID", "NAME", "INSTALLED", "SOURCES"], app_rows(&outcome.apps));
+            print_warnings(&outcome.warnings);
+        }
+    }
+}
+
+fn print_search(format: OutputFormat, outcome: &SearchOutcome) {
+    match format {
+        OutputFormat::Json => print_json(outcome),
+        _ => {
+            print_rows(format, &["APP ID", "NAME", "INSTALLED", "SOURCES"], app_rows(&outcome.apps));
+            print_warnings(&outcome.warnings);
+        }
+    }
+}
+
+fn print_refresh(format: OutputFormat, outcome: &RefreshOutcome) {
+    if format == OutputFormat::Json {
+        print_json(outcome);
+        return;
+    }
+
+    let mut per_plugin: Vec<(String, usize)> = Vec::new();
+    for source in outcome.apps.iter().flat_map(|app| &app.sources) {
+        match per_plugin.iter_mut().find(|(plugin, _)| *plugin == source.plugin) {
+            Some((_, count)) => *count += 1,
+            None => per_plugin.push((source.plugin.clone(), 1)),
+        }
+    }
+
+    let mut rows = per_plugin
+        .into_iter()
+        .map(|(plugin, count)| vec![plugin, "ok".to_string(), count.to_string()])
+        .collect::<Vec<_>>();
+    rows.extend(outcome.warnings.iter().map(|failure| {
+        vec![
+            failure.plugin.clone(),
+            failure.kind.to_string(),
+            "0".to_string(),
+        ]
+    }));
+    print_rows(format, &["PLUGIN", "STATUS", "APPS"], rows);
+}
+
+fn print_details(format: OutputFormat, details: &AppDetails) {
+    if format == OutputFormat::Json {
+        print_json(details);
+        return;
+    }
+
+    let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+    let size = |value: Option<u64>| value.map(|bytes| bytes.to_string()).unwrap_or_default();
+    let rows = vec![
+        vec!["app_id".to_string(), details.app.app_id.clone()],
+        vec!["name".to_string(), details.app.name.clone()],
+        vec!["summary".to_string(), details.app.summary.clone()],
+        vec!["source".to_string(), details.app.source.clone()],
+        vec!["version".to_string(), optional(&details.version)],
+        vec!["license".to_string(), optional(&details.license)],
+        vec!["developer".to_string(), optional(&details.developer)],
+        vec!["homepage".to_string(), optional(&details.homepage)],
+        vec!["icon".to_string(), optional(&details.icon)],
+        vec!["categories".to_string(), details.categories.join(", ")],
+        vec!["screenshots".to_string(), details.screenshots.join(" ")],
+        vec![
+            "install_state".to_string(),
+            format!("{:?}", details.install_state),
+        ],
+        vec!["download_size".to_string(), size(details.download_size)],
+        vec!["installed_size".to_string(), size(details.installed_size)],
+        vec![
+            "content_rating".to_string(),
+            details
+                .content_rating
+                .map(|age| format!("{age}+"))
+                .unwrap_or_default(),
+        ],
+        vec!["description".to_string(), optional(&details.description)],
+    ];
+    print_rows(format, &["FIELD", "VALUE"], rows);
+}
+
+fn print_health(format: OutputFormat, health: &[PluginHealth]) {
+    if format == OutputFormat::Json {
+        print_json(&health);
+        return;
+    }
+
+    let rows = health
+        .iter()
+        .map(|entry| {
+            vec![
+                entry.plugin.clone(),
+                format!("{:?}", entry.plugin_kind),
+                format!("{:?}", entry.state),
+                entry.successes.to_string(),
+                entry.failures.to_string(),
+                entry
+                    .last_error
+                    .as_ref()
+                    .map(ToString::to_string)
+                    .unwrap_or_default(),
+            ]
+        })
+        .collect();
+    print_rows(
+        format,
+        &["PLUGIN", "KIND", "STATE", "OK", "FAILED", "LAST ERROR"],
+        rows,
+    );
+}
+
+fn print_repositories(format: OutputFormat, listing: &RepositoryListing) {
+    if format == OutputFormat::Json {
+        print_json(listing);
+        return;
+    }
+
+    let rows = listing
+        .repositories
+        .iter()
+        .map(|repository| {
+            vec![
+                repository.id.clone(),
+                repository.name.clone(),
+                repository.enabled.to_string(),
+                repository.plugin.clone(),
+                repository.url.clone().unwrap_or_default(),
+            ]
+        })
+        .collect();
+    print_rows(format, &["ID", "NAME", "ENABLED", "PLUGIN", "URL"], rows);
+    print_warnings(&listing.warnings);
+}
+
+#[derive(Serialize)]
+struct CacheReport<'a> {
+    path: Option<&'a Path>,
+    /// Seconds.
+    max_age: u64,
+    /// Unix time, in seconds, at which each plugin's apps were fetched.
+    refreshed: BTreeMap<String, u64>,
+    #[serde(flatten)]
+    outcome: &'a RefreshOutcome,
+}
+
+fn print_cache(format: OutputFormat, service: &AppStoreService, path: Option<&Path>) {
+    let outcome = service.cache_snapshot();
+    let refreshed = service
+        .refresh_times()
+        .into_iter()
+        .map(|(plugin, at)| {
+            let secs = at
+                .duration_since(UNIX_EPOCH)
+                .map(|since| since.as_secs())
+                .unwrap_or(0);
+            (plugin, secs)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    if format == OutputFormat::Json {
+        print_json(&CacheReport {
+            path,
+            max_age: service.max_age().as_secs(),
+            refreshed,
+            outcome: &outcome,
+        });
+        return;
+    }
+
+    let path = path
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|| "(memory)".to_string());
+    let mut rows = vec![
+        vec!["path".to_string(), path],
+        vec!["apps".to_string(), outcome.apps.len().to_string()],
+        vec!["warnings".to_string(), outcome.warnings.len().to_string()],
+        vec![
+            "age".to_string(),
+            outcome
+                .cache_age
+                .map(|age| format!("{}s", age.as_secs()))
+                .unwrap_or_else(|| "never refreshed".to_string()),
+        ],
+        vec![
+            "max_age".to_string(),
+            format!("{}s", service.max_age().as_secs()),
+        ],
+    ];
+    rows.extend(
+        refreshed
+            .into_iter()
+            .map(|(plugin, secs)| vec![format!("refreshed[{plugin}]"), secs.to_string()]),
+    );
+    print_rows(format, &["FIELD", "VALUE"], rows);
+    print_warnings(&outcome.warnings);
+}
+
+fn app_rows(apps: &[MergedApp]) -> Vec<Vec<String>> {
+    apps.iter()
+        .map(|app| {
+            let sources = app
+                .sources
+                .iter()
+                .map(|source| source.source.as_str())
+                .collect::<Vec<_>>()
+                .join(",");
+            vec![
+                app.primary.app_id.clone(),
+                app.primary.name.clone(),
+                if app.primary.installed { "yes" } else { "no" }.to_string(),
+                sources,
+            ]
+        })
+        .collect()
+}
+
+fn print_warnings(warnings: &[PluginFailure]) {
+    for warning in warnings {
+        eprintln!("InstallGrid: warning: {}: {}", warning.plugin, warning.kind);
+    }
+}
+
+fn print_rows(format: OutputFormat, headers: &[&str], rows: Vec<Vec<String>>) {
+    // Keep one record per line so `plain` output stays splittable.
+    let rows = rows
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|cell| cell.replace(['\n', '\t'], " "))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    if format == OutputFormat::Plain {
+        for row in rows {
+            println!("{}", row.join("\t"));
+        }
+        return;
+    }
+
+    let mut widths = headers
+        .iter()
+        .map(|header| header.chars().count())
+        .collect::<Vec<_>>();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: Vec<&str>| {
+        let padded = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>();
+        println!("{}", padded.join("  ").trim_end());
+    };
+    line(headers.to_vec());
+    for row in &rows {
+        line(row.iter().map(String::as_str).collect());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::cache::MemoryCacheStore;
+    use crate::host::PluginHostBuilder;
+    use crate::plugins::{
+        AppSource, AppSummary, NativeMockPlugin, PluginExecutionError, PluginFailureKind,
+        PluginKind, Repository,
+    };
+
+    fn service(plugins: &[&str]) -> AppStoreService {
+        let host = plugins
+            .iter()
+            .fold(PluginHostBuilder::new(), |builder, plugin| {
+                builder.with_backend(NativeMockPlugin::new(*plugin).with_delay(Duration::ZERO))
+            })
+            .build()
+            .unwrap();
+        AppStoreService::new(host, Arc::new(MemoryCacheStore::new()))
+    }
+
+    fn failure(kind: PluginFailureKind) -> PluginFailure {
+        PluginFailure {
+            plugin: "native::broken".to_string(),
+            kind,
+            plugin_kind: PluginKind::Native,
+        }
+    }
+
+    #[test]
+    fn exit_codes_are_stable() {
+        let codes = [
+            (ExitStatus::Success, 0),
+            (ExitStatus::AllFailed, 1),
+            (ExitStatus::Usage, 2),
+            (ExitStatus::RuntimeUnavailable, 3),
+            (ExitStatus::PartialFailure, 4),
+            (ExitStatus::Cancelled, 5),
+            (ExitStatus::NotFound, 6),
+            (ExitStatus::PluginFailed, 7),
+            (ExitStatus::CacheError, 8),
+            (ExitStatus::Unsupported, 9),
+        ];
+        for (status, code) in codes {
+            assert_eq!(status.code(), code, "{status:?}");
+        }
+    }
+
+    #[test]
+    fn host_errors_map_to_exit_statuses() {
+        let plugin_failure = failure(PluginFailureKind::Panic);
+        let cases = [
+            (HostError::AllFailed(vec![plugin_failure.clone()]), ExitStatus::AllFailed),
+            (HostError::RuntimeUnavailable, ExitStatus::RuntimeUnavailable),
+            (HostError::Cancelled, ExitStatus::Cancelled),
+            (HostError::UnknownApp("org.example.App".to_string()), ExitStatus::NotFound),
+            (HostError::UnknownPlugin("native::x".to_string()), ExitStatus::NotFound),
+            (HostError::UnknownRepository("flathub".to_string()), ExitStatus::NotFound),
+            (HostError::Unsupported, ExitStatus::Unsupported),
+            (HostError::Plugin(plugin_failure), ExitStatus::PluginFailed),
+        ];
+        for (err, status) in cases {
+            assert_eq!(ExitStatus::for_error(&err), status, "{err:?}");
+        }
+        assert_eq!(ExitStatus::for_warnings(&[]), ExitStatus::Success);
+        assert_eq!(
+            ExitStatus::for_warnings(&[failure(PluginFailureKind::Disabled)]),
+            ExitStatus::PartialFailure
+        );
+    }
+
+    #[test]
+    fn show_reports_warnings_of_the_refresh_for_a_cache_miss() {
+        let service = service(&["native::first", "native::second"]);
+        service.set_plugin_enabled("native::second", false).unwrap();
+        let show = |app_id: &str| Command::Show {
+            app_id: app_id.to_string(),
+        };
+
+        let status = run(&service, show("org.gnome.Fractal"), OutputFormat::Plain, None);
+        assert_eq!(status, ExitStatus::PartialFailure);
+        // Served from the cache, so no plugin was asked to refresh.
+        let status = run(&service, show("org.gnome.Fractal"), OutputFormat::Plain, None);
+        assert_eq!(status, ExitStatus::Success);
+
+        let status = run(&service, show("org.example.Missing"), OutputFormat::Plain, None);
+        assert_eq!(status, ExitStatus::NotFound);
+    }
+
+    #[test]
+    fn json_shapes_are_stable() {
+        let warning = failure(PluginFailureKind::Execution(PluginExecutionError::Operation(
+            "no network".to_string(),
+        )));
+        let outcome = RefreshOutcome {
+            apps: vec![MergedApp {
+                primary: AppSummary {
+                    app_id: "org.example.App".to_string(),
+                    name: "App".to_string(),
+                    summary: "An app".to_string(),
+                    source: "flathub".to_string(),
+                    installed: true,
+                },
+                sources: vec![AppSource {
+                    plugin: "native::first".to_string(),
+                    source: "flathub".to_string(),
+                }],
+            }],
+            warnings: vec![warning.clone()],
+            cache_age: Some(Duration::from_millis(90_500)),
+        };
+        assert_eq!(
+            serde_json::to_value(&outcome).unwrap(),
+            json!({
+                "apps": [{
+                    "primary": {
+                        "app_id": "org.example.App",
+                        "name": "App",
+                        "summary": "An app",
+                        "source": "flathub",
+                        "installed": true,
+                    },
+                    "sources": [{ "plugin": "native::first", "source": "flathub" }],
+                }],
+                "warnings": [{
+                    "plugin": "native::broken",
+                    "kind": { "Execution": { "Operation": "no network" } },
+                    "plugin_kind": "Native",
+                }],
+                "cache_age": 90,
+            })
+        );
+
+        let search = SearchOutcome {
+            query: "app".to_string(),
+            apps: outcome.apps.clone(),
+            warnings: Vec::new(),
+        };
+        let mut expected = serde_json::to_value(&outcome).unwrap();
+        expected["query"] = json!("app");
+        expected["warnings"] = json!([]);
+        expected.as_object_mut().unwrap().remove("cache_age");
+        assert_eq!(serde_json::to_value(&search).unwrap(), expected);
+
+        let listing = RepositoryListing {
+            repositories: vec![Repository {
+                id: "flathub".to_string(),
+                name: "Flathub".to_string(),
+                url: None,
+                enabled: false,
+                plugin: "native::first".to_string(),
+            }],
+            warnings: Vec::new(),
+        };
+        assert_eq!(
+            serde_json::to_value(&listing).unwrap(),
+            json!({
+                "repositories": [{
+                    "id": "flathub",
+                    "name": "Flathub",
+                    "url": null,
+                    "enabled": false,
+                    "plugin": "native::first",
+                }],
+                "warnings": [],
+            })
+        );
+
+        let report = ErrorReport {
+            error: "native::broken: panic in plugin".to_string(),
+            failures: vec![failure(PluginFailureKind::Panic)],
+        };
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            json!({
+                "error": "native::broken: panic in plugin",
+                "failures": [{
+                    "plugin": "native::broken",
+                    "kind": "Panic",
+                    "plugin_kind": "Native",
+                }],
+            })
+        );
+    }
+}
@@ -48,6 +48,11 @@ pub struct GsApp {
     _data: [u8; 0],
 }
 
+#[repr(C)]
+pub struct GsCategory {
+    _data: [u8; 0],
+}
+
 #[cfg(feature = "legacy-ffi")]
 pub const GS_APP_QUERY_TRISTATE_TRUE: c_int = 1;
 #[cfg(feature = "legacy-ffi")]
@@ -57,19 +62,75 @@ pub const GS_PLUGIN_LIST_APPS_FLAGS_NONE: c_uint = 0;
 #[cfg(feature = "legacy-ffi")]
 pub const GS_PLUGIN_LIST_APPS_FLAGS_INTERACTIVE: c_uint = 1 << 0;
 #[cfg(feature = "legacy-ffi")]
+pub const GS_PLUGIN_LIST_CATEGORIES_FLAGS_NONE: c_uint = 0;
+#[cfg(feature = "legacy-ffi")]
 pub const GS_PLUGIN_REFINE_FLAGS_NONE: c_uint = 0;
 #[cfg(feature = "legacy-ffi")]
+pub const GS_PLUGIN_REFINE_FLAGS_REQUIRE_LICENSE: c_uint = 1 << 1;
+#[cfg(feature = "legacy-ffi")]
+pub const GS_PLUGIN_REFINE_FLAGS_REQUIRE_URL: c_uint = 1 << 2;
+#[cfg(feature = "legacy-ffi")]
 pub const GS_PLUGIN_REFINE_FLAGS_REQUIRE_DESCRIPTION: c_uint = 1 << 3;
 #[cfg(feature = "legacy-ffi")]
+pub const GS_PLUGIN_REFINE_FLAGS_REQUIRE_SIZE: c_uint = 1 << 4;
+#[cfg(feature = "legacy-ffi")]
+pub const GS_PLUGIN_REFINE_FLAGS_REQUIRE_VERSION: c_uint = 1 << 6;
+#[cfg(feature = "legacy-ffi")]
 pub const GS_PLUGIN_REFINE_FLAGS_REQUIRE_ICON: c_uint = 1 << 21;
 #[cfg(feature = "legacy-ffi")]
 pub const GS_PLUGIN_REFINE_FLAGS_REQUIRE_RATING: c_uint = 1 << 5;
 #[cfg(feature = "legacy-ffi")]
 pub const GS_PLUGIN_REFINE_FLAGS_REQUIRE_CATEGORIES: c_uint = 1 << 27;
 #[cfg(feature = "legacy-ffi")]
+pub const GS_PLUGIN_REFINE_FLAGS_REQUIRE_SCREENSHOTS: c_uint = 1 << 26;
+#[cfg(feature = "legacy-ffi")]
+pub const GS_PLUGIN_REFINE_FLAGS_REQUIRE_UPDATE_DETAILS: c_uint = 1 << 9;
+#[cfg(feature = "legacy-ffi")]
 pub const GS_PLUGIN_REFINE_FLAGS_REQUIRE_ORIGIN: c_uint = 1 << 10;
 #[cfg(feature = "legacy-ffi")]
+pub const GS_PLUGIN_REFINE_FLAGS_REQUIRE_DEVELOPER_NAME: c_uint = 1 << 29;
+#[cfg(feature = "legacy-ffi")]
+pub const GS_PLUGIN_REFINE_FLAGS_REQUIRE_CONTENT_RATING: c_uint = 1 << 31;
+#[cfg(feature = "legacy-ffi")]
+pub const GS_APP_STATE_INSTALLED: c_uint = 1;
+#[cfg(feature = "legacy-ffi")]
+pub const GS_APP_STATE_AVAILABLE: c_uint = 2;
+#[cfg(feature = "legacy-ffi")]
+pub const GS_APP_STATE_AVAILABLE_LOCAL: c_uint = 3;
+#[cfg(feature = "legacy-ffi")]
+pub const GS_APP_STATE_UPDATABLE: c_uint = 4;
+#[cfg(feature = "legacy-ffi")]
+pub const GS_APP_STATE_REMOVING: c_uint = 8;
+#[cfg(feature = "legacy-ffi")]
+pub const GS_APP_STATE_UPDATABLE_LIVE: c_uint = 9;
+#[cfg(feature = "legacy-ffi")]
+pub const GS_SIZE_TYPE_VALID: c_uint = 2;
+#[cfg(feature = "legacy-ffi")]
+pub const AS_URL_KIND_HOMEPAGE: c_uint = 1;
+#[cfg(feature = "legacy-ffi")]
+pub const AS_IMAGE_KIND_SOURCE: c_uint = 1;
+#[cfg(feature = "legacy-ffi")]
 pub const GS_PLUGIN_REFRESH_METADATA_FLAGS_NONE: c_uint = 0;
+#[cfg(all(feature = "legacy-ffi", gs_install_apps_job))]
+pub const GS_PLUGIN_INSTALL_APPS_FLAGS_INTERACTIVE: c_uint = 1 << 0;
+#[cfg(all(feature = "legacy-ffi", gs_install_apps_job))]
+pub const GS_PLUGIN_UNINSTALL_APPS_FLAGS_INTERACTIVE: c_uint = 1 << 0;
+#[cfg(feature = "legacy-ffi")]
+pub const GS_PLUGIN_UPDATE_APPS_FLAGS_INTERACTIVE: c_uint = 1 << 0;
+#[cfg(feature = "legacy-ffi")]
+pub const GS_PLUGIN_MANAGE_REPOSITORY_FLAGS_INTERACTIVE: c_uint = 1 << 0;
+#[cfg(feature = "legacy-ffi")]
+pub const GS_PLUGIN_MANAGE_REPOSITORY_FLAGS_INSTALL: c_uint = 1 << 1;
+#[cfg(feature = "legacy-ffi")]
+pub const GS_PLUGIN_MANAGE_REPOSITORY_FLAGS_REMOVE: c_uint = 1 << 2;
+#[cfg(feature = "legacy-ffi")]
+pub const GS_PLUGIN_MANAGE_REPOSITORY_FLAGS_ENABLE: c_uint = 1 << 3;
+#[cfg(feature = "legacy-ffi")]
+pub const GS_PLUGIN_MANAGE_REPOSITORY_FLAGS_DISABLE: c_uint = 1 << 4;
+#[cfg(feature = "legacy-ffi")]
+pub const GS_PLUGIN_FILE_TO_APP_FLAGS_INTERACTIVE: c_uint = 1 << 0;
+#[cfg(feature = "legacy-ffi")]
+pub const GS_APP_PROGRESS_UNKNOWN: c_uint = c_uint::MAX;
 #[cfg(feature = "legacy-ffi")]
 pub const GS_APP_LIST_FILTER_FLAG_NONE: c_uint = 0;
 #[cfg(feature = "legacy-ffi")]
@@ -103,23 +164,83 @@ extern "C" {
         flags: c_uint,
     ) -> *mut GsPluginJob;
     pub fn gs_plugin_job_list_apps_get_result_list(job: *mut GsPluginJob) -> *mut GsAppList;
+    pub fn gs_plugin_job_list_categories_new(flags: c_uint) -> *mut GsPluginJob;
+    pub fn gs_plugin_job_list_categories_get_result_list(
+        job: *mut GsPluginJob,
+    ) -> *mut glib::ffi::GPtrArray;
     pub fn gs_plugin_job_refresh_metadata_new(
         cache_age_secs: u64,
         flags: c_uint,
     ) -> *mut GsPluginJob;
+    pub fn gs_plugin_job_refine_new_for_app(app: *mut GsApp, flags: c_uint) -> *mut GsPluginJob;
+    /// New in gnome-software 47.
+    #[cfg(gs_install_apps_job)]
+    pub fn gs_plugin_job_install_apps_new(apps: *mut GsAppList, flags: c_uint) -> *mut GsPluginJob;
+    /// New in gnome-software 47.
+    #[cfg(gs_install_apps_job)]
+    pub fn gs_plugin_job_uninstall_apps_new(
+        apps: *mut GsAppList,
+        flags: c_uint,
+    ) -> *mut GsPluginJob;
+    pub fn gs_plugin_job_update_apps_new(apps: *mut GsAppList, flags: c_uint) -> *mut GsPluginJob;
+    pub fn gs_plugin_job_manage_repository_new(
+        repository: *mut GsApp,
+        flags: c_uint,
+    ) -> *mut GsPluginJob;
+    pub fn gs_plugin_job_file_to_app_new(
+        file: *mut gio::ffi::GFile,
+        flags: c_uint,
+        refine_flags: c_uint,
+    ) -> *mut GsPluginJob;
+    pub fn gs_plugin_job_file_to_app_get_result_list(job: *mut GsPluginJob) -> *mut GsAppList;
+    pub fn gs_category_get_id(category: *mut GsCategory) -> *const c_char;
+    pub fn gs_category_get_name(category: *mut GsCategory) -> *const c_char;
+    pub fn gs_category_get_icon_name(category: *mut GsCategory) -> *const c_char;
+    pub fn gs_category_find_child(
+        category: *mut GsCategory,
+        id: *const c_char,
+    ) -> *mut GsCategory;
     pub fn gs_app_query_new(first_property_name: *const c_char, ...) -> *mut GsAppQuery;
+    pub fn gs_app_list_new() -> *mut GsAppList;
+    pub fn gs_app_list_add(apps: *mut GsAppList, app: *mut GsApp);
     pub fn gs_app_list_length(apps: *mut GsAppList) -> c_uint;
     pub fn gs_app_list_index(apps: *mut GsAppList, index: c_uint) -> *mut GsApp;
     pub fn gs_app_get_id(app: *mut GsApp) -> *const c_char;
     pub fn gs_app_get_name(app: *mut GsApp) -> *const c_char;
     pub fn gs_app_get_summary(app: *mut GsApp) -> *const c_char;
     pub fn gs_app_get_origin(app: *mut GsApp) -> *const c_char;
+    pub fn gs_app_get_progress(app: *mut GsApp) -> c_uint;
+    pub fn gs_app_get_description(app: *mut GsApp) -> *const c_char;
+    pub fn gs_app_get_version(app: *mut GsApp) -> *const c_char;
+    pub fn gs_app_get_update_version(app: *mut GsApp) -> *const c_char;
+    pub fn gs_app_get_license(app: *mut GsApp) -> *const c_char;
+    pub fn gs_app_get_developer_name(app: *mut GsApp) -> *const c_char;
+    pub fn gs_app_get_url(app: *mut GsApp, kind: c_uint) -> *const c_char;
+    pub fn gs_app_get_icon_for_size(
+        app: *mut GsApp,
+        size: c_uint,
+        scale: c_uint,
+        fallback_icon_name: *const c_char,
+    ) -> *mut gio::ffi::GIcon;
+    pub fn gs_app_get_categories(app: *mut GsApp) -> *mut glib::ffi::GPtrArray;
+    pub fn gs_app_get_screenshots(app: *mut GsApp) -> *mut glib::ffi::GPtrArray;
+    pub fn gs_app_get_state(app: *mut GsApp) -> c_uint;
+    pub fn gs_app_get_size_download(app: *mut GsApp, size_bytes_out: *mut u64) -> c_uint;
+    pub fn gs_app_get_size_installed(app: *mut GsApp, size_bytes_out: *mut u64) -> c_uint;
+    pub fn gs_app_dup_content_rating(app: *mut GsApp) -> *mut GObject;
+    pub fn as_content_rating_get_minimum_age(content_rating: *mut GObject) -> c_uint;
+    pub fn as_screenshot_get_images(screenshot: *mut GObject) -> *mut glib::ffi::GPtrArray;
+    pub fn as_image_get_kind(image: *mut GObject) -> c_uint;
+    pub fn as_image_get_url(image: *mut GObject) -> *const c_char;
 }
 
 #[cfg(not(feature = "legacy-ffi"))]
 pub mod stubs {
     use super::*;
 
+    /// # Safety
+    ///
+    /// Mirrors the C signature; the stub never dereferences its arguments.
     pub unsafe fn gs_plugin_loader_new(
         _session_bus: *mut GObject,
         _system_bus: *mut GObject,
@@ -0,0 +1,994 @@
+use std::collections::HashMap;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::future::{BoxFuture, Shared};
+use futures::{FutureExt, StreamExt};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::OwnedWriteHalf;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::process::{Child, Command};
+use tokio::task::JoinHandle;
+
+use crate::cancellation::CancellationHandle;
+use crate::plugins::{
+    job_stream, AppDetails, AppSummary, AppUpdate, Category, JobKind, JobProgress, JobStream,
+    PluginBackend, PluginDescriptor, PluginExecutionError, Repository, RepositoryAction,
+};
+
+/// Command-line flag that switches the `install_grid` binary into helper mode.
+pub const HELPER_FLAG: &str = "--plugin-helper";
+/// Flag carrying the socket path the helper must connect to.
+pub const SOCKET_FLAG: &str = "--socket";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const EXIT_GRACE: Duration = Duration::from_millis(500);
+
+static NEXT_SOCKET: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Request {
+    id: u64,
+    op: Operation,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Operation {
+    ListPopular,
+    Search { query: String },
+    Installed,
+    Categories,
+    CategoryApps { category_id: String },
+    Updates,
+    Details { app_id: String },
+    Repositories,
+    AddRepository { file: PathBuf },
+    ManageRepository { repository_id: String, action: RepositoryAction },
+    Job { kind: JobKind, app_id: String },
+    Cancel { target: u64 },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Response {
+    id: u64,
+    payload: Payload,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Payload {
+    Apps(Result<Vec<AppSummary>, PluginExecutionError>),
+    Categories(Result<Vec<Category>, PluginExecutionError>),
+    Updates(Result<Vec<AppUpdate>, PluginExecutionError>),
+    Details(Result<Box<AppDetails>, PluginExecutionError>),
+    Repositories(Result<Vec<Repository>, PluginExecutionError>),
+    Repository(Result<Repository, PluginExecutionError>),
+    /// Reply to operations that return nothing.
+    Done(Result<(), PluginExecutionError>),
+    Job(Result<JobProgress, PluginExecutionError>),
+    JobEnd,
+    /// The backend panicked while handling the request.
+    Panicked,
+}
+
+/// Runs `descriptor`'s backend in a helper process started from `program`,
+/// so a segfault or `abort()` inside a C plugin only takes down the helper.
+///
+/// The helper is spawned lazily on first use and again after it dies; calls
+/// in flight when it dies fail with [`PluginExecutionError::ProcessExited`],
+/// which the host reports as [`crate::plugins::PluginFailureKind::Crashed`].
+/// A call the host times out is taken to mean the helper hung: it is killed
+/// the same way and restarted on the next call.
+pub struct OutOfProcessPlugin {
+    descriptor: PluginDescriptor,
+    inner: Arc<HelperInner>,
+}
+
+struct HelperInner {
+    plugin_id: String,
+    program: PathBuf,
+    args: Vec<String>,
+    next_request: AtomicU64,
+    /// The current helper, or its start-up while it is still connecting.
+    /// Callers share one start-up instead of queueing behind a lock.
+    connection: Mutex<Option<Connecting>>,
+}
+
+type Connecting = Shared<BoxFuture<'static, Result<Arc<HelperConnection>, String>>>;
+
+struct HelperConnection {
+    writer: tokio::sync::Mutex<OwnedWriteHalf>,
+    pending: Arc<Mutex<HashMap<u64, async_channel::Sender<Payload>>>>,
+    child: tokio::sync::Mutex<Child>,
+    reader: JoinHandle<()>,
+    /// Set once the helper was killed for hanging; it is not reused even
+    /// before its connection closes.
+    killed: AtomicBool,
+}
+
+impl OutOfProcessPlugin {
+    /// Spawns `program` with `args` followed by `--socket <path>`.
+    pub fn new(
+        descriptor: PluginDescriptor,
+        program: impl Into<PathBuf>,
+        args: impl IntoIterator<Item = String>,
+    ) -> Self {
+        Self {
+            inner: Arc::new(HelperInner {
+                plugin_id: descriptor.id.clone(),
+                program: program.into(),
+                args: args.into_iter().collect(),
+                next_request: AtomicU64::new(1),
+                connection: Mutex::new(None),
+            }),
+            descriptor,
+        }
+    }
+
+    /// Re-executes the current binary as `install_grid --plugin-helper <id>`.
+    pub fn for_current_exe(descriptor: PluginDescriptor) -> std::io::Result<Self> {
+        let program = std::env::current_exe()?;
+        let args = vec![HELPER_FLAG.to_string(), descriptor.id.clone()];
+        Ok(Self::new(descriptor, program, args))
+    }
+}
+
+#[async_trait]
+impl PluginBackend for OutOfProcessPlugin {
+    fn descriptor(&self) -> &PluginDescriptor {
+        &self.descriptor
+    }
+
+    async fn list_popular_apps(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        match self.inner.call(Operation::ListPopular, cancel).await? {
+            Payload::Apps(result) => result,
+            other => Err(unexpected_reply(other)),
+        }
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        let op = Operation::Search {
+            query: query.to_string(),
+        };
+        match self.inner.call(op, cancel).await? {
+            Payload::Apps(result) => result,
+            other => Err(unexpected_reply(other)),
+        }
+    }
+
+    async fn list_installed_apps(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        match self.inner.call(Operation::Installed, cancel).await? {
+            Payload::Apps(result) => result,
+            other => Err(unexpected_reply(other)),
+        }
+    }
+
+    async fn list_categories(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<Category>, PluginExecutionError> {
+        match self.inner.call(Operation::Categories, cancel).await? {
+            Payload::Categories(result) => result,
+            other => Err(unexpected_reply(other)),
+        }
+    }
+
+    async fn list_category_apps(
+        &self,
+        category_id: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        let op = Operation::CategoryApps {
+            category_id: category_id.to_string(),
+        };
+        match self.inner.call(op, cancel).await? {
+            Payload::Apps(result) => result,
+            other => Err(unexpected_reply(other)),
+        }
+    }
+
+    async fn list_updates(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppUpdate>, PluginExecutionError> {
+        match self.inner.call(Operation::Updates, cancel).await? {
+            Payload::Updates(result) => result,
+            other => Err(unexpected_reply(other)),
+        }
+    }
+
+    async fn app_details(
+        &self,
+        app_id: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<AppDetails, PluginExecutionError> {
+        let op = Operation::Details {
+            app_id: app_id.to_string(),
+        };
+        match self.inner.call(op, cancel).await? {
+            Payload::Details(result) => result.map(|details| *details),
+            other => Err(unexpected_reply(other)),
+        }
+    }
+
+    async fn list_repositories(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<Repository>, PluginExecutionError> {
+        match self.inner.call(Operation::Repositories, cancel).await? {
+            Payload::Repositories(result) => result,
+            other => Err(unexpected_reply(other)),
+        }
+    }
+
+    async fn add_repository(
+        &self,
+        file: &Path,
+        cancel: &CancellationHandle,
+    ) -> Result<Repository, PluginExecutionError> {
+        let op = Operation::AddRepository {
+            file: file.to_path_buf(),
+        };
+        match self.inner.call(op, cancel).await? {
+            Payload::Repository(result) => result,
+            other => Err(unexpected_reply(other)),
+        }
+    }
+
+    async fn manage_repository(
+        &self,
+        repository_id: &str,
+        action: RepositoryAction,
+        cancel: &CancellationHandle,
+    ) -> Result<(), PluginExecutionError> {
+        let op = Operation::ManageRepository {
+            repository_id: repository_id.to_string(),
+            action,
+        };
+        match self.inner.call(op, cancel).await? {
+            Payload::Done(result) => result,
+            other => Err(unexpected_reply(other)),
+        }
+    }
+
+    fn install(&self, app_id: &str, cancel: CancellationHandle) -> JobStream {
+        self.inner.clone().job(JobKind::Install, app_id.to_string(), cancel)
+    }
+
+    fn remove(&self, app_id: &str, cancel: CancellationHandle) -> JobStream {
+        self.inner.clone().job(JobKind::Remove, app_id.to_string(), cancel)
+    }
+
+    fn update(&self, app_id: &str, cancel: CancellationHandle) -> JobStream {
+        self.inner.clone().job(JobKind::Update, app_id.to_string(), cancel)
+    }
+}
+
+impl HelperInner {
+    /// Sends a single-reply request and waits for the reply.
+    async fn call(
+        &self,
+        op: Operation,
+        cancel: &CancellationHandle,
+    ) -> Result<Payload, PluginExecutionError> {
+        let (mut request, replies) = self.send(op, cancel, true).await?;
+
+        let payload = tokio::select! {
+            payload = replies.recv() => payload,
+            _ = cancel.cancelled() => return Err(PluginExecutionError::Cancelled),
+        };
+        request.answered = true;
+
+        match payload {
+            Ok(Payload::Panicked) => Err(self.panicked()),
+            Ok(payload) => Ok(payload),
+            Err(_) => Err(self.reap(&request.connection).await),
+        }
+    }
+
+    fn job(self: Arc<Self>, kind: JobKind, app_id: String, cancel: CancellationHandle) -> JobStream {
+        job_stream(move |reporter| async move {
+            let op = Operation::Job { kind, app_id };
+            let (mut request, replies) = self.send(op, &cancel, false).await?;
+
+            loop {
+                let payload = tokio::select! {
+                    payload = replies.recv() => payload,
+                    _ = cancel.cancelled() => return Err(PluginExecutionError::Cancelled),
+                };
+                request.answered = !matches!(payload, Ok(Payload::Job(Ok(_))));
+
+                match payload {
+                    Ok(Payload::Job(Ok(progress))) => reporter.report(progress).await,
+                    Ok(Payload::Job(Err(err))) => return Err(err),
+                    Ok(Payload::JobEnd) => return Ok(()),
+                    Ok(Payload::Panicked) => return Err(self.panicked()),
+                    Ok(other) => return Err(unexpected_reply(other)),
+                    Err(_) => return Err(self.reap(&request.connection).await),
+                }
+            }
+        })
+    }
+
+    /// Sends `op` on a live connection, starting a helper if necessary.
+    /// With `kill_if_abandoned`, dropping the request uncancelled kills the
+    /// helper; see [`InFlight`].
+    async fn send(
+        &self,
+        op: Operation,
+        cancel: &CancellationHandle,
+        kill_if_abandoned: bool,
+    ) -> Result<(InFlight, async_channel::Receiver<Payload>), PluginExecutionError> {
+        let connection = self.connection().await?;
+        let id = self.next_request.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = async_channel::unbounded();
+        connection.pending.lock().insert(id, tx);
+
+        if !connection.is_alive() || connection.write(&Request { id, op }).await.is_err() {
+            connection.pending.lock().remove(&id);
+            return Err(self.reap(&connection).await);
+        }
+        let request = InFlight {
+            connection,
+            id,
+            answered: false,
+            cancel: cancel.clone(),
+            kill_if_abandoned,
+        };
+        Ok((request, rx))
+    }
+
+    fn panicked(&self) -> PluginExecutionError {
+        PluginExecutionError::Panicked(format!("plugin helper for {} panicked", self.plugin_id))
+    }
+
+    async fn connection(&self) -> Result<Arc<HelperConnection>, PluginExecutionError> {
+        let connecting = {
+            let mut slot = self.connection.lock();
+            match slot.as_ref() {
+                // Still connecting, or connected to a helper that is alive.
+                Some(connecting)
+                    if connecting
+                        .peek()
+                        .is_none_or(|result| result.as_ref().is_ok_and(|c| c.is_alive())) =>
+                {
+                    connecting.clone()
+                }
+                _ => {
+                    let connecting = spawn(self.program.clone(), self.args.clone())
+                        .map(|result| result.map(Arc::new).map_err(|err| err.to_string()))
+                        .boxed()
+                        .shared();
+                    *slot = Some(connecting.clone());
+                    connecting
+                }
+            }
+        };
+
+        connecting.await.map_err(|err| {
+            PluginExecutionError::Operation(format!(
+                "failed to start plugin helper for {}: {err}",
+                self.plugin_id
+            ))
+        })
+    }
+
+    /// Describes why `connection` went away and drops it so the next call
+    /// restarts the helper.
+    async fn reap(&self, connection: &Arc<HelperConnection>) -> PluginExecutionError {
+        {
+            let mut slot = self.connection.lock();
+            let current = slot.as_ref().and_then(|connecting| connecting.peek());
+            if let Some(Ok(current)) = current {
+                if Arc::ptr_eq(current, connection) {
+                    *slot = None;
+                }
+            }
+        }
+
+        let mut child = connection.child.lock().await;
+        let status = match tokio::time::timeout(EXIT_GRACE, child.wait()).await {
+            Ok(Ok(status)) => describe_exit(status),
+            Ok(Err(err)) => format!("unable to collect exit status: {err}"),
+            Err(_) => {
+                let _ = child.start_kill();
+                "closed its connection and was killed".to_string()
+            }
+        };
+        PluginExecutionError::ProcessExited(format!("helper for {} {status}", self.plugin_id))
+    }
+}
+
+/// Starts `program` and waits for it to connect back.
+async fn spawn(program: PathBuf, args: Vec<String>) -> std::io::Result<HelperConnection> {
+    let socket_path = helper_socket_path();
+    if let Some(parent) = socket_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let _ = std::fs::remove_file(&socket_path);
+    let listener = UnixListener::bind(&socket_path)?;
+
+    let child = Command::new(&program)
+        .args(&args)
+        .arg(SOCKET_FLAG)
+        .arg(&socket_path)
+        .kill_on_drop(true)
+        .spawn();
+    let child = match child {
+        Ok(child) => child,
+        Err(err) => {
+            let _ = std::fs::remove_file(&socket_path);
+            return Err(err);
+        }
+    };
+
+    let accepted = tokio::time::timeout(CONNECT_TIMEOUT, listener.accept()).await;
+    let _ = std::fs::remove_file(&socket_path);
+    let (stream, _) = match accepted {
+        Ok(result) => result?,
+        Err(_) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "helper did not connect in time",
+            ))
+        }
+    };
+
+    Ok(HelperConnection::new(stream, child))
+}
+
+/// A request sent to the helper. Dropping it before the final reply because
+/// the caller was cancelled cancels it in the helper too.
+///
+/// A call dropped without being cancelled was abandoned by the host's
+/// timeout. The helper is then taken to be hung and killed, so the next call
+/// starts a fresh one and the calls still waiting on it fail as crashed.
+/// Jobs are not timed out and are only ever cancelled.
+struct InFlight {
+    connection: Arc<HelperConnection>,
+    id: u64,
+    answered: bool,
+    cancel: CancellationHandle,
+    kill_if_abandoned: bool,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if self.answered {
+            return;
+        }
+        self.connection.pending.lock().remove(&self.id);
+        if self.kill_if_abandoned && !self.cancel.is_cancelled() {
+            self.connection.kill();
+            return;
+        }
+        // Without a runtime there is no helper left to tell.
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let connection = self.connection.clone();
+            let id = self.id;
+            handle.spawn(async move { connection.cancel(id).await });
+        }
+    }
+}
+
+impl HelperConnection {
+    /// Starts routing the replies read from `stream` to their callers.
+    fn new(stream: UnixStream, child: Child) -> Self {
+        let (read_half, write_half) = stream.into_split();
+        let pending: Arc<Mutex<HashMap<u64, async_channel::Sender<Payload>>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+
+        let reader = {
+            let pending = pending.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(read_half).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let Ok(response) = serde_json::from_str::<Response>(&line) else {
+                        continue;
+                    };
+                    let sender = pending.lock().get(&response.id).cloned();
+                    if let Some(sender) = sender {
+                        let finished = !matches!(response.payload, Payload::Job(Ok(_)));
+                        let _ = sender.send(response.payload).await;
+                        if finished {
+                            pending.lock().remove(&response.id);
+                        }
+                    }
+                }
+                // Dropping every sender wakes the callers, which then reap
+                // the helper and report the crash.
+                pending.lock().clear();
+            })
+        };
+
+        Self {
+            writer: tokio::sync::Mutex::new(write_half),
+            pending,
+            child: tokio::sync::Mutex::new(child),
+            reader,
+            killed: AtomicBool::new(false),
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        !self.reader.is_finished() && !self.killed.load(Ordering::Relaxed)
+    }
+
+    fn kill(&self) {
+        self.killed.store(true, Ordering::Relaxed);
+        // A caller holding the lock is already reaping the helper and kills
+        // it if it does not exit.
+        if let Ok(mut child) = self.child.try_lock() {
+            let _ = child.start_kill();
+        }
+    }
+
+    /// Asks the helper to cancel request `id` and stops waiting for it.
+    async fn cancel(&self, id: u64) {
+        self.pending.lock().remove(&id);
+        let request = Request {
+            id: 0,
+            op: Operation::Cancel { target: id },
+        };
+        let _ = self.write(&request).await;
+    }
+
+    async fn write(&self, request: &Request) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(request)?;
+        line.push(b'\n');
+        let mut writer = self.writer.lock().await;
+        writer.write_all(&line).await?;
+        writer.flush().await
+    }
+}
+
+fn unexpected_reply(payload: Payload) -> PluginExecutionError {
+    PluginExecutionError::Operation(format!("unexpected helper reply: {payload:?}"))
+}
+
+fn helper_socket_path() -> PathBuf {
+    glib::user_runtime_dir().join("install-grid").join(format!(
+        "helper-{}-{}.sock",
+        std::process::id(),
+        NEXT_SOCKET.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+fn describe_exit(status: std::process::ExitStatus) -> String {
+    use std::os::unix::process::ExitStatusExt;
+
+    match (status.code(), status.signal()) {
+        (_, Some(signal)) => format!("was killed by signal {signal}"),
+        (Some(code), _) => format!("exited with status {code}"),
+        (None, None) => "exited".to_string(),
+    }
+}
+
+/// Helper-process entry point: connects to `socket` and serves requests with
+/// `backend` until the parent closes the connection.
+pub async fn serve(backend: Arc<dyn PluginBackend>, socket: &Path) -> std::io::Result<()> {
+    serve_stream(backend, UnixStream::connect(socket).await?).await
+}
+
+/// Serves requests read from `stream` until the other end closes it.
+async fn serve_stream(backend: Arc<dyn PluginBackend>, stream: UnixStream) -> std::io::Result<()> {
+    let (read_half, write_half) = stream.into_split();
+    let writer = Arc::new(tokio::sync::Mutex::new(write_half));
+    let cancels: Arc<Mutex<HashMap<u64, CancellationHandle>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let mut lines = BufReader::new(read_half).lines();
+    while let Some(line) = lines.next_line().await? {
+        let request = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => request,
+            Err(err) => {
+                eprintln!("InstallGrid helper: ignoring malformed request: {err}");
+                continue;
+            }
+        };
+
+        if let Operation::Cancel { target } = request.op {
+            if let Some(cancel) = cancels.lock().get(&target) {
+                cancel.cancel();
+            }
+            continue;
+        }
+
+        let cancel = CancellationHandle::new();
+        cancels.lock().insert(request.id, cancel.clone());
+
+        let backend = backend.clone();
+        let writer = writer.clone();
+        let cancels = cancels.clone();
+        tokio::spawn(async move {
+            let id = request.id;
+            let handled = AssertUnwindSafe(handle(backend, request, cancel, writer.clone()))
+                .catch_unwind()
+                .await;
+            if handled.is_err() {
+                let _ = reply(&writer, id, Payload::Panicked).await;
+            }
+            cancels.lock().remove(&id);
+        });
+    }
+
+    Ok(())
+}
+
+async fn handle(
+    backend: Arc<dyn PluginBackend>,
+    request: Request,
+    cancel: CancellationHandle,
+    writer: Arc<tokio::sync::Mutex<OwnedWriteHalf>>,
+) -> std::io::Result<()> {
+    match request.op {
+        Operation::ListPopular => {
+            let result = backend.list_popular_apps(&cancel).await;
+            reply(&writer, request.id, Payload::Apps(result)).await
+        }
+        Operation::Search { query } => {
+            let result = backend.search(&query, &cancel).await;
+            reply(&writer, request.id, Payload::Apps(result)).await
+        }
+        Operation::Installed => {
+            let result = backend.list_installed_apps(&cancel).await;
+            reply(&writer, request.id, Payload::Apps(result)).await
+        }
+        Operation::Categories => {
+            let result = backend.list_categories(&cancel).await;
+            reply(&writer, request.id, Payload::Categories(result)).await
+        }
+        Operation::CategoryApps { category_id } => {
+            let result = backend.list_category_apps(&category_id, &cancel).await;
+            reply(&writer, request.id, Payload::Apps(result)).await
+        }
+        Operation::Updates => {
+            let result = backend.list_updates(&cancel).await;
+            reply(&writer, request.id, Payload::Updates(result)).await
+        }
+        Operation::Details { app_id } => {
+            let result = backend.app_details(&app_id, &cancel).await.map(Box::new);
+            reply(&writer, request.id, Payload::Details(result)).await
+        }
+        Operation::Repositories => {
+            let result = backend.list_repositories(&cancel).await;
+            reply(&writer, request.id, Payload::Repositories(result)).await
+        }
+        Operation::AddRepository { file } => {
+            let result = backend.add_repository(&file, &cancel).await;
+            reply(&writer, request.id, Payload::Repository(result)).await
+        }
+        Operation::ManageRepository {
+            repository_id,
+            action,
+        } => {
+            let result = backend
+                .manage_repository(&repository_id, action, &cancel)
+                .await;
+            reply(&writer, request.id, Payload::Done(result)).await
+        }
+        Operation::Job { kind, app_id } => {
+            let mut progress = match kind {
+                JobKind::Install => backend.install(&app_id, cancel),
+                JobKind::Remove => backend.remove(&app_id, cancel),
+                JobKind::Update => backend.update(&app_id, cancel),
+            };
+            while let Some(item) = progress.next().await {
+                let failed = item.is_err();
+                reply(&writer, request.id, Payload::Job(item)).await?;
+                if failed {
+                    return Ok(());
+                }
+            }
+            reply(&writer, request.id, Payload::JobEnd).await
+        }
+        Operation::Cancel { .. } => Ok(()),
+    }
+}
+
+async fn reply(
+    writer: &tokio::sync::Mutex<OwnedWriteHalf>,
+    id: u64,
+    payload: Payload,
+) -> std::io::Result<()> {
+    let mut line = serde_json::to_vec(&Response { id, payload })?;
+    line.push(b'\n');
+    let mut writer = writer.lock().await;
+    writer.write_all(&line).await?;
+    writer.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+
+    use tokio::net::unix::OwnedReadHalf;
+
+    use super::*;
+    use crate::plugins::{NativeMockPlugin, PluginFailureKind, PluginKind};
+
+    /// Set in the environment of a test binary re-executed as a helper.
+    const HELPER_SOCKET: &str = "INSTALLGRID_TEST_HELPER_SOCKET";
+
+    fn run<T>(future: impl Future<Output = T>) -> T {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn descriptor(id: &str) -> PluginDescriptor {
+        PluginDescriptor {
+            id: id.to_string(),
+            kind: PluginKind::Native,
+        }
+    }
+
+    /// Runs [`Crashy`] in a copy of this test binary; see [`helper_process`].
+    fn crashy_plugin() -> OutOfProcessPlugin {
+        // The test harness rejects `--socket`, so the shell moves the path
+        // into the environment. The helper's own harness output is dropped.
+        let script = format!(
+            "{HELPER_SOCKET}=\"$2\" exec \"$0\" helper::tests::helper_process --exact \
+             >/dev/null 2>&1"
+        );
+        let exe = std::env::current_exe().unwrap().to_string_lossy().into_owned();
+        let args = ["-c".to_string(), script, exe];
+        OutOfProcessPlugin::new(descriptor("native::crashy"), "/bin/sh", args)
+    }
+
+    /// Lists one app named after the helper's pid. Searching for `abort`
+    /// kills the helper, `hang` never returns and `panic` panics.
+    struct Crashy {
+        descriptor: PluginDescriptor,
+    }
+
+    #[async_trait]
+    impl PluginBackend for Crashy {
+        fn descriptor(&self) -> &PluginDescriptor {
+            &self.descriptor
+        }
+
+        async fn list_popular_apps(
+            &self,
+            _cancel: &CancellationHandle,
+        ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+            Ok(vec![AppSummary {
+                app_id: "org.example.Crashy".to_string(),
+                name: std::process::id().to_string(),
+                summary: String::new(),
+                source: "crashy".to_string(),
+                installed: false,
+            }])
+        }
+
+        async fn search(
+            &self,
+            query: &str,
+            _cancel: &CancellationHandle,
+        ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+            match query {
+                "abort" => std::process::abort(),
+                "hang" => futures::future::pending().await,
+                "panic" => panic!("crashy panic"),
+                _ => Ok(Vec::new()),
+            }
+        }
+
+        async fn app_details(
+            &self,
+            _app_id: &str,
+            _cancel: &CancellationHandle,
+        ) -> Result<AppDetails, PluginExecutionError> {
+            Err(PluginExecutionError::Unsupported)
+        }
+
+        fn install(&self, _app_id: &str, _cancel: CancellationHandle) -> JobStream {
+            crate::plugins::failed_job(PluginExecutionError::Unsupported)
+        }
+
+        fn remove(&self, _app_id: &str, _cancel: CancellationHandle) -> JobStream {
+            crate::plugins::failed_job(PluginExecutionError::Unsupported)
+        }
+    }
+
+    /// Not a test of its own: serves [`Crashy`] when re-executed by
+    /// [`crashy_plugin`], and passes straight away otherwise.
+    #[test]
+    fn helper_process() {
+        let Some(socket) = std::env::var_os(HELPER_SOCKET) else {
+            return;
+        };
+        let backend = Arc::new(Crashy {
+            descriptor: descriptor("native::crashy"),
+        });
+        run(serve(backend, Path::new(&socket))).unwrap();
+    }
+
+    async fn helper_pid(plugin: &OutOfProcessPlugin) -> String {
+        let apps = plugin
+            .list_popular_apps(&CancellationHandle::new())
+            .await
+            .unwrap();
+        apps[0].name.clone()
+    }
+
+    /// Serves a mock backend on one end of a socket pair and returns the
+    /// other end.
+    fn served_mock() -> (BufReader<OwnedReadHalf>, OwnedWriteHalf) {
+        let (ours, theirs) = UnixStream::pair().unwrap();
+        let backend = Arc::new(NativeMockPlugin::new("native::mock").with_delay(Duration::ZERO));
+        tokio::spawn(serve_stream(backend, theirs));
+        let (read_half, write_half) = ours.into_split();
+        (BufReader::new(read_half), write_half)
+    }
+
+    async fn send_line(writer: &mut OwnedWriteHalf, line: &str) {
+        writer.write_all(line.as_bytes()).await.unwrap();
+        writer.write_all(b"\n").await.unwrap();
+    }
+
+    async fn send_request(writer: &mut OwnedWriteHalf, id: u64, op: Operation) {
+        send_line(writer, &serde_json::to_string(&Request { id, op }).unwrap()).await;
+    }
+
+    async fn read_response(reader: &mut BufReader<OwnedReadHalf>) -> Response {
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[test]
+    fn requests_and_job_progress_round_trip_one_line_each() {
+        run(async {
+            let (mut reader, mut writer) = served_mock();
+
+            send_request(&mut writer, 1, Operation::ListPopular).await;
+            let response = read_response(&mut reader).await;
+            assert_eq!(response.id, 1);
+            let Payload::Apps(Ok(apps)) = response.payload else {
+                panic!("expected apps, got {:?}", response.payload);
+            };
+            assert_eq!(apps.len(), 3);
+
+            let op = Operation::Job {
+                kind: JobKind::Install,
+                app_id: "org.mozilla.firefox".to_string(),
+            };
+            send_request(&mut writer, 2, op).await;
+            let mut progress = Vec::new();
+            loop {
+                let response = read_response(&mut reader).await;
+                assert_eq!(response.id, 2);
+                match response.payload {
+                    Payload::Job(Ok(item)) => progress.push(item),
+                    Payload::JobEnd => break,
+                    other => panic!("unexpected reply {other:?}"),
+                }
+            }
+            assert_eq!(progress.first(), Some(&JobProgress::Queued));
+            assert_eq!(progress.last(), Some(&JobProgress::Finished));
+        });
+    }
+
+    #[test]
+    fn malformed_requests_are_skipped() {
+        run(async {
+            let (mut reader, mut writer) = served_mock();
+
+            send_line(&mut writer, "not json").await;
+            send_line(&mut writer, r#"{"id":1,"op":"NoSuchOperation"}"#).await;
+            send_request(&mut writer, 2, Operation::Repositories).await;
+
+            let response = read_response(&mut reader).await;
+            assert_eq!(response.id, 2);
+            assert!(matches!(response.payload, Payload::Repositories(Ok(_))));
+        });
+    }
+
+    #[test]
+    fn dropping_an_unanswered_request_cancels_it_in_the_helper() {
+        run(async {
+            let (ours, theirs) = UnixStream::pair().unwrap();
+            let child = Command::new("sleep").arg("60").kill_on_drop(true).spawn().unwrap();
+            let connection = Arc::new(HelperConnection::new(ours, child));
+            let cancel = CancellationHandle::new();
+            cancel.cancel();
+            drop(InFlight {
+                connection: connection.clone(),
+                id: 7,
+                answered: false,
+                cancel,
+                kill_if_abandoned: true,
+            });
+
+            let mut lines = BufReader::new(theirs).lines();
+            let line = lines.next_line().await.unwrap().unwrap();
+            let request: Request = serde_json::from_str(&line).unwrap();
+            assert!(matches!(request.op, Operation::Cancel { target: 7 }));
+            assert!(connection.is_alive());
+        });
+    }
+
+    #[test]
+    fn crashed_helper_fails_its_calls_and_is_restarted() {
+        run(async {
+            let plugin = crashy_plugin();
+            let first = helper_pid(&plugin).await;
+
+            let err = plugin
+                .search("abort", &CancellationHandle::new())
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(PluginFailureKind::from(err.clone()), PluginFailureKind::Crashed(_)),
+                "{err:?}"
+            );
+
+            assert_ne!(helper_pid(&plugin).await, first);
+        });
+    }
+
+    #[test]
+    fn helper_panics_are_reported_without_a_restart() {
+        run(async {
+            let plugin = crashy_plugin();
+            let first = helper_pid(&plugin).await;
+
+            let err = plugin
+                .search("panic", &CancellationHandle::new())
+                .await
+                .unwrap_err();
+            assert!(matches!(err, PluginExecutionError::Panicked(_)), "{err:?}");
+            assert_eq!(helper_pid(&plugin).await, first);
+        });
+    }
+
+    #[test]
+    fn timed_out_calls_kill_the_helper() {
+        run(async {
+            let plugin = crashy_plugin();
+            let first = helper_pid(&plugin).await;
+
+            let cancel = CancellationHandle::new();
+            let call = plugin.search("hang", &cancel);
+            assert!(tokio::time::timeout(Duration::from_millis(200), call).await.is_err());
+
+            assert_ne!(helper_pid(&plugin).await, first);
+        });
+    }
+
+    #[test]
+    fn cancelled_calls_keep_the_helper() {
+        run(async {
+            let plugin = crashy_plugin();
+            let first = helper_pid(&plugin).await;
+
+            let cancel = CancellationHandle::new();
+            let canceller = cancel.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                canceller.cancel();
+            });
+            let err = plugin.search("hang", &cancel).await.unwrap_err();
+            assert!(matches!(err, PluginExecutionError::Cancelled), "{err:?}");
+
+            assert_eq!(helper_pid(&plugin).await, first);
+        });
+    }
+}
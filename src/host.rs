@@ -1,31 +1,71 @@
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
 use anyhow::Context;
 use async_channel::bounded;
 use futures::future::{join_all, BoxFuture};
-use futures::FutureExt;
+use futures::stream::{self, BoxStream, FuturesUnordered};
+use futures::{FutureExt, StreamExt};
 use parking_lot::RwLock;
+use serde::{Serialize, Serializer};
 use thiserror::Error;
+use tokio::sync::broadcast;
 
-use crate::plugins::{AppSummary, PluginBackend, PluginDescriptor, PluginFailure, PluginFailureKind};
+use crate::cache::{CacheError, CacheStore};
+use crate::cancellation::CancellationHandle;
+use crate::plugins::{
+    AppDetails, AppSource, AppSummary, AppUpdate, Category, JobKind, JobProgress, MergedApp, PluginBackend,
+    PluginDescriptor, PluginExecutionError, PluginFailure, PluginFailureKind, Repository,
+    RepositoryAction,
+};
+use crate::registry::{PluginHealth, PluginRegistry, QuarantinePolicy};
+use crate::scheduler::{RefreshSchedule, RefreshScheduler};
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum HostError {
     #[error("all plugins failed")]
     AllFailed(Vec<PluginFailure>),
     #[error("host runtime unavailable")]
     RuntimeUnavailable,
+    #[error("operation cancelled")]
+    Cancelled,
+    #[error("unknown application {0}")]
+    UnknownApp(String),
+    #[error("unknown plugin {0}")]
+    UnknownPlugin(String),
+    #[error("unknown repository {0}")]
+    UnknownRepository(String),
+    /// No registered plugin implements the operation.
+    #[error("no plugin supports this operation")]
+    Unsupported,
+    /// The single plugin an operation was routed to failed.
+    #[error("{}: {}", .0.plugin, .0.kind)]
+    Plugin(PluginFailure),
 }
 
+#[derive(Default)]
 pub struct PluginHostBuilder {
     plugins: Vec<Arc<dyn PluginBackend>>,
+    timeouts: PluginTimeouts,
+    quarantine: QuarantinePolicy,
+    priority: Vec<String>,
 }
 
-impl Default for PluginHostBuilder {
-    fn default() -> Self {
-        Self { plugins: Vec::new() }
+/// Deadlines applied to each backend call; `None` means the call may run
+/// until the backend returns.
+#[derive(Debug, Clone, Default)]
+pub struct PluginTimeouts {
+    default: Option<Duration>,
+    overrides: HashMap<String, Duration>,
+}
+
+impl PluginTimeouts {
+    pub fn for_plugin(&self, plugin_id: &str) -> Option<Duration> {
+        self.overrides.get(plugin_id).copied().or(self.default)
     }
 }
 
@@ -42,6 +82,40 @@ impl PluginHostBuilder {
         self
     }
 
+    /// Timeout applied to every backend without an explicit override.
+    pub fn with_default_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.default = Some(timeout);
+        self
+    }
+
+    /// Quarantines a plugin after `panic_threshold` consecutive panics and
+    /// retries it once `cooldown` has elapsed.
+    pub fn with_quarantine(mut self, panic_threshold: u32, cooldown: Duration) -> Self {
+        self.quarantine = QuarantinePolicy {
+            panic_threshold,
+            cooldown,
+        };
+        self
+    }
+
+    /// Order in which backends supply the primary metadata when several
+    /// report the same `app_id`. Unlisted backends follow in registration
+    /// order.
+    pub fn with_priority<I, S>(mut self, plugin_ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.priority = plugin_ids.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Overrides the timeout for the backend whose descriptor id is `plugin_id`.
+    pub fn with_timeout(mut self, plugin_id: impl Into<String>, timeout: Duration) -> Self {
+        self.timeouts.overrides.insert(plugin_id.into(), timeout);
+        self
+    }
+
     pub fn build(self) -> anyhow::Result<PluginHost> {
         let runtime = tokio::runtime::Builder::new_multi_thread()
             .enable_all()
@@ -49,19 +123,40 @@ impl PluginHostBuilder {
             .context("failed to build tokio runtime")?;
         let handle = runtime.handle().clone();
 
+        let mut priority = self.priority;
+        for backend in &self.plugins {
+            let id = &backend.descriptor().id;
+            if !priority.contains(id) {
+                priority.push(id.clone());
+            }
+        }
+
         Ok(PluginHost {
-            runtime: Arc::new(runtime),
+            _runtime: Arc::new(runtime),
             handle,
-            plugins: Arc::new(self.plugins),
+            state: Arc::new(HostState {
+                registry: PluginRegistry::new(self.plugins, self.quarantine),
+                timeouts: self.timeouts,
+                priority,
+            }),
         })
     }
 }
 
 #[derive(Clone)]
 pub struct PluginHost {
-    runtime: Arc<tokio::runtime::Runtime>,
+    // Owned so the runtime lives as long as any clone of the host.
+    _runtime: Arc<tokio::runtime::Runtime>,
     handle: tokio::runtime::Handle,
-    plugins: Arc<Vec<Arc<dyn PluginBackend>>>,
+    state: Arc<HostState>,
+}
+
+/// Host configuration and bookkeeping shared with tasks on the runtime.
+struct HostState {
+    registry: PluginRegistry,
+    timeouts: PluginTimeouts,
+    /// Every plugin id, most preferred first.
+    priority: Vec<String>,
 }
 
 pub struct HostResponse<T> {
@@ -69,18 +164,382 @@ pub struct HostResponse<T> {
     pub warnings: Vec<PluginFailure>,
 }
 
+/// One step of a streamed fan-out, yielded as each backend completes.
+#[derive(Debug, Clone)]
+pub enum HostEvent {
+    /// `plugin` answered; `apps` are the merged records for the app ids it
+    /// reported, taking every backend that has answered so far into account.
+    Apps {
+        plugin: String,
+        apps: Vec<MergedApp>,
+    },
+    Failed(PluginFailure),
+}
+
+/// Streamed fan-out. After the last backend the stream ends; it ends early
+/// with [`HostError::Cancelled`], or with [`HostError::AllFailed`] when no
+/// backend succeeded.
+pub type HostEvents = BoxStream<'static, Result<HostEvent, HostError>>;
+
+/// Progress of a job dispatched through the host; failures carry the plugin
+/// that ran the job.
+pub type JobEvents = BoxStream<'static, Result<JobProgress, PluginFailure>>;
+
+/// Boxed per-backend operation used by the fan-out helpers.
+type BackendCall<T> = Arc<
+    dyn Fn(Arc<dyn PluginBackend>, CancellationHandle) -> BoxFuture<'static, Result<T, PluginExecutionError>>
+        + Send
+        + Sync,
+>;
+
 impl PluginHost {
+    /// Queries every backend for popular apps. Firing `cancel` aborts the
+    /// remaining backends and resolves to [`HostError::Cancelled`].
     pub fn list_popular(
         &self,
-    ) -> impl Future<Output = Result<HostResponse<Vec<AppSummary>>, HostError>> {
-        let plugins = self.plugins.clone();
+        cancel: CancellationHandle,
+    ) -> impl Future<Output = Result<HostResponse<Vec<MergedApp>>, HostError>> {
+        let state = self.state.clone();
+
+        self.spawn(async move { collect_popular(state, cancel).await })
+    }
+
+    /// Like [`PluginHost::list_popular`], but yields each backend's results as
+    /// soon as that backend finishes instead of waiting for the slowest one.
+    pub fn list_popular_stream(&self, cancel: CancellationHandle) -> HostEvents {
+        let call = popular_call(self.state.clone());
+        let (tx, rx) = async_channel::unbounded();
+        self.handle
+            .spawn(stream_apps(self.state.clone(), cancel, call, tx));
+        rx.boxed()
+    }
+
+    /// Runs a keyword search on every backend and merges the matches by
+    /// `app_id`; ranking is left to the caller.
+    pub fn search(
+        &self,
+        query: impl Into<String>,
+        cancel: CancellationHandle,
+    ) -> impl Future<Output = Result<HostResponse<Vec<MergedApp>>, HostError>> {
+        let state = self.state.clone();
+        let query = query.into();
+
+        self.spawn(async move { collect_search(state, query, cancel).await })
+    }
+
+    /// Asks every backend which apps are installed and merges the answers by
+    /// `app_id`. Backends that cannot tell contribute nothing.
+    pub fn list_installed(
+        &self,
+        cancel: CancellationHandle,
+    ) -> impl Future<Output = Result<HostResponse<Vec<MergedApp>>, HostError>> {
+        let state = self.state.clone();
+        let call: BackendCall<Vec<AppSummary>> = Arc::new(|backend, cancel| {
+            async move { backend.list_installed_apps(&cancel).await }.boxed()
+        });
+
+        self.spawn(async move { collect_apps(state, cancel, call).await })
+    }
+
+    /// Asks every backend for its categories and merges them by id. The
+    /// plugin earliest in priority order names a shared category.
+    pub fn list_categories(
+        &self,
+        cancel: CancellationHandle,
+    ) -> impl Future<Output = Result<HostResponse<Vec<Category>>, HostError>> {
+        let state = self.state.clone();
+
+        self.spawn(async move { collect_categories(state, cancel).await })
+    }
+
+    /// Asks every backend for the apps in `category_id` and merges them by
+    /// `app_id`. Backends without that category contribute nothing.
+    pub fn list_category_apps(
+        &self,
+        category_id: impl Into<String>,
+        cancel: CancellationHandle,
+    ) -> impl Future<Output = Result<HostResponse<Vec<MergedApp>>, HostError>> {
+        let state = self.state.clone();
+        let category_id = Arc::new(category_id.into());
+        let call: BackendCall<Vec<AppSummary>> = Arc::new(move |backend, cancel| {
+            let category_id = category_id.clone();
+            async move { backend.list_category_apps(&category_id, &cancel).await }.boxed()
+        });
+
+        self.spawn(async move { collect_apps(state, cancel, call).await })
+    }
+
+    /// Asks every backend which installed apps have updates, in registration
+    /// order. Each update's `plugin` is set to the backend that reported it.
+    pub fn list_updates(
+        &self,
+        cancel: CancellationHandle,
+    ) -> impl Future<Output = Result<HostResponse<Vec<AppUpdate>>, HostError>> {
+        let state = self.state.clone();
+
+        self.spawn(async move { collect_updates(state, cancel).await })
+    }
+
+    /// Asks every backend for its repositories, in registration order. Each
+    /// repository's `plugin` is set to the backend that reported it.
+    pub fn list_repositories(
+        &self,
+        cancel: CancellationHandle,
+    ) -> impl Future<Output = Result<HostResponse<Vec<Repository>>, HostError>> {
+        let state = self.state.clone();
+
+        self.spawn(async move { collect_repositories(state, cancel).await })
+    }
+
+    /// Fetches full metadata for `app_id` from the plugin `source` names.
+    pub fn app_details(
+        &self,
+        app_id: &str,
+        source: &AppSource,
+        cancel: CancellationHandle,
+    ) -> impl Future<Output = Result<AppDetails, HostError>> {
+        let state = self.state.clone();
+        let plugin = source.plugin.clone();
+        let app_id = Arc::new(app_id.to_string());
+
+        self.spawn(async move {
+            let backend = state
+                .registry
+                .find(&plugin)
+                .ok_or(HostError::UnknownPlugin(plugin))?;
+            let call: BackendCall<AppDetails> = Arc::new(move |backend, cancel| {
+                let app_id = app_id.clone();
+                async move { backend.app_details(&app_id, &cancel).await }.boxed()
+            });
+            run_single(state, backend, cancel, call).await
+        })
+    }
+
+    /// Adds the repository described by a `.flatpakrepo` file through
+    /// `plugin`, or else through the first backend in priority order that
+    /// supports adding repositories.
+    pub fn add_repository(
+        &self,
+        file: impl Into<PathBuf>,
+        plugin: Option<&str>,
+        cancel: CancellationHandle,
+    ) -> impl Future<Output = Result<Repository, HostError>> {
+        let state = self.state.clone();
+        let file = Arc::new(file.into());
+        let plugin = plugin.map(str::to_string);
+
+        self.spawn(async move {
+            let candidates = match &plugin {
+                Some(id) => vec![state
+                    .registry
+                    .find(id)
+                    .ok_or_else(|| HostError::UnknownPlugin(id.clone()))?],
+                None => state
+                    .priority
+                    .iter()
+                    .filter_map(|id| state.registry.find(id))
+                    .collect(),
+            };
+
+            for backend in candidates {
+                let plugin_id = backend.descriptor().id.clone();
+                let file = file.clone();
+                let call: BackendCall<Repository> = Arc::new(move |backend, cancel| {
+                    let file = file.clone();
+                    async move { backend.add_repository(&file, &cancel).await }.boxed()
+                });
+                match run_single(state.clone(), backend, cancel.clone(), call).await {
+                    Ok(repository) => {
+                        return Ok(Repository {
+                            plugin: plugin_id,
+                            ..repository
+                        })
+                    }
+                    Err(HostError::Plugin(PluginFailure {
+                        kind: PluginFailureKind::Execution(PluginExecutionError::Unsupported),
+                        ..
+                    })) if plugin.is_none() => continue,
+                    Err(err) => return Err(err),
+                }
+            }
+            Err(HostError::Unsupported)
+        })
+    }
+
+    /// Enables, disables or removes `repository` through the plugin that
+    /// listed it.
+    pub fn manage_repository(
+        &self,
+        repository: &Repository,
+        action: RepositoryAction,
+        cancel: CancellationHandle,
+    ) -> impl Future<Output = Result<(), HostError>> {
+        let state = self.state.clone();
+        let plugin = repository.plugin.clone();
+        let repository_id = Arc::new(repository.id.clone());
+
+        self.spawn(async move {
+            let backend = state
+                .registry
+                .find(&plugin)
+                .ok_or(HostError::UnknownPlugin(plugin))?;
+            let call: BackendCall<()> = Arc::new(move |backend, cancel| {
+                let repository_id = repository_id.clone();
+                async move {
+                    backend
+                        .manage_repository(&repository_id, action, &cancel)
+                        .await
+                }
+                .boxed()
+            });
+            run_single(state, backend, cancel, call).await
+        })
+    }
+
+    /// Installs `app_id` through the plugin `source` names.
+    pub fn install(
+        &self,
+        app_id: &str,
+        source: &AppSource,
+        cancel: CancellationHandle,
+    ) -> Result<JobEvents, HostError> {
+        self.run_job(app_id, source, JobKind::Install, cancel)
+    }
+
+    /// Removes `app_id` through the plugin `source` names.
+    pub fn remove(
+        &self,
+        app_id: &str,
+        source: &AppSource,
+        cancel: CancellationHandle,
+    ) -> Result<JobEvents, HostError> {
+        self.run_job(app_id, source, JobKind::Remove, cancel)
+    }
+
+    /// Updates `app_id` through the plugin `source` names.
+    pub fn update(
+        &self,
+        app_id: &str,
+        source: &AppSource,
+        cancel: CancellationHandle,
+    ) -> Result<JobEvents, HostError> {
+        self.run_job(app_id, source, JobKind::Update, cancel)
+    }
+
+    pub fn plugin_count(&self) -> usize {
+        self.state.registry.len()
+    }
+
+    pub fn plugin_health(&self) -> Vec<PluginHealth> {
+        self.state.registry.snapshot()
+    }
+
+    /// Lets the next call through to a quarantined plugin. Fails with
+    /// [`HostError::UnknownPlugin`] for plugins that are not registered.
+    pub fn retry_plugin(&self, plugin_id: &str) -> Result<(), HostError> {
+        self.state
+            .registry
+            .retry(plugin_id)
+            .then_some(())
+            .ok_or_else(|| HostError::UnknownPlugin(plugin_id.to_string()))
+    }
+
+    /// Enables or disables a plugin until the host is dropped. Calls to a
+    /// disabled plugin fail with [`PluginFailureKind::Disabled`].
+    pub fn set_plugin_enabled(&self, plugin_id: &str, enabled: bool) -> Result<(), HostError> {
+        self.state
+            .registry
+            .set_enabled(plugin_id, enabled)
+            .then_some(())
+            .ok_or_else(|| HostError::UnknownPlugin(plugin_id.to_string()))
+    }
+
+    /// Drives the backend job on the host runtime and forwards its progress,
+    /// converting errors and panics into [`PluginFailure`]s. How the job
+    /// ended is recorded in the registry like any other call; a job nobody
+    /// listens to any more counts as cancelled.
+    fn run_job(
+        &self,
+        app_id: &str,
+        source: &AppSource,
+        kind: JobKind,
+        cancel: CancellationHandle,
+    ) -> Result<JobEvents, HostError> {
+        let backend = self
+            .state
+            .registry
+            .find(&source.plugin)
+            .ok_or_else(|| HostError::UnknownPlugin(source.plugin.clone()))?;
+        let admitted = self.state.registry.admit(&backend.descriptor().id);
+        let state = self.state.clone();
+        let app_id = app_id.to_string();
+        let (tx, rx) = async_channel::unbounded();
+
+        self.handle.spawn(async move {
+            let descriptor: PluginDescriptor = backend.descriptor().clone();
+            let failure = |kind| PluginFailure {
+                plugin: descriptor.id.clone(),
+                plugin_kind: descriptor.kind,
+                kind,
+            };
+
+            if let Err(kind) = admitted {
+                let _ = tx.send(Err(failure(kind))).await;
+                return;
+            }
+
+            let call = PendingCall::new(state, &descriptor.id);
+            let started = std::panic::catch_unwind(AssertUnwindSafe(|| match kind {
+                JobKind::Install => backend.install(&app_id, cancel),
+                JobKind::Remove => backend.remove(&app_id, cancel),
+                JobKind::Update => backend.update(&app_id, cancel),
+            }));
+            let mut progress = match started {
+                Ok(progress) => progress,
+                Err(_) => {
+                    call.finish(Err(&PluginFailureKind::Panic));
+                    let _ = tx.send(Err(failure(PluginFailureKind::Panic))).await;
+                    return;
+                }
+            };
+
+            loop {
+                let item = match AssertUnwindSafe(progress.next()).catch_unwind().await {
+                    Ok(Some(Ok(update))) => Ok(update),
+                    Ok(Some(Err(err))) => Err(failure(PluginFailureKind::from(err))),
+                    Ok(None) => {
+                        call.finish(Ok(()));
+                        break;
+                    }
+                    Err(_) => Err(failure(PluginFailureKind::Panic)),
+                };
+                if let Err(failure) = &item {
+                    call.finish(Err(&failure.kind));
+                    let _ = tx.send(item).await;
+                    break;
+                }
+                if tx.send(item).await.is_err() {
+                    // Dropping `call` records the job as cancelled.
+                    break;
+                }
+            }
+        });
+
+        Ok(rx.boxed())
+    }
+
+    fn spawn<T, F>(&self, task: F) -> impl Future<Output = Result<T, HostError>>
+    where
+        T: Send + 'static,
+        F: Future<Output = Result<T, HostError>> + Send + 'static,
+    {
         let handle = self.handle.clone();
 
         async move {
             let (tx, rx) = bounded(1);
 
             handle.spawn(async move {
-                let result = collect_popular(plugins).await;
+                let result = task.await;
                 let _ = tx.send(result).await;
             });
 
@@ -90,27 +549,81 @@ impl PluginHost {
 }
 
 async fn collect_popular(
-    plugins: Arc<Vec<Arc<dyn PluginBackend>>>,
-) -> Result<HostResponse<Vec<AppSummary>>, HostError> {
-    let plugin_count = plugins.len();
+    state: Arc<HostState>,
+    cancel: CancellationHandle,
+) -> Result<HostResponse<Vec<MergedApp>>, HostError> {
+    let call = popular_call(state.clone());
+    collect_apps(state, cancel, call).await
+}
+
+/// Lists popular apps, recording in the registry how long each completed
+/// listing took.
+fn popular_call(state: Arc<HostState>) -> BackendCall<Vec<AppSummary>> {
+    Arc::new(move |backend, cancel| {
+        let state = state.clone();
+        async move {
+            let started = Instant::now();
+            let result = backend.list_popular_apps(&cancel).await;
+            state
+                .registry
+                .record_refresh(&backend.descriptor().id, started.elapsed());
+            result
+        }
+        .boxed()
+    })
+}
+
+async fn collect_search(
+    state: Arc<HostState>,
+    query: String,
+    cancel: CancellationHandle,
+) -> Result<HostResponse<Vec<MergedApp>>, HostError> {
+    let query = Arc::new(query);
+    let call: BackendCall<Vec<AppSummary>> = Arc::new(move |backend, cancel| {
+        let query = query.clone();
+        async move { backend.search(&query, &cancel).await }.boxed()
+    });
+    collect_apps(state, cancel, call).await
+}
+
+async fn collect_apps(
+    state: Arc<HostState>,
+    cancel: CancellationHandle,
+    call: BackendCall<Vec<AppSummary>>,
+) -> Result<HostResponse<Vec<MergedApp>>, HostError> {
+    let plugin_count = state.registry.len();
     let mut tasks: Vec<BoxFuture<'static, Result<Vec<AppSummary>, PluginFailure>>> = Vec::new();
 
-    for backend in plugins.iter().cloned() {
-        tasks.push(run_plugin(backend));
+    for backend in state.registry.backends().cloned() {
+        let timeout = state.timeouts.for_plugin(&backend.descriptor().id);
+        tasks.push(run_plugin(
+            state.clone(),
+            backend,
+            timeout,
+            cancel.clone(),
+            call.clone(),
+        ));
     }
 
-    let results = join_all(tasks).await;
+    let results = tokio::select! {
+        results = join_all(tasks) => results,
+        _ = cancel.cancelled() => return Err(HostError::Cancelled),
+    };
+    if cancel.is_cancelled() {
+        return Err(HostError::Cancelled);
+    }
 
-    let mut apps = Vec::new();
+    let mut chunks = Vec::new();
     let mut warnings = Vec::new();
 
-    for result in results {
+    for (backend, result) in state.registry.backends().zip(results) {
         match result {
-            Ok(mut chunk) => apps.append(&mut chunk),
+            Ok(chunk) => chunks.push((backend.descriptor().id.clone(), chunk)),
             Err(failure) => warnings.push(failure),
         }
     }
 
+    let apps = merge_by_app_id(chunks, &state.priority);
     if apps.is_empty() && warnings.len() == plugin_count && plugin_count > 0 {
         return Err(HostError::AllFailed(warnings));
     }
@@ -118,84 +631,2880 @@ async fn collect_popular(
     Ok(HostResponse { data: apps, warnings })
 }
 
-fn run_plugin(
+async fn collect_repositories(
+    state: Arc<HostState>,
+    cancel: CancellationHandle,
+) -> Result<HostResponse<Vec<Repository>>, HostError> {
+    let call: BackendCall<Vec<Repository>> = Arc::new(|backend, cancel| {
+        async move { backend.list_repositories(&cancel).await }.boxed()
+    });
+    let response = collect_per_plugin(state, cancel, call).await?;
+
+    let repositories = response
+        .data
+        .into_iter()
+        .flat_map(|(plugin_id, listed)| {
+            listed.into_iter().map(move |repository| Repository {
+                plugin: plugin_id.clone(),
+                ..repository
+            })
+        })
+        .collect();
+    Ok(HostResponse {
+        data: repositories,
+        warnings: response.warnings,
+    })
+}
+
+async fn collect_categories(
+    state: Arc<HostState>,
+    cancel: CancellationHandle,
+) -> Result<HostResponse<Vec<Category>>, HostError> {
+    let call: BackendCall<Vec<Category>> = Arc::new(|backend, cancel| {
+        async move { backend.list_categories(&cancel).await }.boxed()
+    });
+    let mut response = collect_per_plugin(state.clone(), cancel, call).await?;
+
+    let rank = |plugin: &str| {
+        state
+            .priority
+            .iter()
+            .position(|id| id == plugin)
+            .unwrap_or(state.priority.len())
+    };
+    response.data.sort_by_key(|(plugin_id, _)| rank(plugin_id));
+
+    let mut seen = HashSet::new();
+    let categories = response
+        .data
+        .into_iter()
+        .flat_map(|(_, listed)| listed)
+        .filter(|category| seen.insert(category.id.clone()))
+        .collect();
+    Ok(HostResponse {
+        data: categories,
+        warnings: response.warnings,
+    })
+}
+
+async fn collect_updates(
+    state: Arc<HostState>,
+    cancel: CancellationHandle,
+) -> Result<HostResponse<Vec<AppUpdate>>, HostError> {
+    let call: BackendCall<Vec<AppUpdate>> = Arc::new(|backend, cancel| {
+        async move { backend.list_updates(&cancel).await }.boxed()
+    });
+    let response = collect_per_plugin(state, cancel, call).await?;
+
+    let mut updates = Vec::new();
+    for (plugin_id, listed) in response.data {
+        updates.extend(listed.into_iter().map(|update| AppUpdate {
+            plugin: plugin_id.clone(),
+            ..update
+        }));
+    }
+    Ok(HostResponse {
+        data: updates,
+        warnings: response.warnings,
+    })
+}
+
+/// Runs `call` on every backend at once and returns what each successful
+/// backend answered, tagged with its id, in registration order.
+async fn collect_per_plugin<T: Send + 'static>(
+    state: Arc<HostState>,
+    cancel: CancellationHandle,
+    call: BackendCall<T>,
+) -> Result<HostResponse<Vec<(String, T)>>, HostError> {
+    let plugin_count = state.registry.len();
+    let tasks = state
+        .registry
+        .backends()
+        .cloned()
+        .map(|backend| {
+            let timeout = state.timeouts.for_plugin(&backend.descriptor().id);
+            run_plugin(state.clone(), backend, timeout, cancel.clone(), call.clone())
+        })
+        .collect::<Vec<_>>();
+
+    let results = tokio::select! {
+        results = join_all(tasks) => results,
+        _ = cancel.cancelled() => return Err(HostError::Cancelled),
+    };
+    if cancel.is_cancelled() {
+        return Err(HostError::Cancelled);
+    }
+
+    let mut data = Vec::new();
+    let mut warnings = Vec::new();
+    for (backend, result) in state.registry.backends().zip(results) {
+        match result {
+            Ok(answer) => data.push((backend.descriptor().id.clone(), answer)),
+            Err(failure) => warnings.push(failure),
+        }
+    }
+
+    if warnings.len() == plugin_count && plugin_count > 0 {
+        return Err(HostError::AllFailed(warnings));
+    }
+    Ok(HostResponse { data, warnings })
+}
+
+async fn stream_apps(
+    state: Arc<HostState>,
+    cancel: CancellationHandle,
+    call: BackendCall<Vec<AppSummary>>,
+    tx: async_channel::Sender<Result<HostEvent, HostError>>,
+) {
+    let plugin_count = state.registry.len();
+    let mut tasks = state
+        .registry
+        .backends()
+        .cloned()
+        .map(|backend| {
+            let plugin_id = backend.descriptor().id.clone();
+            let timeout = state.timeouts.for_plugin(&plugin_id);
+            run_plugin(state.clone(), backend, timeout, cancel.clone(), call.clone())
+                .map(move |result| (plugin_id, result))
+        })
+        .collect::<FuturesUnordered<_>>();
+
+    // Every entry reported so far per app id, so each chunk only re-merges
+    // the apps it mentions.
+    let mut seen: HashMap<String, Vec<(String, AppSummary)>> = HashMap::new();
+    let mut answered = false;
+    let mut failures = Vec::new();
+
+    loop {
+        let next = tokio::select! {
+            next = tasks.next() => next,
+            _ = cancel.cancelled() => None,
+        };
+        if cancel.is_cancelled() {
+            let _ = tx.send(Err(HostError::Cancelled)).await;
+            return;
+        }
+        let Some((plugin_id, result)) = next else {
+            break;
+        };
+
+        let event = match result {
+            Ok(chunk) => {
+                answered = true;
+                let mut reported = Vec::new();
+                let mut known = HashSet::new();
+                for app in chunk {
+                    if known.insert(app.app_id.clone()) {
+                        reported.push(app.app_id.clone());
+                    }
+                    let entries = seen.entry(app.app_id.clone()).or_default();
+                    entries.push((plugin_id.clone(), app));
+                }
+
+                let apps = reported
+                    .iter()
+                    .flat_map(|app_id| {
+                        let chunks = seen[app_id]
+                            .iter()
+                            .map(|(plugin, app)| (plugin.clone(), vec![app.clone()]))
+                            .collect();
+                        merge_by_app_id(chunks, &state.priority)
+                    })
+                    .collect();
+                HostEvent::Apps {
+                    plugin: plugin_id,
+                    apps,
+                }
+            }
+            Err(failure) => {
+                failures.push(failure.clone());
+                HostEvent::Failed(failure)
+            }
+        };
+        if tx.send(Ok(event)).await.is_err() {
+            // Nobody is listening any more.
+            return;
+        }
+    }
+
+    if !answered && failures.len() == plugin_count && plugin_count > 0 {
+        let _ = tx.send(Err(HostError::AllFailed(failures))).await;
+    }
+}
+
+/// Groups per-plugin results by `app_id`. The entry from the plugin earliest
+/// in `priority` supplies the metadata; every `(plugin, source)` pair is kept.
+/// An app is installed if any plugin says so. Apps keep the order in which
+/// they were first seen.
+fn merge_by_app_id(chunks: Vec<(String, Vec<AppSummary>)>, priority: &[String]) -> Vec<MergedApp> {
+    let rank = |plugin: &str| {
+        priority
+            .iter()
+            .position(|id| id == plugin)
+            .unwrap_or(priority.len())
+    };
+
+    let mut order = Vec::new();
+    let mut groups: HashMap<String, Vec<(usize, String, AppSummary)>> = HashMap::new();
+    for (plugin, apps) in chunks {
+        let plugin_rank = rank(&plugin);
+        for app in apps {
+            let group = groups.entry(app.app_id.clone()).or_insert_with(|| {
+                order.push(app.app_id.clone());
+                Vec::new()
+            });
+            group.push((plugin_rank, plugin.clone(), app));
+        }
+    }
+
+    order
+        .into_iter()
+        .filter_map(|app_id| {
+            let mut group = groups.remove(&app_id)?;
+            group.sort_by_key(|(plugin_rank, _, _)| *plugin_rank);
+
+            let mut sources: Vec<AppSource> = Vec::with_capacity(group.len());
+            for (_, plugin, app) in &group {
+                if !sources
+                    .iter()
+                    .any(|known| known.plugin == *plugin && known.source == app.source)
+                {
+                    sources.push(AppSource {
+                        plugin: plugin.clone(),
+                        source: app.source.clone(),
+                    });
+                }
+            }
+
+            let installed = group.iter().any(|(_, _, app)| app.installed);
+            let (_, _, mut primary) = group.into_iter().next()?;
+            primary.installed = installed;
+            Some(MergedApp { primary, sources })
+        })
+        .collect()
+}
+
+/// Runs `call` on the one backend an operation was routed to.
+async fn run_single<T: Send + 'static>(
+    state: Arc<HostState>,
+    backend: Arc<dyn PluginBackend>,
+    cancel: CancellationHandle,
+    call: BackendCall<T>,
+) -> Result<T, HostError> {
+    let timeout = state.timeouts.for_plugin(&backend.descriptor().id);
+    let result = tokio::select! {
+        result = run_plugin(state, backend, timeout, cancel.clone(), call) => result,
+        _ = cancel.cancelled() => return Err(HostError::Cancelled),
+    };
+    result.map_err(|failure| match failure.kind {
+        PluginFailureKind::Execution(PluginExecutionError::Cancelled) => HostError::Cancelled,
+        _ => HostError::Plugin(failure),
+    })
+}
+
+/// Runs one backend call with panic isolation and the configured timeout,
+/// recording the outcome in the registry. Disabled and quarantined plugins
+/// are skipped.
+fn run_plugin<T: Send + 'static>(
+    state: Arc<HostState>,
     backend: Arc<dyn PluginBackend>,
-) -> BoxFuture<'static, Result<Vec<AppSummary>, PluginFailure>> {
+    timeout: Option<Duration>,
+    cancel: CancellationHandle,
+    call: BackendCall<T>,
+) -> BoxFuture<'static, Result<T, PluginFailure>> {
     async move {
         let descriptor: PluginDescriptor = backend.descriptor().clone();
         let plugin_name = descriptor.id.clone();
         let plugin_kind = descriptor.kind;
 
-        let result = AssertUnwindSafe(backend.list_popular_apps())
-            .catch_unwind()
-            .await;
-
-        match result {
-            Ok(Ok(apps)) => Ok(apps),
-            Ok(Err(kind)) => Err(PluginFailure {
-                plugin: plugin_name,
-                plugin_kind,
-                kind: PluginFailureKind::Execution(kind),
-            }),
-            Err(_) => Err(PluginFailure {
+        if let Err(kind) = state.registry.admit(&plugin_name) {
+            return Err(PluginFailure {
                 plugin: plugin_name,
                 plugin_kind,
-                kind: PluginFailureKind::Panic,
-            }),
+                kind,
+            });
         }
+
+        let pending = PendingCall::new(state, &plugin_name);
+        let call = AssertUnwindSafe(call(backend, cancel)).catch_unwind();
+        let result = match timeout {
+            // The backend future is dropped on expiry; legacy work already
+            // handed to `spawn_blocking` finishes in the background.
+            Some(limit) => match tokio::time::timeout(limit, call).await {
+                Ok(result) => result,
+                Err(_) => Ok(Err(PluginExecutionError::Timeout(limit))),
+            },
+            None => call.await,
+        };
+
+        let result = match result {
+            Ok(Ok(data)) => Ok(data),
+            Ok(Err(err)) => Err(PluginFailureKind::from(err)),
+            Err(_) => Err(PluginFailureKind::Panic),
+        };
+        pending.finish(result.as_ref().map(|_| ()));
+
+        result.map_err(|kind| PluginFailure {
+            plugin: plugin_name,
+            plugin_kind,
+            kind,
+        })
     }
     .boxed()
 }
 
+/// An admitted call whose outcome has not been recorded yet. Dropping it
+/// unfinished, e.g. when a cancelled fan-out stops polling the call, records
+/// it as cancelled so a half-open plugin is not left waiting for its probe.
+struct PendingCall {
+    state: Arc<HostState>,
+    plugin: String,
+    started: Instant,
+    recorded: bool,
+}
+
+impl PendingCall {
+    fn new(state: Arc<HostState>, plugin: &str) -> Self {
+        Self {
+            state,
+            plugin: plugin.to_string(),
+            started: Instant::now(),
+            recorded: false,
+        }
+    }
+
+    fn finish(mut self, outcome: Result<(), &PluginFailureKind>) {
+        self.record(outcome);
+    }
+
+    fn record(&mut self, outcome: Result<(), &PluginFailureKind>) {
+        self.recorded = true;
+        self.state
+            .registry
+            .record(&self.plugin, outcome, self.started.elapsed());
+    }
+}
+
+impl Drop for PendingCall {
+    fn drop(&mut self) {
+        if !self.recorded {
+            self.record(Err(&PluginFailureKind::Execution(
+                PluginExecutionError::Cancelled,
+            )));
+        }
+    }
+}
+
+/// Cached apps younger than this are served without asking the backends.
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+const STORE_EVENT_CAPACITY: usize = 64;
+
 #[derive(Clone)]
 pub struct AppStoreService {
     host: PluginHost,
-    cache: Arc<RwLock<Vec<AppSummary>>>,
+    store: Arc<dyn CacheStore>,
+    cache: Arc<RwLock<Vec<MergedApp>>>,
     warnings: Arc<RwLock<Vec<PluginFailure>>>,
+    /// App ids reported by [`PluginHost::list_installed`] in the last refresh.
+    installed: Arc<RwLock<HashSet<String>>>,
+    /// When each plugin that answered the last completed refresh did so.
+    refreshed: Arc<RwLock<HashMap<String, SystemTime>>>,
+    /// Repositories listed by the last refresh, kept per plugin when that
+    /// plugin failed to list them.
+    repositories: Arc<RwLock<Vec<Repository>>>,
+    /// Updates found by the last check, kept per plugin when that plugin
+    /// failed to list them.
+    updates: Arc<RwLock<Vec<AppUpdate>>>,
+    update_warnings: Arc<RwLock<Vec<PluginFailure>>>,
+    updates_checked: Arc<RwLock<Option<SystemTime>>>,
+    /// Categories and the apps in each, with when they were fetched. Kept in
+    /// memory only and served while younger than the max age.
+    categories: Arc<RwLock<Option<(SystemTime, CategoryListing)>>>,
+    category_apps: Arc<RwLock<HashMap<String, (SystemTime, CategoryApps)>>>,
+    /// Matches of the last search, so they can be looked up like cached apps.
+    search_results: Arc<RwLock<Vec<MergedApp>>>,
+    max_age: Duration,
+    scheduler: RefreshScheduler,
+    events: broadcast::Sender<StoreEvent>,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct RefreshOutcome {
-    pub apps: Vec<AppSummary>,
+    pub apps: Vec<MergedApp>,
+    pub warnings: Vec<PluginFailure>,
+    /// Time since the oldest plugin data in `apps` was fetched; `None` when
+    /// nothing has been fetched yet. Serialized as whole seconds.
+    #[serde(serialize_with = "serialize_secs")]
+    pub cache_age: Option<Duration>,
+}
+
+/// A change to the data held by an [`AppStoreService`], delivered to every
+/// [`AppStoreService::subscribe`] receiver.
+#[derive(Debug, Clone)]
+pub enum StoreEvent {
+    /// A refresh began, whether started by hand or by the scheduler.
+    RefreshStarted,
+    /// Apps whose `app_id` was not cached before.
+    AppsAdded(Vec<MergedApp>),
+    /// Cached apps whose metadata, sources or installed flag changed.
+    AppsChanged(Vec<MergedApp>),
+    /// Ids of apps dropped from the cache.
+    AppsRemoved(Vec<String>),
+    /// The persisted plugin warnings were replaced by these.
+    WarningsChanged(Vec<PluginFailure>),
+    /// A refresh found repositories added, removed or changed since the
+    /// previous one. Plugins listed for the first time report nothing.
+    RepositoriesChanged(Vec<RepositoryChange>),
+    /// The pending updates are now these, after a check or a finished update.
+    UpdatesChanged(Vec<AppUpdate>),
+    /// The refresh ended. A refresh that is dropped before finishing ends
+    /// with [`HostError::Cancelled`].
+    RefreshFinished(Result<(), HostError>),
+}
+
+/// How a repository differs from what the previous refresh listed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepositoryChange {
+    Added(Repository),
+    Removed(Repository),
+    /// Enabled, disabled, renamed or moved to another URL.
+    Changed(Repository),
+}
+
+impl RepositoryChange {
+    pub fn repository(&self) -> &Repository {
+        match self {
+            Self::Added(repository) | Self::Removed(repository) | Self::Changed(repository) => {
+                repository
+            }
+        }
+    }
+}
+
+/// Incremental progress of [`AppStoreService::refresh_popular_stream`].
+#[derive(Clone)]
+pub enum RefreshEvent {
+    /// Apps added or updated by one backend; already applied to the cache.
+    Apps(Vec<MergedApp>),
+    Warning(PluginFailure),
+    /// Every backend has answered. Apps no backend reported any more have
+    /// been dropped from the cache, and the result has been persisted.
+    Finished(RefreshOutcome),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateOutcome {
+    pub updates: Vec<AppUpdate>,
+    pub warnings: Vec<PluginFailure>,
+    /// Time since the updates were checked; `None` when they never were.
+    /// Serialized as whole seconds.
+    #[serde(serialize_with = "serialize_secs")]
+    pub checked_age: Option<Duration>,
+}
+
+/// Progress of one app within [`AppStoreService::update_all`].
+#[derive(Debug, Clone)]
+pub struct UpdateProgress {
+    pub update: AppUpdate,
+    pub progress: Result<JobProgress, HostError>,
+}
+
+/// Result of [`AppStoreService::refresh_repositories`].
+#[derive(Debug, Clone, Serialize)]
+pub struct RepositoryListing {
+    pub repositories: Vec<Repository>,
+    pub warnings: Vec<PluginFailure>,
+}
+
+/// Result of [`AppStoreService::categories`].
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryListing {
+    pub categories: Vec<Category>,
+    pub warnings: Vec<PluginFailure>,
+}
+
+/// Result of [`AppStoreService::category_apps`].
+#[derive(Clone, Serialize)]
+pub struct CategoryApps {
+    pub category_id: String,
+    pub apps: Vec<MergedApp>,
+    pub warnings: Vec<PluginFailure>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SearchOutcome {
+    pub query: String,
+    /// Best match first; each `app_id` appears once.
+    pub apps: Vec<MergedApp>,
     pub warnings: Vec<PluginFailure>,
 }
 
 impl AppStoreService {
-    pub fn new(host: PluginHost) -> Self {
+    pub fn new(host: PluginHost, store: Arc<dyn CacheStore>) -> Self {
+        let apps = store.load_apps().unwrap_or_else(|err| {
+            eprintln!("InstallGrid: failed to load cached applications: {err}");
+            Vec::new()
+        });
+        let warnings = store.load_warnings().unwrap_or_else(|err| {
+            eprintln!("InstallGrid: failed to load cached plugin warnings: {err}");
+            Vec::new()
+        });
+
+        let (events, _) = broadcast::channel(STORE_EVENT_CAPACITY);
+        let refreshed = store.load_refresh_times().unwrap_or_else(|err| {
+            eprintln!("InstallGrid: failed to load cache refresh times: {err}");
+            HashMap::new()
+        });
+        let repositories = store.load_repositories().unwrap_or_else(|err| {
+            eprintln!("InstallGrid: failed to load cached repositories: {err}");
+            Vec::new()
+        });
+        let (updates, updates_checked) = store.load_updates().unwrap_or_else(|err| {
+            eprintln!("InstallGrid: failed to load cached updates: {err}");
+            (Vec::new(), None)
+        });
+
+        let installed = apps
+            .iter()
+            .filter(|app| app.primary.installed)
+            .map(|app| app.app_id().to_string())
+            .collect();
+
         Self {
             host,
-            cache: Arc::new(RwLock::new(Vec::new())),
-            warnings: Arc::new(RwLock::new(Vec::new())),
+            store,
+            cache: Arc::new(RwLock::new(apps)),
+            warnings: Arc::new(RwLock::new(warnings)),
+            installed: Arc::new(RwLock::new(installed)),
+            refreshed: Arc::new(RwLock::new(refreshed)),
+            repositories: Arc::new(RwLock::new(repositories)),
+            updates: Arc::new(RwLock::new(updates)),
+            update_warnings: Arc::new(RwLock::new(Vec::new())),
+            updates_checked: Arc::new(RwLock::new(updates_checked)),
+            categories: Arc::new(RwLock::new(None)),
+            category_apps: Arc::new(RwLock::new(HashMap::new())),
+            search_results: Arc::new(RwLock::new(Vec::new())),
+            max_age: DEFAULT_MAX_AGE,
+            scheduler: RefreshScheduler::new(RefreshSchedule::default()),
+            events,
+        }
+    }
+
+    /// Changes to the cache from now on. A receiver that falls behind gets
+    /// `RecvError::Lagged` and should re-read [`AppStoreService::cache_snapshot`].
+    pub fn subscribe(&self) -> broadcast::Receiver<StoreEvent> {
+        self.events.subscribe()
+    }
+
+    fn emit(&self, event: StoreEvent) {
+        // Nobody listening is fine.
+        let _ = self.events.send(event);
+    }
+
+    /// Emits what replacing `before` with `after` added, changed and removed.
+    fn emit_diff(&self, before: &[MergedApp], after: &[MergedApp]) {
+        let (added, changed) = classify_updates(before, after);
+        let kept = after.iter().map(MergedApp::app_id).collect::<HashSet<_>>();
+        let removed = before
+            .iter()
+            .filter(|app| !kept.contains(app.app_id()))
+            .map(|app| app.app_id().to_string())
+            .collect::<Vec<_>>();
+
+        if !added.is_empty() {
+            self.emit(StoreEvent::AppsAdded(added));
         }
+        if !changed.is_empty() {
+            self.emit(StoreEvent::AppsChanged(changed));
+        }
+        if !removed.is_empty() {
+            self.emit(StoreEvent::AppsRemoved(removed));
+        }
+    }
+
+    /// Jitter and backoff of the background refresh started by
+    /// [`AppStoreService::start_scheduler`].
+    pub fn with_schedule(mut self, schedule: RefreshSchedule) -> Self {
+        self.scheduler = RefreshScheduler::new(schedule);
+        self
+    }
+
+    /// Starts refreshing in the background on the host runtime each time the
+    /// cache reaches its max age. Calling it again has no effect.
+    pub fn start_scheduler(&self) {
+        self.scheduler.start(self.clone(), &self.host.handle);
+    }
+
+    /// Pause, resume and event subscription for the background refresh.
+    pub fn scheduler(&self) -> &RefreshScheduler {
+        &self.scheduler
+    }
+
+    /// How long cached apps are served before [`AppStoreService::load_popular`]
+    /// asks the backends again. Defaults to one hour.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    pub fn max_age(&self) -> Duration {
+        self.max_age
     }
 
     pub fn cache_snapshot(&self) -> RefreshOutcome {
         RefreshOutcome {
             apps: self.cache.read().clone(),
             warnings: self.warnings.read().clone(),
+            cache_age: self.cache_age(),
         }
     }
 
-    pub async fn refresh_popular(&self) -> Result<RefreshOutcome, HostError> {
-        let response = self.host.list_popular().await?;
+    /// Age of the oldest plugin data in the cache.
+    pub fn cache_age(&self) -> Option<Duration> {
+        let oldest = self.refreshed.read().values().min().copied()?;
+        // A clock that went backwards makes the cache look brand new.
+        Some(oldest.elapsed().unwrap_or_default())
+    }
 
-        {
-            let mut cache = self.cache.write();
-            *cache = response.data.clone();
-        }
+    /// When each plugin's cached apps were fetched.
+    pub fn refresh_times(&self) -> HashMap<String, SystemTime> {
+        self.refreshed.read().clone()
+    }
 
-        {
-            let mut warnings = self.warnings.write();
-            *warnings = response.warnings.clone();
+    /// Whether the cache has never been filled or some plugin's data is older
+    /// than the max age.
+    pub fn is_stale(&self) -> bool {
+        self.cache_age().is_none_or(|age| age >= self.max_age)
+    }
+
+    /// Serves the cache while it is fresh and refreshes otherwise. Callers
+    /// that can show stale data first should use
+    /// [`AppStoreService::cache_snapshot`] and, if [`AppStoreService::is_stale`],
+    /// revalidate with [`AppStoreService::refresh_popular_stream`].
+    pub async fn load_popular(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<RefreshOutcome, HostError> {
+        if self.is_stale() {
+            self.refresh_popular(cancel).await
+        } else {
+            Ok(self.cache_snapshot())
         }
+    }
 
-        Ok(RefreshOutcome {
-            apps: response.data,
-            warnings: response.warnings,
-        })
+    /// Repositories as of the last refresh.
+    pub fn repositories(&self) -> Vec<Repository> {
+        self.repositories.read().clone()
     }
 
-    pub fn plugin_count(&self) -> usize {
-        self.host.plugins.len()
+    /// Lists repositories without refreshing apps, reporting changes like a
+    /// refresh would.
+    pub async fn refresh_repositories(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<RepositoryListing, HostError> {
+        let response = self.host.list_repositories(cancel.clone()).await?;
+        let warnings = response.warnings.clone();
+        let baseline = self.refresh_times();
+        self.update_repositories(response, &baseline);
+
+        Ok(RepositoryListing {
+            repositories: self.repositories(),
+            warnings,
+        })
+    }
+
+    /// The known repository `repository_id`. When several plugins list one
+    /// with that id, `plugin` picks one; otherwise the first listed wins.
+    pub fn find_repository(
+        &self,
+        repository_id: &str,
+        plugin: Option<&str>,
+    ) -> Result<Repository, HostError> {
+        self.repositories
+            .read()
+            .iter()
+            .find(|repository| {
+                repository.id == repository_id
+                    && plugin.is_none_or(|plugin| repository.plugin == plugin)
+            })
+            .cloned()
+            .ok_or_else(|| HostError::UnknownRepository(repository_id.to_string()))
+    }
+
+    /// Adds the repository in a `.flatpakrepo` file; see
+    /// [`PluginHost::add_repository`]. Emits
+    /// [`StoreEvent::RepositoriesChanged`] on success.
+    pub async fn add_repository(
+        &self,
+        file: &Path,
+        plugin: Option<&str>,
+        cancel: &CancellationHandle,
+    ) -> Result<Repository, HostError> {
+        let repository = self
+            .host
+            .add_repository(file, plugin, cancel.clone())
+            .await?;
+        self.apply_repository_change(RepositoryChange::Added(repository.clone()));
+        Ok(repository)
+    }
+
+    /// Enables, disables or removes a known repository. Emits
+    /// [`StoreEvent::RepositoriesChanged`] on success.
+    pub async fn manage_repository(
+        &self,
+        repository: &Repository,
+        action: RepositoryAction,
+        cancel: &CancellationHandle,
+    ) -> Result<(), HostError> {
+        self.host
+            .manage_repository(repository, action, cancel.clone())
+            .await?;
+
+        let change = match action {
+            RepositoryAction::Enable | RepositoryAction::Disable => {
+                RepositoryChange::Changed(Repository {
+                    enabled: action == RepositoryAction::Enable,
+                    ..repository.clone()
+                })
+            }
+            RepositoryAction::Remove => RepositoryChange::Removed(repository.clone()),
+        };
+        self.apply_repository_change(change);
+        Ok(())
+    }
+
+    fn apply_repository_change(&self, change: RepositoryChange) {
+        {
+            let mut repositories = self.repositories.write();
+            let changed = change.repository();
+            let index = repositories.iter().position(|repository| {
+                repository.plugin == changed.plugin && repository.id == changed.id
+            });
+            match (&change, index) {
+                (RepositoryChange::Removed(_), Some(index)) => {
+                    repositories.remove(index);
+                }
+                (RepositoryChange::Removed(_), None) => {}
+                (_, Some(index)) => repositories[index] = changed.clone(),
+                (_, None) => repositories.push(changed.clone()),
+            }
+            if let Err(err) = self.store.store_repositories(&repositories) {
+                eprintln!("InstallGrid: failed to persist repositories: {err}");
+            }
+        }
+        self.emit(StoreEvent::RepositoriesChanged(vec![change]));
+    }
+
+    /// Categories fetched within the max age, or else freshly listed.
+    pub async fn categories(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<CategoryListing, HostError> {
+        if let Some((fetched, listing)) = self.categories.read().as_ref() {
+            if self.is_fresh(*fetched) {
+                return Ok(listing.clone());
+            }
+        }
+
+        let response = self.host.list_categories(cancel.clone()).await?;
+        let listing = CategoryListing {
+            categories: response.data,
+            warnings: response.warnings,
+        };
+        *self.categories.write() = Some((SystemTime::now(), listing.clone()));
+        Ok(listing)
+    }
+
+    /// Apps in `category_id`, fetched within the max age or else freshly
+    /// listed. Apps are marked installed like cached ones; they are not
+    /// added to the cache.
+    pub async fn category_apps(
+        &self,
+        category_id: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<CategoryApps, HostError> {
+        if let Some((fetched, apps)) = self.category_apps.read().get(category_id) {
+            if self.is_fresh(*fetched) {
+                return Ok(apps.clone());
+            }
+        }
+
+        let response = self
+            .host
+            .list_category_apps(category_id, cancel.clone())
+            .await?;
+        let mut apps = response.data;
+        mark_installed(&mut apps, &self.installed.read());
+        let listing = CategoryApps {
+            category_id: category_id.to_string(),
+            apps,
+            warnings: response.warnings,
+        };
+        self.category_apps
+            .write()
+            .insert(category_id.to_string(), (SystemTime::now(), listing.clone()));
+        Ok(listing)
+    }
+
+    fn is_fresh(&self, fetched: SystemTime) -> bool {
+        // A clock that went backwards makes the data look brand new.
+        fetched.elapsed().unwrap_or_default() < self.max_age
+    }
+
+    pub fn cached_app(&self, app_id: &str) -> Option<MergedApp> {
+        self.cache
+            .read()
+            .iter()
+            .find(|app| app.app_id() == app_id)
+            .cloned()
+    }
+
+    /// A cached app, or else one listed by a category fetched earlier or by
+    /// the last search.
+    pub fn find_app(&self, app_id: &str) -> Option<MergedApp> {
+        self.cached_app(app_id)
+            .or_else(|| {
+                self.category_apps
+                    .read()
+                    .values()
+                    .flat_map(|(_, listing)| &listing.apps)
+                    .find(|app| app.app_id() == app_id)
+                    .cloned()
+            })
+            .or_else(|| {
+                self.search_results
+                    .read()
+                    .iter()
+                    .find(|app| app.app_id() == app_id)
+                    .cloned()
+            })
+    }
+
+    /// Empties the in-memory and persisted cache, categories included.
+    /// Repositories are forgotten without a
+    /// [`StoreEvent::RepositoriesChanged`]; the next refresh lists them as if
+    /// for the first time.
+    pub fn clear_cache(&self) -> Result<(), CacheError> {
+        let apps = std::mem::take(&mut *self.cache.write());
+        let warnings = std::mem::take(&mut *self.warnings.write());
+        self.refreshed.write().clear();
+        self.repositories.write().clear();
+        let updates = std::mem::take(&mut *self.updates.write());
+        self.update_warnings.write().clear();
+        self.updates_checked.write().take();
+        self.categories.write().take();
+        self.category_apps.write().clear();
+        self.emit_diff(&apps, &[]);
+        if !warnings.is_empty() {
+            self.emit(StoreEvent::WarningsChanged(Vec::new()));
+        }
+        if !updates.is_empty() {
+            self.emit(StoreEvent::UpdatesChanged(Vec::new()));
+        }
+        self.store.store_apps(&[])?;
+        self.store.store_warnings(&[])?;
+        self.store.store_repositories(&[])?;
+        self.store.store_updates(&[], None)?;
+        self.store.store_refresh_times(&HashMap::new())
+    }
+
+    /// Details stored by an earlier [`AppStoreService::app_details`] call.
+    pub fn cached_details(&self, app_id: &str) -> Option<AppDetails> {
+        self.store.load_details(app_id).unwrap_or_else(|err| {
+            eprintln!("InstallGrid: failed to load cached details for {app_id}: {err}");
+            None
+        })
+    }
+
+    /// Fetches full details for an app known to [`AppStoreService::find_app`],
+    /// trying its sources in priority order, and writes them through to the
+    /// store. An app without sources is as unknown as one never listed.
+    pub async fn app_details(
+        &self,
+        app_id: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<AppDetails, HostError> {
+        let app = self
+            .find_app(app_id)
+            .filter(|app| !app.sources.is_empty())
+            .ok_or_else(|| HostError::UnknownApp(app_id.to_string()))?;
+
+        let mut failures = Vec::new();
+        for source in &app.sources {
+            match self
+                .host
+                .app_details(app.app_id(), source, cancel.clone())
+                .await
+            {
+                Ok(details) => {
+                    if let Err(err) = self.store.store_details(&details) {
+                        eprintln!("InstallGrid: failed to persist details for {app_id}: {err}");
+                    }
+                    return Ok(details);
+                }
+                Err(HostError::Plugin(failure)) => failures.push(failure),
+                Err(err) => return Err(err),
+            }
+        }
+        Err(HostError::AllFailed(failures))
+    }
+
+    /// Refreshes popular apps and waits for every backend. See
+    /// [`AppStoreService::refresh_popular_stream`] for how the cache changes.
+    pub async fn refresh_popular(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<RefreshOutcome, HostError> {
+        let mut events = self.refresh_popular_stream(cancel);
+        while let Some(event) = events.next().await {
+            if let RefreshEvent::Finished(outcome) = event? {
+                return Ok(outcome);
+            }
+        }
+        Err(HostError::RuntimeUnavailable)
+    }
+
+    /// Refreshes popular apps, upserting each backend's results into the
+    /// in-memory cache as they arrive. Only a refresh that runs to
+    /// [`RefreshEvent::Finished`] prunes stale apps and writes to the store;
+    /// a cancelled or failed one leaves the persisted cache untouched. Apps,
+    /// sources and refresh times of plugins that fail are kept.
+    ///
+    /// Installed apps are looked up alongside. Incremental events are marked
+    /// with the previous refresh's answer; the finished outcome uses the new
+    /// one. Repositories are listed alongside too, and compared with the
+    /// previous refresh once it finishes.
+    pub fn refresh_popular_stream(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> BoxStream<'static, Result<RefreshEvent, HostError>> {
+        type Lookup<T> = tokio::task::JoinHandle<Result<HostResponse<Vec<T>>, HostError>>;
+
+        struct Progress {
+            events: HostEvents,
+            installed: Lookup<MergedApp>,
+            repositories: Lookup<Repository>,
+            service: AppStoreService,
+            fresh: Vec<MergedApp>,
+            warnings: Vec<PluginFailure>,
+            answered: HashMap<String, SystemTime>,
+            finished: FinishGuard,
+        }
+
+        /// Sends [`StoreEvent::RefreshFinished`] exactly once, even when the
+        /// stream is dropped half-way.
+        struct FinishGuard {
+            events: broadcast::Sender<StoreEvent>,
+            sent: bool,
+        }
+
+        impl FinishGuard {
+            fn finish(&mut self, result: Result<(), HostError>) {
+                self.sent = true;
+                let _ = self.events.send(StoreEvent::RefreshFinished(result));
+            }
+        }
+
+        impl Drop for FinishGuard {
+            fn drop(&mut self) {
+                if !self.sent {
+                    self.finish(Err(HostError::Cancelled));
+                }
+            }
+        }
+
+        self.emit(StoreEvent::RefreshStarted);
+        let progress = Progress {
+            events: self.host.list_popular_stream(cancel.clone()),
+            installed: self
+                .host
+                .handle
+                .spawn(self.host.list_installed(cancel.clone())),
+            repositories: self
+                .host
+                .handle
+                .spawn(self.host.list_repositories(cancel.clone())),
+            service: self.clone(),
+            fresh: Vec::new(),
+            warnings: Vec::new(),
+            answered: HashMap::new(),
+            finished: FinishGuard {
+                events: self.events.clone(),
+                sent: false,
+            },
+        };
+
+        stream::unfold(Some(progress), |progress| async move {
+            let mut progress = progress?;
+            match progress.events.next().await {
+                Some(Ok(HostEvent::Apps { plugin, mut apps })) => {
+                    progress.answered.insert(plugin, SystemTime::now());
+                    mark_installed(&mut apps, &progress.service.installed.read());
+                    upsert_apps(&mut progress.fresh, &apps);
+                    let (apps, added, changed) = {
+                        let mut cache = progress.service.cache.write();
+                        let apps = apps
+                            .into_iter()
+                            .map(|app| {
+                                match cache.iter().find(|cached| cached.app_id() == app.app_id()) {
+                                    Some(cached) => keep_pending_sources(
+                                        app,
+                                        cached,
+                                        |plugin| !progress.answered.contains_key(plugin),
+                                        &progress.service.host.state.priority,
+                                    ),
+                                    None => app,
+                                }
+                            })
+                            .collect::<Vec<_>>();
+                        let (added, changed) = classify_updates(&cache, &apps);
+                        upsert_apps(&mut cache, &apps);
+                        (apps, added, changed)
+                    };
+                    if !added.is_empty() {
+                        progress.service.emit(StoreEvent::AppsAdded(added));
+                    }
+                    if !changed.is_empty() {
+                        progress.service.emit(StoreEvent::AppsChanged(changed));
+                    }
+                    Some((Ok(RefreshEvent::Apps(apps)), Some(progress)))
+                }
+                Some(Ok(HostEvent::Failed(failure))) => {
+                    progress.warnings.push(failure.clone());
+                    Some((Ok(RefreshEvent::Warning(failure)), Some(progress)))
+                }
+                Some(Err(err)) => {
+                    progress.installed.abort();
+                    progress.repositories.abort();
+                    progress.finished.finish(Err(err.clone()));
+                    Some((Err(err), None))
+                }
+                None => {
+                    let installed = progress
+                        .installed
+                        .await
+                        .unwrap_or(Err(HostError::RuntimeUnavailable));
+                    match installed {
+                        Ok(response) => {
+                            *progress.service.installed.write() = response
+                                .data
+                                .iter()
+                                .map(|app| app.app_id().to_string())
+                                .collect();
+                            add_warnings(&mut progress.warnings, response.warnings);
+                        }
+                        Err(HostError::Cancelled) => {
+                            progress.repositories.abort();
+                            progress.finished.finish(Err(HostError::Cancelled));
+                            return Some((Err(HostError::Cancelled), None));
+                        }
+                        Err(HostError::AllFailed(failures)) => {
+                            add_warnings(&mut progress.warnings, failures);
+                        }
+                        Err(err) => {
+                            eprintln!("InstallGrid: failed to look up installed apps: {err}");
+                        }
+                    }
+
+                    let repositories = match progress
+                        .repositories
+                        .await
+                        .unwrap_or(Err(HostError::RuntimeUnavailable))
+                    {
+                        Ok(response) => {
+                            add_warnings(&mut progress.warnings, response.warnings.clone());
+                            Some(response)
+                        }
+                        Err(HostError::Cancelled) => {
+                            progress.finished.finish(Err(HostError::Cancelled));
+                            return Some((Err(HostError::Cancelled), None));
+                        }
+                        Err(HostError::AllFailed(failures)) => {
+                            add_warnings(&mut progress.warnings, failures);
+                            None
+                        }
+                        Err(err) => {
+                            eprintln!("InstallGrid: failed to list repositories: {err}");
+                            None
+                        }
+                    };
+
+                    mark_installed(&mut progress.fresh, &progress.service.installed.read());
+                    let outcome = progress.service.finish_refresh(
+                        progress.fresh,
+                        progress.warnings,
+                        progress.answered,
+                        repositories,
+                    );
+                    progress.finished.finish(Ok(()));
+                    Some((Ok(RefreshEvent::Finished(outcome)), None))
+                }
+            }
+        })
+        .boxed()
+    }
+
+    fn finish_refresh(
+        &self,
+        fresh: Vec<MergedApp>,
+        warnings: Vec<PluginFailure>,
+        mut answered: HashMap<String, SystemTime>,
+        repositories: Option<HostResponse<Vec<Repository>>>,
+    ) -> RefreshOutcome {
+        let apps = self.keep_failed_plugins(fresh, &warnings, &mut answered);
+        let before = std::mem::replace(&mut *self.cache.write(), apps.clone());
+        let warnings_before = std::mem::replace(&mut *self.warnings.write(), warnings.clone());
+        let answered_before = std::mem::replace(&mut *self.refreshed.write(), answered.clone());
+
+        self.emit_diff(&before, &apps);
+        if warnings_before != warnings {
+            self.emit(StoreEvent::WarningsChanged(warnings.clone()));
+        }
+        if let Some(response) = repositories {
+            self.update_repositories(response, &answered_before);
+        }
+
+        if let Err(err) = self.store.store_apps(&apps) {
+            eprintln!("InstallGrid: failed to persist applications: {err}");
+        }
+        if let Err(err) = self.store.store_warnings(&warnings) {
+            eprintln!("InstallGrid: failed to persist plugin warnings: {err}");
+        }
+        if let Err(err) = self.store.store_refresh_times(&answered) {
+            eprintln!("InstallGrid: failed to persist cache refresh times: {err}");
+        }
+
+        RefreshOutcome {
+            apps,
+            warnings,
+            cache_age: self.cache_age(),
+        }
+    }
+
+    /// Merges `fresh` with what plugins that failed this refresh reported
+    /// before: their sources on apps others still list, apps only they
+    /// listed, and when their data was fetched. Only plugins that answered
+    /// replace their rows.
+    fn keep_failed_plugins(
+        &self,
+        fresh: Vec<MergedApp>,
+        warnings: &[PluginFailure],
+        answered: &mut HashMap<String, SystemTime>,
+    ) -> Vec<MergedApp> {
+        let failed = warnings
+            .iter()
+            .map(|failure| failure.plugin.as_str())
+            .filter(|plugin| !answered.contains_key(*plugin))
+            .collect::<HashSet<_>>();
+        if failed.is_empty() {
+            return fresh;
+        }
+
+        for (plugin, at) in self.refreshed.read().iter() {
+            if failed.contains(plugin.as_str()) {
+                answered.insert(plugin.clone(), *at);
+            }
+        }
+
+        let cache = self.cache.read();
+        let priority = &self.host.state.priority;
+        let mut apps = fresh
+            .into_iter()
+            .map(|app| match cache.iter().find(|cached| cached.app_id() == app.app_id()) {
+                Some(cached) => {
+                    keep_pending_sources(app, cached, |plugin| failed.contains(plugin), priority)
+                }
+                None => app,
+            })
+            .collect::<Vec<_>>();
+
+        let listed = apps
+            .iter()
+            .map(|app| app.app_id().to_string())
+            .collect::<HashSet<_>>();
+        for cached in cache.iter().filter(|cached| !listed.contains(cached.app_id())) {
+            let sources = cached
+                .sources
+                .iter()
+                .filter(|source| failed.contains(source.plugin.as_str()))
+                .cloned()
+                .collect::<Vec<_>>();
+            if !sources.is_empty() {
+                apps.push(MergedApp {
+                    primary: cached.primary.clone(),
+                    sources,
+                });
+            }
+        }
+        apps
+    }
+
+    /// Replaces the repositories of every plugin that listed them and emits
+    /// what changed for plugins that had answered a refresh before.
+    fn update_repositories(
+        &self,
+        response: HostResponse<Vec<Repository>>,
+        answered_before: &HashMap<String, SystemTime>,
+    ) {
+        let failed = response
+            .warnings
+            .iter()
+            .map(|failure| failure.plugin.as_str())
+            .collect::<HashSet<_>>();
+        let before = self.repositories.read().clone();
+        let mut after = response.data;
+        after.extend(
+            before
+                .iter()
+                .filter(|repository| failed.contains(repository.plugin.as_str()))
+                .cloned(),
+        );
+
+        let changes = diff_repositories(&before, &after, answered_before);
+        if !changes.is_empty() {
+            self.emit(StoreEvent::RepositoriesChanged(changes));
+        }
+        if before != after {
+            if let Err(err) = self.store.store_repositories(&after) {
+                eprintln!("InstallGrid: failed to persist repositories: {err}");
+            }
+            *self.repositories.write() = after;
+        }
+    }
+
+    /// Updates found by the last check, without asking the backends.
+    pub fn update_snapshot(&self) -> UpdateOutcome {
+        UpdateOutcome {
+            updates: self.updates.read().clone(),
+            warnings: self.update_warnings.read().clone(),
+            checked_age: self
+                .updates_checked
+                .read()
+                .map(|at| at.elapsed().unwrap_or_default()),
+        }
+    }
+
+    /// Serves the last update check while it is younger than the max age and
+    /// checks again otherwise.
+    pub async fn check_updates(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<UpdateOutcome, HostError> {
+        let snapshot = self.update_snapshot();
+        match snapshot.checked_age {
+            Some(age) if age < self.max_age => Ok(snapshot),
+            _ => self.refresh_updates(cancel).await,
+        }
+    }
+
+    /// Asks every backend for updates and caches the result. Plugins that
+    /// fail keep the updates they reported before.
+    pub async fn refresh_updates(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<UpdateOutcome, HostError> {
+        let response = self.host.list_updates(cancel.clone()).await?;
+        let failed = response
+            .warnings
+            .iter()
+            .map(|failure| failure.plugin.as_str())
+            .collect::<HashSet<_>>();
+
+        let before = self.updates.read().clone();
+        let mut after = response.data;
+        after.extend(
+            before
+                .iter()
+                .filter(|update| failed.contains(update.plugin.as_str()))
+                .cloned(),
+        );
+        let checked_at = SystemTime::now();
+        if let Err(err) = self.store.store_updates(&after, Some(checked_at)) {
+            eprintln!("InstallGrid: failed to persist updates: {err}");
+        }
+        *self.updates.write() = after.clone();
+        *self.update_warnings.write() = response.warnings.clone();
+        *self.updates_checked.write() = Some(checked_at);
+        if before != after {
+            self.emit(StoreEvent::UpdatesChanged(after.clone()));
+        }
+
+        Ok(UpdateOutcome {
+            updates: after,
+            warnings: response.warnings,
+            checked_age: Some(Duration::ZERO),
+        })
+    }
+
+    /// Starts applying `update`; progress is reported on the returned stream.
+    /// Once the job finishes the update is dropped from the pending ones.
+    pub fn update(
+        &self,
+        update: &AppUpdate,
+        cancel: &CancellationHandle,
+    ) -> Result<JobEvents, HostError> {
+        let source = AppSource {
+            plugin: update.plugin.clone(),
+            source: update.app.source.clone(),
+        };
+        let events = self.host.update(&update.app.app_id, &source, cancel.clone())?;
+        let service = self.clone();
+        let update = update.clone();
+        Ok(events
+            .inspect(move |item| {
+                if matches!(item, Ok(JobProgress::Finished)) {
+                    service.forget_update(&update);
+                }
+            })
+            .boxed())
+    }
+
+    /// Applies every pending update, one app at a time. A failed update does
+    /// not stop the others; cancelling does.
+    pub fn update_all(&self, cancel: &CancellationHandle) -> BoxStream<'static, UpdateProgress> {
+        let service = self.clone();
+        let cancel = cancel.clone();
+        let (tx, rx) = async_channel::unbounded();
+
+        self.host.handle.spawn(async move {
+            let pending = service.updates.read().clone();
+            for update in pending {
+                if cancel.is_cancelled() {
+                    break;
+                }
+                let mut events = match service.update(&update, &cancel) {
+                    Ok(events) => events,
+                    Err(err) => {
+                        let progress = UpdateProgress {
+                            update,
+                            progress: Err(err),
+                        };
+                        if tx.send(progress).await.is_err() {
+                            return;
+                        }
+                        continue;
+                    }
+                };
+                while let Some(item) = events.next().await {
+                    let progress = item.map_err(|failure| match failure.kind {
+                        PluginFailureKind::Execution(PluginExecutionError::Cancelled) => {
+                            HostError::Cancelled
+                        }
+                        _ => HostError::Plugin(failure),
+                    });
+                    let progress = UpdateProgress {
+                        update: update.clone(),
+                        progress,
+                    };
+                    if tx.send(progress).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        rx.boxed()
+    }
+
+    fn forget_update(&self, done: &AppUpdate) {
+        let updates = {
+            let mut updates = self.updates.write();
+            updates.retain(|update| {
+                update.plugin != done.plugin || update.app.app_id != done.app.app_id
+            });
+            updates.clone()
+        };
+        let checked_at = *self.updates_checked.read();
+        if let Err(err) = self.store.store_updates(&updates, checked_at) {
+            eprintln!("InstallGrid: failed to persist updates: {err}");
+        }
+        self.emit(StoreEvent::UpdatesChanged(updates));
+    }
+
+    /// Searches every backend and returns the merged, ranked matches. Results
+    /// are not written to the cache, but [`AppStoreService::find_app`] knows
+    /// them until the next search.
+    pub async fn search(
+        &self,
+        query: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<SearchOutcome, HostError> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Ok(SearchOutcome {
+                query: String::new(),
+                apps: Vec::new(),
+                warnings: Vec::new(),
+            });
+        }
+
+        let response = self.host.search(query, cancel.clone()).await?;
+        let mut apps = rank_search_results(query, response.data);
+        mark_installed(&mut apps, &self.installed.read());
+        *self.search_results.write() = apps.clone();
+
+        Ok(SearchOutcome {
+            query: query.to_string(),
+            apps,
+            warnings: response.warnings,
+        })
+    }
+
+    /// Starts installing `app_id` from `source`; progress is reported on
+    /// the returned stream. Once the job finishes the app is marked installed.
+    pub fn install(
+        &self,
+        app_id: &str,
+        source: &AppSource,
+        cancel: &CancellationHandle,
+    ) -> Result<JobEvents, HostError> {
+        let events = self.host.install(app_id, source, cancel.clone())?;
+        Ok(self.on_finished(events, app_id, true))
+    }
+
+    /// Starts removing `app_id` through `source`; progress is reported on
+    /// the returned stream. Once the job finishes the app is marked not
+    /// installed.
+    pub fn remove(
+        &self,
+        app_id: &str,
+        source: &AppSource,
+        cancel: &CancellationHandle,
+    ) -> Result<JobEvents, HostError> {
+        let events = self.host.remove(app_id, source, cancel.clone())?;
+        Ok(self.on_finished(events, app_id, false))
+    }
+
+    fn on_finished(&self, events: JobEvents, app_id: &str, installed: bool) -> JobEvents {
+        let service = self.clone();
+        let app_id = app_id.to_string();
+        events
+            .inspect(move |item| {
+                if matches!(item, Ok(JobProgress::Finished)) {
+                    service.set_installed(&app_id, installed);
+                }
+            })
+            .boxed()
+    }
+
+    /// Records the outcome of a finished install or remove without waiting
+    /// for the next refresh, which stays authoritative.
+    fn set_installed(&self, app_id: &str, installed: bool) {
+        if installed {
+            self.installed.write().insert(app_id.to_string());
+        } else {
+            self.installed.write().remove(app_id);
+        }
+        for app in self.search_results.write().iter_mut() {
+            if app.app_id() == app_id {
+                app.primary.installed = installed;
+            }
+        }
+        for (_, listing) in self.category_apps.write().values_mut() {
+            for app in listing.apps.iter_mut().filter(|app| app.app_id() == app_id) {
+                app.primary.installed = installed;
+            }
+        }
+
+        let (changed, apps) = {
+            let mut cache = self.cache.write();
+            let Some(app) = cache.iter_mut().find(|app| app.app_id() == app_id) else {
+                return;
+            };
+            if app.primary.installed == installed {
+                return;
+            }
+            app.primary.installed = installed;
+            (app.clone(), cache.clone())
+        };
+        if let Err(err) = self.store.store_apps(&apps) {
+            eprintln!("InstallGrid: failed to persist applications: {err}");
+        }
+        self.emit(StoreEvent::AppsChanged(vec![changed]));
+    }
+
+    pub fn plugin_count(&self) -> usize {
+        self.host.plugin_count()
+    }
+
+    /// Health of every backend, including quarantined ones.
+    pub fn plugin_health(&self) -> Vec<PluginHealth> {
+        self.host.plugin_health()
+    }
+
+    pub fn retry_plugin(&self, plugin_id: &str) -> Result<(), HostError> {
+        self.host.retry_plugin(plugin_id)
+    }
+
+    pub fn set_plugin_enabled(&self, plugin_id: &str, enabled: bool) -> Result<(), HostError> {
+        self.host.set_plugin_enabled(plugin_id, enabled)
+    }
+}
+
+/// Folds into `update` the sources of `cached` whose plugins are `pending`,
+/// i.e. have not answered the running refresh yet or failed it, so the cache
+/// does not lose them (or their higher-priority metadata).
+fn keep_pending_sources(
+    mut update: MergedApp,
+    cached: &MergedApp,
+    pending: impl Fn(&str) -> bool,
+    priority: &[String],
+) -> MergedApp {
+    let pending = |source: &AppSource| pending(&source.plugin);
+    for source in cached.sources.iter().filter(|source| pending(source)) {
+        if !update.sources.contains(source) {
+            update.sources.push(source.clone());
+        }
+    }
+
+    let rank = |plugin: &str| {
+        priority
+            .iter()
+            .position(|id| id == plugin)
+            .unwrap_or(priority.len())
+    };
+    update.sources.sort_by_key(|source| rank(&source.plugin));
+
+    if cached.sources.first().is_some_and(|first| {
+        pending(first) && update.sources.first() == Some(first)
+    }) {
+        update.primary = AppSummary {
+            installed: update.primary.installed || cached.primary.installed,
+            ..cached.primary.clone()
+        };
+    }
+    update
+}
+
+/// Splits `updates` into apps missing from `cache` and apps that differ from
+/// their cached record; unchanged apps are left out.
+fn classify_updates(cache: &[MergedApp], updates: &[MergedApp]) -> (Vec<MergedApp>, Vec<MergedApp>) {
+    let cached = cache
+        .iter()
+        .map(|app| (app.app_id(), app))
+        .collect::<HashMap<_, _>>();
+
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    for update in updates {
+        match cached.get(update.app_id()) {
+            None => added.push(update.clone()),
+            Some(existing) if *existing != update => changed.push(update.clone()),
+            Some(_) => {}
+        }
+    }
+    (added, changed)
+}
+
+/// Compares repositories by plugin and id. Plugins missing from `baseline`
+/// have not been listed before, so nothing is reported for them.
+fn diff_repositories(
+    before: &[Repository],
+    after: &[Repository],
+    baseline: &HashMap<String, SystemTime>,
+) -> Vec<RepositoryChange> {
+    let key = |repository: &Repository| (repository.plugin.clone(), repository.id.clone());
+    let previous = before
+        .iter()
+        .map(|repository| (key(repository), repository))
+        .collect::<HashMap<_, _>>();
+    let current = after.iter().map(key).collect::<HashSet<_>>();
+
+    let mut changes = Vec::new();
+    for repository in after.iter().filter(|repository| baseline.contains_key(&repository.plugin)) {
+        match previous.get(&key(repository)) {
+            None => changes.push(RepositoryChange::Added(repository.clone())),
+            Some(known) if *known != repository => {
+                changes.push(RepositoryChange::Changed(repository.clone()));
+            }
+            Some(_) => {}
+        }
+    }
+    for repository in before.iter().filter(|repository| baseline.contains_key(&repository.plugin)) {
+        if !current.contains(&key(repository)) {
+            changes.push(RepositoryChange::Removed(repository.clone()));
+        }
+    }
+    changes
+}
+
+/// Replaces entries of `cache` with the same `app_id` as an update and
+/// appends the rest.
+fn upsert_apps(cache: &mut Vec<MergedApp>, updates: &[MergedApp]) {
+    for update in updates {
+        match cache.iter_mut().find(|app| app.app_id() == update.app_id()) {
+            Some(existing) => *existing = update.clone(),
+            None => cache.push(update.clone()),
+        }
+    }
+}
+
+fn serialize_secs<S: Serializer>(age: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+    age.map(|age| age.as_secs()).serialize(serializer)
+}
+
+/// Sets `installed` on every app in `apps` whose id is in `installed`. Flags
+/// already reported by the app's own backends are kept.
+fn mark_installed(apps: &mut [MergedApp], installed: &HashSet<String>) {
+    for app in apps {
+        if installed.contains(app.app_id()) {
+            app.primary.installed = true;
+        }
+    }
+}
+
+/// Appends `failures` for plugins that have no warning yet.
+fn add_warnings(warnings: &mut Vec<PluginFailure>, failures: Vec<PluginFailure>) {
+    for failure in failures {
+        if !warnings.iter().any(|known| known.plugin == failure.plugin) {
+            warnings.push(failure);
+        }
+    }
+}
+
+/// Orders merged matches by relevance to `query`. Ties fall back to the name
+/// so the order is stable.
+fn rank_search_results(query: &str, apps: Vec<MergedApp>) -> Vec<MergedApp> {
+    let terms = query
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+
+    let mut ranked = apps
+        .into_iter()
+        .map(|app| (search_score(&terms, &app.primary), app))
+        .collect::<Vec<_>>();
+    ranked.sort_by(|(left_score, left), (right_score, right)| {
+        right_score
+            .cmp(left_score)
+            .then_with(|| {
+                left.primary
+                    .name
+                    .to_lowercase()
+                    .cmp(&right.primary.name.to_lowercase())
+            })
+            .then_with(|| left.primary.app_id.cmp(&right.primary.app_id))
+    });
+    ranked.into_iter().map(|(_, app)| app).collect()
+}
+
+fn search_score(terms: &[String], app: &AppSummary) -> u32 {
+    let name = app.name.to_lowercase();
+    let app_id = app.app_id.to_lowercase();
+    let summary = app.summary.to_lowercase();
+
+    terms
+        .iter()
+        .map(|term| {
+            if name == *term {
+                100
+            } else if name.starts_with(term.as_str()) {
+                60
+            } else if name.contains(term.as_str()) {
+                40
+            } else if app_id.contains(term.as_str()) {
+                20
+            } else if summary.contains(term.as_str()) {
+                10
+            } else {
+                0
+            }
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use futures::executor::block_on;
+    use parking_lot::Mutex;
+
+    use super::*;
+    use crate::cache::MemoryCacheStore;
+    use crate::plugins::{failed_job, JobStream, NativeMockPlugin, PluginKind};
+    use crate::registry::HealthState;
+
+    const FIREFOX: &str = "org.mozilla.firefox";
+
+    fn mock(id: &str) -> NativeMockPlugin {
+        NativeMockPlugin::new(id).with_delay(Duration::ZERO)
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    enum Behaviour {
+        Answer,
+        Fail,
+        Panic,
+        /// Never returns, not even when cancelled.
+        Hang,
+    }
+
+    /// Backend whose every call behaves as its shared [`Behaviour`] says.
+    /// Every instance lists its `apps` with the same source, `scripted`.
+    struct Scripted {
+        descriptor: PluginDescriptor,
+        apps: Vec<String>,
+        behaviour: Arc<Mutex<Behaviour>>,
+    }
+
+    impl Scripted {
+        fn new(id: &str, apps: &[&str]) -> (Self, Arc<Mutex<Behaviour>>) {
+            let behaviour = Arc::new(Mutex::new(Behaviour::Answer));
+            let scripted = Self {
+                descriptor: PluginDescriptor {
+                    id: id.to_string(),
+                    kind: PluginKind::Native,
+                },
+                apps: apps.iter().map(|app_id| app_id.to_string()).collect(),
+                behaviour: behaviour.clone(),
+            };
+            (scripted, behaviour)
+        }
+
+        fn summary(&self, app_id: &str) -> AppSummary {
+            AppSummary {
+                app_id: app_id.to_string(),
+                name: format!("{app_id} from {}", self.descriptor.id),
+                summary: String::new(),
+                source: "scripted".to_string(),
+                installed: false,
+            }
+        }
+
+        async fn act<T>(&self, answer: T) -> Result<T, PluginExecutionError> {
+            let behaviour = *self.behaviour.lock();
+            match behaviour {
+                Behaviour::Answer => Ok(answer),
+                Behaviour::Fail => Err(PluginExecutionError::Operation("scripted".to_string())),
+                Behaviour::Panic => panic!("scripted panic"),
+                Behaviour::Hang => futures::future::pending().await,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl PluginBackend for Scripted {
+        fn descriptor(&self) -> &PluginDescriptor {
+            &self.descriptor
+        }
+
+        async fn list_popular_apps(
+            &self,
+            _cancel: &CancellationHandle,
+        ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+            let apps = self.apps.iter().map(|app_id| self.summary(app_id)).collect();
+            self.act(apps).await
+        }
+
+        async fn search(
+            &self,
+            _query: &str,
+            _cancel: &CancellationHandle,
+        ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+            self.act(Vec::new()).await
+        }
+
+        async fn list_updates(
+            &self,
+            _cancel: &CancellationHandle,
+        ) -> Result<Vec<AppUpdate>, PluginExecutionError> {
+            let updates = self
+                .apps
+                .iter()
+                .map(|app_id| AppUpdate {
+                    app: self.summary(app_id),
+                    version_from: Some("1".to_string()),
+                    version_to: Some("2".to_string()),
+                    download_size: None,
+                    plugin: self.descriptor.id.clone(),
+                })
+                .collect();
+            self.act(updates).await
+        }
+
+        async fn app_details(
+            &self,
+            app_id: &str,
+            _cancel: &CancellationHandle,
+        ) -> Result<AppDetails, PluginExecutionError> {
+            self.act(AppDetails::from_summary(self.summary(app_id))).await
+        }
+
+        fn install(&self, _app_id: &str, _cancel: CancellationHandle) -> JobStream {
+            if matches!(*self.behaviour.lock(), Behaviour::Panic) {
+                panic!("scripted panic");
+            }
+            failed_job(PluginExecutionError::Unsupported)
+        }
+
+        fn remove(&self, _app_id: &str, _cancel: CancellationHandle) -> JobStream {
+            failed_job(PluginExecutionError::Unsupported)
+        }
+    }
+
+    /// Sleeps through every listing, keeping the handle the host passed in.
+    struct Sleeper {
+        descriptor: PluginDescriptor,
+        received: Arc<Mutex<Option<CancellationHandle>>>,
+    }
+
+    #[async_trait]
+    impl PluginBackend for Sleeper {
+        fn descriptor(&self) -> &PluginDescriptor {
+            &self.descriptor
+        }
+
+        async fn list_popular_apps(
+            &self,
+            cancel: &CancellationHandle,
+        ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+            *self.received.lock() = Some(cancel.clone());
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(Vec::new())
+        }
+
+        async fn search(
+            &self,
+            _query: &str,
+            _cancel: &CancellationHandle,
+        ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+            Ok(Vec::new())
+        }
+
+        async fn app_details(
+            &self,
+            _app_id: &str,
+            _cancel: &CancellationHandle,
+        ) -> Result<AppDetails, PluginExecutionError> {
+            Err(PluginExecutionError::Unsupported)
+        }
+
+        fn install(&self, _app_id: &str, _cancel: CancellationHandle) -> JobStream {
+            failed_job(PluginExecutionError::Unsupported)
+        }
+
+        fn remove(&self, _app_id: &str, _cancel: CancellationHandle) -> JobStream {
+            failed_job(PluginExecutionError::Unsupported)
+        }
+    }
+
+    /// Lists whatever apps and categories the test put in its state, counting
+    /// calls. Every category holds all of `apps`.
+    struct Listing {
+        descriptor: PluginDescriptor,
+        state: Arc<Mutex<ListingState>>,
+    }
+
+    #[derive(Default)]
+    struct ListingState {
+        apps: Vec<AppSummary>,
+        calls: usize,
+        categories: Vec<Category>,
+        category_calls: usize,
+    }
+
+    impl Listing {
+        fn new(id: &str) -> (Self, Arc<Mutex<ListingState>>) {
+            let state = Arc::new(Mutex::new(ListingState::default()));
+            let listing = Self {
+                descriptor: PluginDescriptor {
+                    id: id.to_string(),
+                    kind: PluginKind::Native,
+                },
+                state: state.clone(),
+            };
+            (listing, state)
+        }
+    }
+
+    #[async_trait]
+    impl PluginBackend for Listing {
+        fn descriptor(&self) -> &PluginDescriptor {
+            &self.descriptor
+        }
+
+        async fn list_popular_apps(
+            &self,
+            _cancel: &CancellationHandle,
+        ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+            let mut state = self.state.lock();
+            state.calls += 1;
+            Ok(state.apps.clone())
+        }
+
+        async fn list_categories(
+            &self,
+            _cancel: &CancellationHandle,
+        ) -> Result<Vec<Category>, PluginExecutionError> {
+            let mut state = self.state.lock();
+            state.category_calls += 1;
+            Ok(state.categories.clone())
+        }
+
+        async fn list_category_apps(
+            &self,
+            _category_id: &str,
+            _cancel: &CancellationHandle,
+        ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+            let mut state = self.state.lock();
+            state.category_calls += 1;
+            Ok(state.apps.clone())
+        }
+
+        async fn search(
+            &self,
+            _query: &str,
+            _cancel: &CancellationHandle,
+        ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+            Ok(Vec::new())
+        }
+
+        async fn app_details(
+            &self,
+            _app_id: &str,
+            _cancel: &CancellationHandle,
+        ) -> Result<AppDetails, PluginExecutionError> {
+            Err(PluginExecutionError::Unsupported)
+        }
+
+        fn install(&self, _app_id: &str, _cancel: CancellationHandle) -> JobStream {
+            failed_job(PluginExecutionError::Unsupported)
+        }
+
+        fn remove(&self, _app_id: &str, _cancel: CancellationHandle) -> JobStream {
+            failed_job(PluginExecutionError::Unsupported)
+        }
+    }
+
+    fn listed(app_id: &str, name: &str) -> AppSummary {
+        AppSummary {
+            app_id: app_id.to_string(),
+            name: name.to_string(),
+            summary: String::new(),
+            source: "listing".to_string(),
+            installed: false,
+        }
+    }
+
+    fn drain(events: &mut broadcast::Receiver<StoreEvent>) -> Vec<StoreEvent> {
+        std::iter::from_fn(|| events.try_recv().ok()).collect()
+    }
+
+    /// App ids added, changed and removed by `events`, each sorted.
+    fn app_changes(events: &[StoreEvent]) -> [Vec<String>; 3] {
+        let mut changes: [Vec<String>; 3] = Default::default();
+        for event in events {
+            match event {
+                StoreEvent::AppsAdded(apps) => {
+                    changes[0].extend(apps.iter().map(|app| app.app_id().to_string()))
+                }
+                StoreEvent::AppsChanged(apps) => {
+                    changes[1].extend(apps.iter().map(|app| app.app_id().to_string()))
+                }
+                StoreEvent::AppsRemoved(app_ids) => changes[2].extend(app_ids.iter().cloned()),
+                _ => {}
+            }
+        }
+        for ids in &mut changes {
+            ids.sort();
+            ids.dedup();
+        }
+        changes
+    }
+
+    fn health(host: &PluginHost, plugin: &str) -> PluginHealth {
+        host.plugin_health()
+            .into_iter()
+            .find(|health| health.plugin == plugin)
+            .expect("plugin registered")
+    }
+
+    fn popular(host: &PluginHost) -> Vec<MergedApp> {
+        block_on(host.list_popular(CancellationHandle::new()))
+            .expect("popular apps")
+            .data
+    }
+
+    fn sources(apps: &[MergedApp], app_id: &str) -> Vec<AppSource> {
+        apps.iter()
+            .find(|app| app.app_id() == app_id)
+            .map(|app| app.sources.clone())
+            .expect("app listed")
+    }
+
+    fn installed(host: &PluginHost) -> Vec<MergedApp> {
+        block_on(host.list_installed(CancellationHandle::new()))
+            .expect("installed apps")
+            .data
+    }
+
+    fn finished_job() -> Vec<Result<JobProgress, PluginFailure>> {
+        let mut progress = vec![Ok(JobProgress::Queued)];
+        progress.extend([0, 25, 50, 75, 100].map(|percent| {
+            Ok(JobProgress::Running {
+                percent: Some(percent),
+            })
+        }));
+        progress.push(Ok(JobProgress::Finished));
+        progress
+    }
+
+    #[test]
+    fn install_and_remove_report_progress_and_change_installed_state() {
+        let host = PluginHostBuilder::new()
+            .with_backend(mock("native::mock"))
+            .build()
+            .unwrap();
+        let firefox = &sources(&popular(&host), FIREFOX)[0];
+        assert!(installed(&host).is_empty());
+
+        let events = host.install(FIREFOX, firefox, CancellationHandle::new()).unwrap();
+        assert_eq!(block_on(events.collect::<Vec<_>>()), finished_job());
+        let apps = installed(&host);
+        assert_eq!(apps.len(), 1);
+        assert_eq!(apps[0].app_id(), FIREFOX);
+        assert!(apps[0].primary.installed);
+
+        let events = host.remove(FIREFOX, firefox, CancellationHandle::new()).unwrap();
+        assert_eq!(block_on(events.collect::<Vec<_>>()), finished_job());
+        assert!(installed(&host).is_empty());
+    }
+
+    #[test]
+    fn failed_job_ends_with_the_plugin_failure() {
+        let host = PluginHostBuilder::new()
+            .with_backend(mock("native::mock"))
+            .build()
+            .unwrap();
+        let firefox = &sources(&popular(&host), FIREFOX)[0];
+
+        let events = host.remove(FIREFOX, firefox, CancellationHandle::new()).unwrap();
+        let progress = block_on(events.collect::<Vec<_>>());
+        assert_eq!(progress.len(), 1);
+        let failure = progress[0].clone().unwrap_err();
+        assert_eq!(failure.plugin, "native::mock");
+        assert!(matches!(
+            failure.kind,
+            PluginFailureKind::Execution(PluginExecutionError::Operation(_))
+        ));
+    }
+
+    #[test]
+    fn jobs_are_routed_to_the_plugin_of_the_chosen_source() {
+        let host = PluginHostBuilder::new()
+            .with_backend(mock("native::first"))
+            .with_backend(mock("native::second"))
+            .build()
+            .unwrap();
+        // Both plugins report the same source string; only the plugin tells
+        // them apart.
+        let firefox = sources(&popular(&host), FIREFOX);
+        assert_eq!(firefox.len(), 2);
+        assert_eq!(firefox[0].source, firefox[1].source);
+        assert_eq!(firefox[1].plugin, "native::second");
+
+        let events = host.install(FIREFOX, &firefox[1], CancellationHandle::new()).unwrap();
+        assert_eq!(block_on(events.collect::<Vec<_>>()), finished_job());
+        let apps = installed(&host);
+        assert_eq!(apps.len(), 1);
+        assert_eq!(apps[0].sources, vec![firefox[1].clone()]);
+    }
+
+    #[test]
+    fn jobs_need_a_registered_plugin_but_no_prior_listing() {
+        let host = PluginHostBuilder::new()
+            .with_backend(mock("native::mock"))
+            .build()
+            .unwrap();
+        let source = AppSource {
+            plugin: "native::mock".to_string(),
+            source: "mock::packagekit".to_string(),
+        };
+        let events = host.install(FIREFOX, &source, CancellationHandle::new()).unwrap();
+        assert_eq!(block_on(events.collect::<Vec<_>>()), finished_job());
+
+        let unknown = AppSource {
+            plugin: "native::snap".to_string(),
+            ..source
+        };
+        assert!(matches!(
+            host.install(FIREFOX, &unknown, CancellationHandle::new()),
+            Err(HostError::UnknownPlugin(plugin)) if plugin == "native::snap"
+        ));
+    }
+
+    #[test]
+    fn job_panics_count_towards_quarantine() {
+        let (scripted, behaviour) = Scripted::new("native::scripted", &["org.example.App"]);
+        let host = PluginHostBuilder::new()
+            .with_backend(scripted)
+            .with_quarantine(1, Duration::from_secs(3600))
+            .build()
+            .unwrap();
+        let source = &sources(&popular(&host), "org.example.App")[0];
+
+        *behaviour.lock() = Behaviour::Panic;
+        let events = host.install("org.example.App", source, CancellationHandle::new()).unwrap();
+        let progress = block_on(events.collect::<Vec<_>>());
+        assert_eq!(progress.len(), 1);
+        assert_eq!(progress[0].clone().unwrap_err().kind, PluginFailureKind::Panic);
+
+        let health = health(&host, "native::scripted");
+        assert_eq!(health.state, HealthState::Quarantined);
+        assert_eq!(health.failures, 1);
+    }
+
+    #[tokio::test]
+    async fn hung_backends_time_out_without_holding_back_the_others() {
+        let (hung, hung_behaviour) = Scripted::new("native::hung", &["org.example.Hung"]);
+        let (overridden, overridden_behaviour) =
+            Scripted::new("native::overridden", &["org.example.Overridden"]);
+        *hung_behaviour.lock() = Behaviour::Hang;
+        *overridden_behaviour.lock() = Behaviour::Hang;
+        let host = PluginHostBuilder::new()
+            .with_backend(hung)
+            .with_backend(overridden)
+            .with_backend(mock("native::mock"))
+            .with_default_timeout(Duration::from_millis(100))
+            .with_timeout("native::overridden", Duration::from_millis(50))
+            .build()
+            .unwrap();
+
+        let response = host.list_popular(CancellationHandle::new()).await.unwrap();
+
+        assert_eq!(response.data.len(), 3);
+        assert!(response
+            .data
+            .iter()
+            .all(|app| app.sources[0].plugin == "native::mock"));
+        let timeouts = response
+            .warnings
+            .iter()
+            .map(|failure| (failure.plugin.as_str(), failure.kind.clone()))
+            .collect::<Vec<_>>();
+        let timeout = |millis| {
+            PluginFailureKind::Execution(PluginExecutionError::Timeout(Duration::from_millis(
+                millis,
+            )))
+        };
+        assert_eq!(
+            timeouts,
+            [("native::hung", timeout(100)), ("native::overridden", timeout(50))]
+        );
+
+        // The host owns a runtime, which must not be dropped in async code.
+        tokio::task::spawn_blocking(move || drop(host)).await.unwrap();
+    }
+
+    #[test]
+    fn cancelling_a_refresh_returns_promptly_and_reaches_the_backend() {
+        let received = Arc::new(Mutex::new(None));
+        let sleeper = Sleeper {
+            descriptor: PluginDescriptor {
+                id: "native::sleeper".to_string(),
+                kind: PluginKind::Native,
+            },
+            received: received.clone(),
+        };
+        let host = PluginHostBuilder::new()
+            .with_backend(sleeper)
+            .with_backend(mock("native::mock"))
+            .build()
+            .unwrap();
+        let service = AppStoreService::new(host, Arc::new(MemoryCacheStore::new()));
+
+        let cancel = CancellationHandle::new();
+        let refresh = std::thread::spawn({
+            let service = service.clone();
+            let cancel = cancel.clone();
+            move || block_on(service.refresh_popular(&cancel))
+        });
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while received.lock().is_none() {
+            assert!(Instant::now() < deadline, "the backend was never called");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let cancelled_at = Instant::now();
+        cancel.cancel();
+
+        assert!(matches!(refresh.join().unwrap(), Err(HostError::Cancelled)));
+        assert!(cancelled_at.elapsed() < Duration::from_secs(5));
+        assert!(received.lock().as_ref().unwrap().is_cancelled());
+    }
+
+    #[test]
+    fn cancelled_fan_out_frees_the_half_open_probe() {
+        let (scripted, behaviour) = Scripted::new("native::scripted", &["org.example.App"]);
+        let host = PluginHostBuilder::new()
+            .with_backend(scripted)
+            .with_quarantine(1, Duration::from_secs(3600))
+            .build()
+            .unwrap();
+
+        *behaviour.lock() = Behaviour::Panic;
+        assert!(block_on(host.list_popular(CancellationHandle::new())).is_err());
+        assert_eq!(health(&host, "native::scripted").state, HealthState::Quarantined);
+
+        // The probe hangs until the refresh is cancelled.
+        host.retry_plugin("native::scripted").unwrap();
+        *behaviour.lock() = Behaviour::Hang;
+        let cancel = CancellationHandle::new();
+        let refresh = std::thread::spawn({
+            let refresh = host.list_popular(cancel.clone());
+            move || block_on(refresh)
+        });
+        std::thread::sleep(Duration::from_millis(50));
+        cancel.cancel();
+        assert!(matches!(refresh.join().unwrap(), Err(HostError::Cancelled)));
+
+        *behaviour.lock() = Behaviour::Answer;
+        assert_eq!(popular(&host).len(), 1);
+        assert_eq!(health(&host, "native::scripted").state, HealthState::Healthy);
+    }
+
+    #[test]
+    fn failed_plugin_keeps_its_cached_apps_and_refresh_time() {
+        let (first, _) = Scripted::new(
+            "native::first",
+            &["org.example.Shared", "org.example.First"],
+        );
+        let (second, behaviour) =
+            Scripted::new("native::second", &["org.example.Shared", "org.example.Second"]);
+        let host = PluginHostBuilder::new()
+            .with_backend(first)
+            .with_backend(second)
+            .with_priority(["native::second"])
+            .build()
+            .unwrap();
+        let store = Arc::new(MemoryCacheStore::new());
+        let service = AppStoreService::new(host, store.clone());
+
+        let outcome = block_on(service.refresh_popular(&CancellationHandle::new())).unwrap();
+        assert_eq!(outcome.apps.len(), 3);
+        let before = outcome.apps;
+        let refreshed = service.refresh_times()["native::second"];
+
+        *behaviour.lock() = Behaviour::Fail;
+        let outcome = block_on(service.refresh_popular(&CancellationHandle::new())).unwrap();
+
+        assert_eq!(outcome.warnings.len(), 1);
+        assert_eq!(outcome.warnings[0].plugin, "native::second");
+        let mut apps = outcome.apps.clone();
+        apps.sort_by(|left, right| left.app_id().cmp(right.app_id()));
+        let mut expected = before;
+        expected.sort_by(|left, right| left.app_id().cmp(right.app_id()));
+        assert_eq!(apps, expected);
+        let shared = service.cached_app("org.example.Shared").unwrap();
+        assert_eq!(shared.primary.name, "org.example.Shared from native::second");
+        assert_eq!(shared.sources.len(), 2);
+        assert_eq!(service.refresh_times()["native::second"], refreshed);
+
+        assert_eq!(store.load_apps().unwrap().len(), 3);
+        assert_eq!(store.load_refresh_times().unwrap()["native::second"], refreshed);
+    }
+
+    #[test]
+    fn finished_jobs_update_the_cached_installed_state() {
+        let host = PluginHostBuilder::new()
+            .with_backend(mock("native::mock"))
+            .build()
+            .unwrap();
+        let store = Arc::new(MemoryCacheStore::new());
+        let service = AppStoreService::new(host, store.clone());
+        let cancel = CancellationHandle::new();
+        block_on(service.refresh_popular(&cancel)).unwrap();
+        let firefox = service.cached_app(FIREFOX).unwrap();
+        assert!(!firefox.primary.installed);
+        let mut events = service.subscribe();
+
+        let job = service.install(FIREFOX, &firefox.sources[0], &cancel).unwrap();
+        assert_eq!(block_on(job.collect::<Vec<_>>()), finished_job());
+        assert!(service.cached_app(FIREFOX).unwrap().primary.installed);
+        let stored = store.load_apps().unwrap();
+        assert!(stored.iter().any(|app| app.app_id() == FIREFOX && app.primary.installed));
+        match events.try_recv() {
+            Ok(StoreEvent::AppsChanged(apps)) => {
+                assert_eq!(apps.len(), 1);
+                assert_eq!(apps[0].app_id(), FIREFOX);
+                assert!(apps[0].primary.installed);
+            }
+            other => panic!("expected AppsChanged, got {other:?}"),
+        }
+
+        let job = service.remove(FIREFOX, &firefox.sources[0], &cancel).unwrap();
+        assert_eq!(block_on(job.collect::<Vec<_>>()), finished_job());
+        assert!(!service.cached_app(FIREFOX).unwrap().primary.installed);
+        assert!(matches!(
+            events.try_recv(),
+            Ok(StoreEvent::AppsChanged(apps)) if !apps[0].primary.installed
+        ));
+
+        // A failed job leaves the cache alone.
+        let job = service.remove(FIREFOX, &firefox.sources[0], &cancel).unwrap();
+        assert!(block_on(job.collect::<Vec<_>>())[0].is_err());
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn refresh_streams_chunks_and_failures_in_completion_order() {
+        let (failing, behaviour) = Scripted::new("native::failing", &["org.example.App"]);
+        *behaviour.lock() = Behaviour::Fail;
+        let slow = NativeMockPlugin::new("native::slow").with_delay(Duration::from_millis(300));
+        let host = PluginHostBuilder::new()
+            .with_backend(slow)
+            .with_backend(failing)
+            .with_backend(mock("native::fast"))
+            .build()
+            .unwrap();
+        let service = AppStoreService::new(host, Arc::new(MemoryCacheStore::new()));
+
+        let events = service.refresh_popular_stream(&CancellationHandle::new());
+        let events = block_on(events.collect::<Vec<_>>());
+
+        let mut chunks = Vec::new();
+        let mut warnings = Vec::new();
+        for event in &events[..events.len() - 1] {
+            match event {
+                Ok(RefreshEvent::Apps(apps)) => chunks.push(apps.clone()),
+                Ok(RefreshEvent::Warning(failure)) => warnings.push(failure.plugin.clone()),
+                _ => panic!("unexpected event before the end"),
+            }
+        }
+        assert!(matches!(events.last(), Some(Ok(RefreshEvent::Finished(_)))));
+        assert_eq!(warnings, ["native::failing"]);
+        assert_eq!(chunks.len(), 2);
+
+        // The fast backend answers first, alone.
+        assert_eq!(chunks[0].len(), 3);
+        assert!(chunks[0].iter().all(|app| app.sources.len() == 1));
+        assert!(chunks[0].iter().all(|app| app.sources[0].plugin == "native::fast"));
+        // The slow one is merged with what the fast one reported.
+        assert_eq!(chunks[1].len(), 3);
+        let plugins = chunks[1][0]
+            .sources
+            .iter()
+            .map(|source| source.plugin.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(plugins, ["native::slow", "native::fast"]);
+    }
+
+    #[test]
+    fn refreshes_tell_subscribers_what_changed() {
+        let (listing, listing_state) = Listing::new("native::listing");
+        let (flaky, behaviour) = Scripted::new("native::flaky", &["org.example.Flaky"]);
+        let host = PluginHostBuilder::new()
+            .with_backend(listing)
+            .with_backend(flaky)
+            .build()
+            .unwrap();
+        let service = AppStoreService::new(host, Arc::new(MemoryCacheStore::new()));
+        let mut events = service.subscribe();
+        let refresh = || block_on(service.refresh_popular(&CancellationHandle::new())).unwrap();
+
+        listing_state.lock().apps = vec![
+            listed("org.example.Keep", "Keep"),
+            listed("org.example.Drop", "Drop"),
+            listed("org.example.Change", "Old name"),
+        ];
+        refresh();
+        let first = drain(&mut events);
+        assert!(matches!(first.first(), Some(StoreEvent::RefreshStarted)));
+        assert!(matches!(first.last(), Some(StoreEvent::RefreshFinished(Ok(())))));
+        let [added, changed, removed] = app_changes(&first);
+        assert_eq!(
+            added,
+            ["org.example.Change", "org.example.Drop", "org.example.Flaky", "org.example.Keep"]
+        );
+        assert!(changed.is_empty());
+        assert!(removed.is_empty());
+        assert!(!first.iter().any(|event| matches!(event, StoreEvent::WarningsChanged(_))));
+
+        listing_state.lock().apps = vec![
+            listed("org.example.Keep", "Keep"),
+            listed("org.example.Change", "New name"),
+            listed("org.example.New", "New"),
+        ];
+        *behaviour.lock() = Behaviour::Fail;
+        refresh();
+        let second = drain(&mut events);
+        assert!(matches!(second.first(), Some(StoreEvent::RefreshStarted)));
+        assert!(matches!(second.last(), Some(StoreEvent::RefreshFinished(Ok(())))));
+        // The failed plugin's app is kept, so it is neither changed nor removed.
+        assert_eq!(
+            app_changes(&second),
+            [
+                vec!["org.example.New".to_string()],
+                vec!["org.example.Change".to_string()],
+                vec!["org.example.Drop".to_string()],
+            ]
+        );
+        let warnings = second
+            .iter()
+            .filter_map(|event| match event {
+                StoreEvent::WarningsChanged(warnings) => Some(warnings),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].len(), 1);
+        assert_eq!(warnings[0][0].plugin, "native::flaky");
+
+        *behaviour.lock() = Behaviour::Answer;
+        refresh();
+        let third = drain(&mut events);
+        assert_eq!(app_changes(&third), <[Vec<String>; 3]>::default());
+        let cleared = |event: &StoreEvent| {
+            matches!(event, StoreEvent::WarningsChanged(warnings) if warnings.is_empty())
+        };
+        assert!(third.iter().any(cleared));
+    }
+
+    /// A service over `listing` whose store holds one app fetched `age` ago.
+    fn cached_service(listing: Listing, age: Duration) -> AppStoreService {
+        let store = Arc::new(MemoryCacheStore::new());
+        let cached = MergedApp {
+            primary: listed("org.example.Cached", "Cached"),
+            sources: vec![AppSource {
+                plugin: "native::listing".to_string(),
+                source: "listing".to_string(),
+            }],
+        };
+        store.store_apps(&[cached]).unwrap();
+        let fetched = SystemTime::now() - age;
+        let times = HashMap::from([("native::listing".to_string(), fetched)]);
+        store.store_refresh_times(&times).unwrap();
+        let host = PluginHostBuilder::new().with_backend(listing).build().unwrap();
+        AppStoreService::new(host, store).with_max_age(Duration::from_secs(3600))
+    }
+
+    #[test]
+    fn fresh_cache_is_served_without_asking_the_backends() {
+        let (listing, state) = Listing::new("native::listing");
+        let service = cached_service(listing, Duration::from_secs(60));
+        assert!(!service.is_stale());
+
+        let outcome = block_on(service.load_popular(&CancellationHandle::new())).unwrap();
+
+        assert_eq!(state.lock().calls, 0);
+        assert_eq!(outcome.apps.len(), 1);
+        assert_eq!(outcome.apps[0].app_id(), "org.example.Cached");
+        let age = outcome.cache_age.unwrap();
+        assert!(age >= Duration::from_secs(60) && age < Duration::from_secs(120), "{age:?}");
+    }
+
+    #[test]
+    fn stale_cache_is_served_then_revalidated() {
+        let (listing, state) = Listing::new("native::listing");
+        state.lock().apps = vec![listed("org.example.Fresh", "Fresh")];
+        let service = cached_service(listing, Duration::from_secs(2 * 3600));
+        assert!(service.is_stale());
+
+        let snapshot = service.cache_snapshot();
+        assert_eq!(snapshot.apps[0].app_id(), "org.example.Cached");
+        assert!(snapshot.cache_age.unwrap() >= Duration::from_secs(2 * 3600));
+        assert_eq!(state.lock().calls, 0);
+
+        let outcome = block_on(service.load_popular(&CancellationHandle::new())).unwrap();
+
+        assert_eq!(state.lock().calls, 1);
+        assert_eq!(outcome.apps.len(), 1);
+        assert_eq!(outcome.apps[0].app_id(), "org.example.Fresh");
+        assert!(outcome.cache_age.unwrap() < Duration::from_secs(60));
+        assert!(!service.is_stale());
+    }
+
+    #[test]
+    fn empty_cache_is_stale_and_has_no_age() {
+        let (listing, _) = Listing::new("native::listing");
+        let host = PluginHostBuilder::new().with_backend(listing).build().unwrap();
+        let service = AppStoreService::new(host, Arc::new(MemoryCacheStore::new()));
+
+        assert!(service.is_stale());
+        assert_eq!(service.cache_snapshot().cache_age, None);
+    }
+
+    #[test]
+    fn categories_are_listed_once_per_max_age() {
+        let (listing, state) = Listing::new("native::listing");
+        state.lock().categories = vec![Category {
+            id: "Graphics".to_string(),
+            name: "Graphics".to_string(),
+            icon: None,
+        }];
+        let host = PluginHostBuilder::new().with_backend(listing).build().unwrap();
+        let service = AppStoreService::new(host, Arc::new(MemoryCacheStore::new()));
+        let cancel = CancellationHandle::new();
+
+        let first = block_on(service.categories(&cancel)).unwrap();
+        let second = block_on(service.categories(&cancel)).unwrap();
+        assert_eq!(first.categories, second.categories);
+        assert_eq!(first.categories[0].id, "Graphics");
+        assert_eq!(state.lock().category_calls, 1);
+
+        service.clear_cache().unwrap();
+        block_on(service.categories(&cancel)).unwrap();
+        assert_eq!(state.lock().category_calls, 2);
+
+        let service = service.with_max_age(Duration::ZERO);
+        block_on(service.categories(&cancel)).unwrap();
+        assert_eq!(state.lock().category_calls, 3);
+    }
+
+    #[test]
+    fn category_apps_are_cached_per_category() {
+        let (listing, state) = Listing::new("native::listing");
+        state.lock().apps = vec![listed("org.example.Paint", "Paint")];
+        let host = PluginHostBuilder::new().with_backend(listing).build().unwrap();
+        let service = AppStoreService::new(host, Arc::new(MemoryCacheStore::new()));
+        let cancel = CancellationHandle::new();
+
+        let graphics = block_on(service.category_apps("Graphics", &cancel)).unwrap();
+        assert_eq!(graphics.category_id, "Graphics");
+        assert_eq!(graphics.apps[0].app_id(), "org.example.Paint");
+        block_on(service.category_apps("Graphics", &cancel)).unwrap();
+        assert_eq!(state.lock().category_calls, 1);
+
+        block_on(service.category_apps("Office", &cancel)).unwrap();
+        assert_eq!(state.lock().category_calls, 2);
+
+        // Listed apps can be looked up without being cached.
+        assert!(service.cached_app("org.example.Paint").is_none());
+        assert!(service.find_app("org.example.Paint").is_some());
+    }
+
+    const FRACTAL: &str = "org.gnome.Fractal";
+
+    fn updated_apps(updates: &[AppUpdate]) -> Vec<(&str, &str)> {
+        updates
+            .iter()
+            .map(|update| (update.plugin.as_str(), update.app.app_id.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn update_checks_store_and_announce_pending_updates() {
+        let host = PluginHostBuilder::new()
+            .with_backend(mock("native::mock").with_update(FRACTAL, "10"))
+            .build()
+            .unwrap();
+        let store = Arc::new(MemoryCacheStore::new());
+        let service = AppStoreService::new(host, store.clone());
+        let mut events = service.subscribe();
+        let cancel = CancellationHandle::new();
+
+        let outcome = block_on(service.refresh_updates(&cancel)).unwrap();
+
+        assert_eq!(updated_apps(&outcome.updates), [("native::mock", FRACTAL)]);
+        assert_eq!(outcome.updates[0].version_to.as_deref(), Some("10"));
+        assert!(outcome.warnings.is_empty());
+        let (stored, checked_at) = store.load_updates().unwrap();
+        assert_eq!(stored, outcome.updates);
+        assert!(checked_at.is_some());
+        assert!(matches!(
+            events.try_recv(),
+            Ok(StoreEvent::UpdatesChanged(updates)) if updates == outcome.updates
+        ));
+
+        // Nothing changed, so nothing is announced.
+        block_on(service.refresh_updates(&cancel)).unwrap();
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn finished_update_is_forgotten() {
+        let host = PluginHostBuilder::new()
+            .with_backend(mock("native::mock").with_update(FRACTAL, "10"))
+            .build()
+            .unwrap();
+        let store = Arc::new(MemoryCacheStore::new());
+        let service = AppStoreService::new(host, store.clone());
+        let cancel = CancellationHandle::new();
+        let update = block_on(service.refresh_updates(&cancel)).unwrap().updates[0].clone();
+        let mut events = service.subscribe();
+
+        let job = service.update(&update, &cancel).unwrap();
+        assert_eq!(block_on(job.collect::<Vec<_>>()), finished_job());
+
+        assert!(service.update_snapshot().updates.is_empty());
+        assert!(store.load_updates().unwrap().0.is_empty());
+        assert!(matches!(
+            events.try_recv(),
+            Ok(StoreEvent::UpdatesChanged(updates)) if updates.is_empty()
+        ));
+        assert!(block_on(service.refresh_updates(&cancel)).unwrap().updates.is_empty());
+    }
+
+    #[test]
+    fn update_all_keeps_going_after_a_failed_update() {
+        // Scripted backends do not implement updates, so theirs fail.
+        let (scripted, _) = Scripted::new("native::scripted", &["org.example.App"]);
+        let host = PluginHostBuilder::new()
+            .with_backend(scripted)
+            .with_backend(mock("native::mock").with_update(FRACTAL, "10"))
+            .build()
+            .unwrap();
+        let service = AppStoreService::new(host, Arc::new(MemoryCacheStore::new()));
+        let cancel = CancellationHandle::new();
+        block_on(service.refresh_updates(&cancel)).unwrap();
+
+        let progress = block_on(service.update_all(&cancel).collect::<Vec<_>>());
+
+        let failed = progress
+            .iter()
+            .filter(|item| item.update.plugin == "native::scripted")
+            .collect::<Vec<_>>();
+        assert_eq!(failed.len(), 1);
+        assert!(matches!(
+            &failed[0].progress,
+            Err(HostError::Plugin(failure)) if failure.kind
+                == PluginFailureKind::Execution(PluginExecutionError::Unsupported)
+        ));
+        let applied = progress
+            .iter()
+            .filter(|item| item.update.plugin == "native::mock")
+            .map(|item| item.progress.clone().unwrap())
+            .collect::<Vec<_>>();
+        let expected = finished_job().into_iter().map(Result::unwrap).collect::<Vec<_>>();
+        assert_eq!(applied, expected);
+        assert_eq!(
+            updated_apps(&service.update_snapshot().updates),
+            [("native::scripted", "org.example.App")]
+        );
+    }
+
+    #[test]
+    fn merge_takes_metadata_by_priority_and_keeps_every_source() {
+        let (first, _) = Scripted::new(
+            "native::first",
+            &["org.example.Shared", "org.example.First"],
+        );
+        let (second, _) = Scripted::new("native::second", &["org.example.Shared"]);
+        let host = PluginHostBuilder::new()
+            .with_backend(first)
+            .with_backend(second)
+            .with_priority(["native::second"])
+            .build()
+            .unwrap();
+
+        let apps = popular(&host);
+        assert_eq!(apps.len(), 2);
+        let shared = apps
+            .iter()
+            .find(|app| app.app_id() == "org.example.Shared")
+            .unwrap();
+        assert_eq!(shared.primary.name, "org.example.Shared from native::second");
+        let plugins = shared
+            .sources
+            .iter()
+            .map(|source| source.plugin.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(plugins, ["native::second", "native::first"]);
+        let only_first = apps
+            .iter()
+            .find(|app| app.app_id() == "org.example.First")
+            .unwrap();
+        assert_eq!(only_first.sources.len(), 1);
+        assert_eq!(only_first.sources[0].plugin, "native::first");
+    }
+
+    #[test]
+    fn details_are_fetched_from_the_plugin_of_each_source() {
+        let (first, first_behaviour) =
+            Scripted::new("native::first", &["org.example.Shared"]);
+        let (second, _) = Scripted::new("native::second", &["org.example.Shared"]);
+        let host = PluginHostBuilder::new()
+            .with_backend(first)
+            .with_backend(second)
+            .with_priority(["native::second"])
+            .build()
+            .unwrap();
+        let service = AppStoreService::new(host, Arc::new(MemoryCacheStore::new()));
+        block_on(service.refresh_popular(&CancellationHandle::new())).unwrap();
+
+        // Both plugins report the source `scripted`; the first registered
+        // one failing must not hide the preferred one.
+        *first_behaviour.lock() = Behaviour::Fail;
+        let cancel = CancellationHandle::new();
+        let details = block_on(service.app_details("org.example.Shared", &cancel)).unwrap();
+        assert_eq!(details.app.name, "org.example.Shared from native::second");
+    }
+
+    #[test]
+    fn search_ranks_exact_then_prefix_then_substring_then_summary() {
+        let app = |app_id: &str, name: &str, summary: &str| MergedApp {
+            primary: AppSummary {
+                summary: summary.to_string(),
+                ..listed(app_id, name)
+            },
+            sources: Vec::new(),
+        };
+        let apps = vec![
+            app("org.example.Jotter", "Jotter", "Take a note"),
+            app("org.example.Calculator", "Calculator", "Adds numbers"),
+            app("org.example.NoteTaker", "Taker", ""),
+            app("org.example.QuickNotes", "Quick Notes", ""),
+            app("org.example.Notebook", "Notebook", ""),
+            app("org.example.Note", "Note", ""),
+        ];
+
+        let ranked = rank_search_results("note", apps);
+
+        let names = ranked.iter().map(|app| app.primary.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            ["Note", "Notebook", "Quick Notes", "Taker", "Jotter", "Calculator"]
+        );
+    }
+
+    #[test]
+    fn search_merges_matches_across_backends() {
+        let (failing, behaviour) = Scripted::new("native::failing", &[]);
+        *behaviour.lock() = Behaviour::Fail;
+        let host = PluginHostBuilder::new()
+            .with_backend(mock("native::first"))
+            .with_backend(failing)
+            .with_backend(mock("native::second"))
+            .build()
+            .unwrap();
+        let service = AppStoreService::new(host, Arc::new(MemoryCacheStore::new()));
+
+        let outcome = block_on(service.search("  fire ", &CancellationHandle::new())).unwrap();
+
+        assert_eq!(outcome.query, "fire");
+        assert_eq!(outcome.apps.len(), 1);
+        assert_eq!(outcome.apps[0].app_id(), FIREFOX);
+        let plugins = outcome.apps[0]
+            .sources
+            .iter()
+            .map(|source| source.plugin.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(plugins, ["native::first", "native::second"]);
+        assert_eq!(outcome.warnings.len(), 1);
+        assert_eq!(outcome.warnings[0].plugin, "native::failing");
+        assert_eq!(service.find_app(FIREFOX).unwrap(), outcome.apps[0]);
+    }
+
+    fn repository(plugin: &str, id: &str, enabled: bool) -> Repository {
+        Repository {
+            id: id.to_string(),
+            name: id.to_string(),
+            url: None,
+            enabled,
+            plugin: plugin.to_string(),
+        }
+    }
+
+    #[test]
+    fn repository_diffs() {
+        let flathub = repository("native::mock", "flathub", true);
+        let beta = repository("native::mock", "flathub-beta", false);
+        let beta_enabled = repository("native::mock", "flathub-beta", true);
+        let other_flathub = repository("native::other", "flathub", true);
+        let listed = HashMap::from([("native::mock".to_string(), SystemTime::now())]);
+        let cases = [
+            (
+                "unchanged",
+                vec![flathub.clone(), beta.clone()],
+                vec![flathub.clone(), beta.clone()],
+                vec![],
+            ),
+            (
+                "added",
+                vec![flathub.clone()],
+                vec![flathub.clone(), beta.clone()],
+                vec![RepositoryChange::Added(beta.clone())],
+            ),
+            (
+                "removed",
+                vec![flathub.clone(), beta.clone()],
+                vec![flathub.clone()],
+                vec![RepositoryChange::Removed(beta.clone())],
+            ),
+            (
+                "toggled",
+                vec![flathub.clone(), beta.clone()],
+                vec![flathub.clone(), beta_enabled.clone()],
+                vec![RepositoryChange::Changed(beta_enabled.clone())],
+            ),
+            (
+                "reordered",
+                vec![flathub.clone(), beta.clone()],
+                vec![beta.clone(), flathub.clone()],
+                vec![],
+            ),
+            (
+                "plugin listed for the first time",
+                vec![flathub.clone()],
+                vec![flathub.clone(), other_flathub.clone()],
+                vec![],
+            ),
+        ];
+
+        for (name, before, after, expected) in cases {
+            assert_eq!(diff_repositories(&before, &after, &listed), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn details_of_an_app_without_sources_are_unknown() {
+        let store = Arc::new(MemoryCacheStore::new());
+        let orphan = MergedApp {
+            primary: listed("org.example.Orphan", "Orphan"),
+            sources: Vec::new(),
+        };
+        store.store_apps(&[orphan]).unwrap();
+        let host = PluginHostBuilder::new()
+            .with_backend(mock("native::mock"))
+            .build()
+            .unwrap();
+        let service = AppStoreService::new(host, store);
+        let cancel = CancellationHandle::new();
+
+        assert!(matches!(
+            block_on(service.app_details("org.example.Orphan", &cancel)),
+            Err(HostError::UnknownApp(app_id)) if app_id == "org.example.Orphan"
+        ));
+        assert!(matches!(
+            block_on(service.app_details("org.example.Missing", &cancel)),
+            Err(HostError::UnknownApp(_))
+        ));
+    }
+
+    #[test]
+    fn repositories_with_the_same_id_are_told_apart_by_plugin() {
+        let host = PluginHostBuilder::new()
+            .with_backend(mock("native::first"))
+            .with_backend(mock("native::second"))
+            .build()
+            .unwrap();
+        let service = AppStoreService::new(host, Arc::new(MemoryCacheStore::new()));
+        let cancel = CancellationHandle::new();
+        block_on(service.refresh_repositories(&cancel)).unwrap();
+
+        let first = service.find_repository("flathub", None).unwrap();
+        assert_eq!(first.plugin, "native::first");
+        let second = service
+            .find_repository("flathub", Some("native::second"))
+            .unwrap();
+        assert_eq!(second.plugin, "native::second");
+        assert!(matches!(
+            service.find_repository("flathub", Some("native::missing")),
+            Err(HostError::UnknownRepository(_))
+        ));
+
+        block_on(service.manage_repository(&second, RepositoryAction::Disable, &cancel)).unwrap();
+        let enabled = |plugin| {
+            service
+                .find_repository("flathub", Some(plugin))
+                .unwrap()
+                .enabled
+        };
+        assert!(enabled("native::first"));
+        assert!(!enabled("native::second"));
+
+        block_on(service.manage_repository(&first, RepositoryAction::Remove, &cancel)).unwrap();
+        let remaining = service.find_repository("flathub", None).unwrap();
+        assert_eq!(remaining.plugin, "native::second");
     }
 }
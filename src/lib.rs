@@ -1,7 +1,16 @@
+pub mod cache;
+pub mod cancellation;
+pub mod cli;
 pub mod ffi;
+pub mod helper;
 pub mod host;
 pub mod plugins;
+pub mod registry;
+pub mod scheduler;
 pub mod ui;
 
+pub use cancellation::CancellationHandle;
 pub use host::{AppStoreService, PluginHost, PluginHostBuilder};
+pub mod app_object;
 pub mod application;
+pub mod repository_object;
@@ -1,25 +1,223 @@
+use std::collections::HashSet;
+use std::future::Future;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
 
 use async_trait::async_trait;
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
 #[cfg(feature = "legacy-ffi")]
 use once_cell::sync::OnceCell;
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::cancellation::CancellationHandle;
+
 #[cfg(feature = "legacy-ffi")]
 mod legacy;
+mod appstream;
+mod desktop;
+
+pub use appstream::AppStreamPlugin;
+pub use desktop::DesktopPlugin;
+
+/// How old gnome-software metadata may get before legacy plugins refresh it.
+const DEFAULT_METADATA_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
 
 /// Minimal subset of app metadata needed for the InstallGrid UI.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AppSummary {
     pub app_id: String,
     pub name: String,
     pub summary: String,
     pub source: String,
+    /// Whether the app is present on this system, as far as the reporting
+    /// backend (or [`PluginBackend::list_installed_apps`]) knows.
+    #[serde(default)]
+    pub installed: bool,
+}
+
+/// Whether an app is present on the system.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InstallState {
+    #[default]
+    Unknown,
+    Available,
+    Installed,
+    /// Installed with a newer version available.
+    Updatable,
+}
+
+/// Full metadata for a single app, fetched on demand.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppDetails {
+    pub app: AppSummary,
+    /// Long description; may contain AppStream markup.
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    /// SPDX license expression.
+    #[serde(default)]
+    pub license: Option<String>,
+    #[serde(default)]
+    pub developer: Option<String>,
+    #[serde(default)]
+    pub homepage: Option<String>,
+    /// Themed icon name, file path or URL, as understood by `gio::Icon`.
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Screenshot image URLs, the default screenshot first.
+    #[serde(default)]
+    pub screenshots: Vec<String>,
+    #[serde(default)]
+    pub install_state: InstallState,
+    #[serde(default)]
+    pub download_size: Option<u64>,
+    #[serde(default)]
+    pub installed_size: Option<u64>,
+    /// Minimum age from the app's OARS content rating.
+    #[serde(default)]
+    pub content_rating: Option<u32>,
+}
+
+impl AppDetails {
+    /// Details carrying only what `app` already knows.
+    pub fn from_summary(app: AppSummary) -> Self {
+        Self {
+            app,
+            description: None,
+            version: None,
+            license: None,
+            developer: None,
+            homepage: None,
+            icon: None,
+            categories: Vec::new(),
+            screenshots: Vec::new(),
+            install_state: InstallState::Unknown,
+            download_size: None,
+            installed_size: None,
+            content_rating: None,
+        }
+    }
+}
+
+/// A group of apps backends can list with
+/// [`PluginBackend::list_category_apps`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Category {
+    /// Backend-specific id, e.g. a freedesktop main category such as
+    /// `Graphics`. Backends sharing an id are merged.
+    pub id: String,
+    pub name: String,
+    /// Themed icon name.
+    #[serde(default)]
+    pub icon: Option<String>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// freedesktop.org main categories as `(id, name, icon)`, in the order
+/// native backends list them.
+const MAIN_CATEGORIES: &[(&str, &str, &str)] = &[
+    ("AudioVideo", "Audio & Video", "applications-multimedia"),
+    ("Development", "Developer Tools", "applications-development"),
+    ("Education", "Education", "applications-science"),
+    ("Game", "Games", "applications-games"),
+    ("Graphics", "Graphics & Photography", "applications-graphics"),
+    ("Network", "Networking", "applications-internet"),
+    ("Office", "Productivity", "applications-office"),
+    ("Science", "Science", "applications-science"),
+    ("Settings", "Settings", "preferences-system"),
+    ("System", "System", "applications-system"),
+    ("Utility", "Utilities", "applications-utilities"),
+];
+
+/// The main categories used by any of `categories`. Additional categories
+/// such as `2DGraphics` are not listed on their own.
+pub(crate) fn main_categories<'a>(
+    categories: impl IntoIterator<Item = &'a String>,
+) -> Vec<Category> {
+    let used = categories.into_iter().collect::<HashSet<_>>();
+    MAIN_CATEGORIES
+        .iter()
+        .filter(|(id, _, _)| used.contains(&id.to_string()))
+        .map(|(id, name, icon)| Category {
+            id: id.to_string(),
+            name: name.to_string(),
+            icon: Some(icon.to_string()),
+        })
+        .collect()
+}
+
+/// A newer version of an installed app, as reported by
+/// [`PluginBackend::list_updates`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppUpdate {
+    pub app: AppSummary,
+    /// Installed version.
+    #[serde(default)]
+    pub version_from: Option<String>,
+    /// Version the update installs.
+    #[serde(default)]
+    pub version_to: Option<String>,
+    /// Bytes to download.
+    #[serde(default)]
+    pub download_size: Option<u64>,
+    /// Id of the backend that reported the update; set by the host.
+    #[serde(default)]
+    pub plugin: String,
+}
+
+/// A remote a backend installs apps from or reads its catalogue from, such
+/// as a Flatpak remote or an AppStream origin.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Repository {
+    /// Unique within the owning plugin, e.g. the Flatpak remote name.
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub url: Option<String>,
+    pub enabled: bool,
+    /// Id of the backend that reported the repository; set by the host.
+    #[serde(default)]
+    pub plugin: String,
+}
+
+/// Change applied to a known repository through
+/// [`PluginBackend::manage_repository`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RepositoryAction {
+    Enable,
+    Disable,
+    Remove,
+}
+
+/// A backend able to provide an app, and the origin it reported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppSource {
+    pub plugin: String,
+    pub source: String,
+}
+
+/// One app as reported by every backend that knows it, merged by `app_id`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergedApp {
+    /// Metadata from the highest-priority backend.
+    pub primary: AppSummary,
+    /// Every backend offering this app, primary first.
+    pub sources: Vec<AppSource>,
+}
+
+impl MergedApp {
+    pub fn app_id(&self) -> &str {
+        &self.primary.app_id
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PluginKind {
     Legacy,
     Native,
@@ -31,25 +229,113 @@ pub struct PluginDescriptor {
     pub kind: PluginKind,
 }
 
-#[derive(Debug, Error, Clone)]
+#[derive(Debug, Error, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PluginExecutionError {
     #[error("legacy backend unavailable")]
     LegacyUnavailable,
+    /// Something the backend depends on is missing, such as the system bus.
+    #[error("backend unavailable: {0}")]
+    BackendUnavailable(String),
     #[error("operation failed: {0}")]
     Operation(String),
     #[error("timed out after {0:?}")]
     Timeout(Duration),
+    #[error("cancelled")]
+    Cancelled,
+    /// The process running the plugin went away (see `crate::helper`).
+    #[error("plugin process exited: {0}")]
+    ProcessExited(String),
+    /// The plugin panicked inside its helper process. Counted like a panic
+    /// in this process.
+    #[error("{0}")]
+    Panicked(String),
+    /// The backend does not implement the operation. Not counted against
+    /// the plugin's health.
+    #[error("not supported by this plugin")]
+    Unsupported,
 }
 
-#[derive(Debug, Error, Clone)]
+#[derive(Debug, Error, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PluginFailureKind {
     #[error("{0}")]
-    Execution(#[from] PluginExecutionError),
+    Execution(PluginExecutionError),
     #[error("panic in plugin")]
     Panic,
+    #[error("plugin quarantined after repeated panics")]
+    Quarantined,
+    #[error("plugin disabled")]
+    Disabled,
+    #[error("plugin helper crashed: {0}")]
+    Crashed(String),
 }
 
-#[derive(Debug, Clone)]
+impl From<PluginExecutionError> for PluginFailureKind {
+    fn from(err: PluginExecutionError) -> Self {
+        match err {
+            PluginExecutionError::ProcessExited(status) => PluginFailureKind::Crashed(status),
+            PluginExecutionError::Panicked(_) => PluginFailureKind::Panic,
+            other => PluginFailureKind::Execution(other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JobKind {
+    Install,
+    Remove,
+    Update,
+}
+
+/// Progress reported by an install, remove or update job.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JobProgress {
+    Queued,
+    /// `percent` is `None` while the backend cannot estimate progress.
+    Running { percent: Option<u8> },
+    Finished,
+}
+
+/// Progress stream returned by job operations. It ends after
+/// [`JobProgress::Finished`] or the first error.
+pub type JobStream = BoxStream<'static, Result<JobProgress, PluginExecutionError>>;
+
+/// Sending half handed to job implementations built with [`job_stream`].
+#[derive(Clone)]
+pub struct JobReporter {
+    tx: async_channel::Sender<Result<JobProgress, PluginExecutionError>>,
+}
+
+impl JobReporter {
+    pub async fn report(&self, progress: JobProgress) {
+        let _ = self.tx.send(Ok(progress)).await;
+    }
+}
+
+/// Turns `work` into a [`JobStream`]. The job starts when the stream is first
+/// polled; an `Err` returned by `work` becomes the final item.
+pub fn job_stream<F, Fut>(work: F) -> JobStream
+where
+    F: FnOnce(JobReporter) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), PluginExecutionError>> + Send + 'static,
+{
+    let (tx, rx) = async_channel::unbounded();
+    let driver = stream::once(async move {
+        let reporter = JobReporter { tx: tx.clone() };
+        if let Err(err) = work(reporter).await {
+            let _ = tx.send(Err(err)).await;
+        }
+    })
+    .filter_map(|()| futures::future::ready(None));
+
+    stream::select(driver, rx).boxed()
+}
+
+/// A [`JobStream`] that fails immediately with `err`.
+pub fn failed_job(err: PluginExecutionError) -> JobStream {
+    stream::once(futures::future::ready(Err(err))).boxed()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PluginFailure {
     pub plugin: String,
     pub kind: PluginFailureKind,
@@ -59,13 +345,120 @@ pub struct PluginFailure {
 #[async_trait]
 pub trait PluginBackend: Send + Sync {
     fn descriptor(&self) -> &PluginDescriptor;
-    async fn list_popular_apps(&self) -> Result<Vec<AppSummary>, PluginExecutionError>;
+    /// Backends should return [`PluginExecutionError::Cancelled`] promptly once
+    /// `cancel` fires; the host stops waiting for them either way.
+    async fn list_popular_apps(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError>;
+
+    /// Full-text search over the backend's catalogue. Matching is up to the
+    /// backend; the host merges and ranks the combined results.
+    async fn search(
+        &self,
+        query: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError>;
+
+    /// Apps present on this system, with `installed` set. Backends that cannot
+    /// tell report nothing.
+    async fn list_installed_apps(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        let _ = cancel;
+        Ok(Vec::new())
+    }
+
+    /// Categories this backend can list apps for. Backends without
+    /// categories report none.
+    async fn list_categories(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<Category>, PluginExecutionError> {
+        let _ = cancel;
+        Ok(Vec::new())
+    }
+
+    /// Apps in `category_id`. Ids this backend did not list yield no apps.
+    async fn list_category_apps(
+        &self,
+        category_id: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        let _ = (category_id, cancel);
+        Ok(Vec::new())
+    }
+
+    /// Installed apps with a newer version available. Backends that cannot
+    /// tell report none.
+    async fn list_updates(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppUpdate>, PluginExecutionError> {
+        let _ = cancel;
+        Ok(Vec::new())
+    }
+
+    /// Full metadata for `app_id`; the host only routes apps whose `source`
+    /// this backend reported.
+    async fn app_details(
+        &self,
+        app_id: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<AppDetails, PluginExecutionError>;
+
+    /// Repositories this backend knows, enabled or not. Backends without
+    /// repositories report none.
+    async fn list_repositories(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<Repository>, PluginExecutionError> {
+        let _ = cancel;
+        Ok(Vec::new())
+    }
+
+    /// Adds and enables the repository described by a `.flatpakrepo` file.
+    async fn add_repository(
+        &self,
+        file: &Path,
+        cancel: &CancellationHandle,
+    ) -> Result<Repository, PluginExecutionError> {
+        let _ = (file, cancel);
+        Err(PluginExecutionError::Unsupported)
+    }
+
+    /// Enables, disables or removes a repository this backend listed.
+    async fn manage_repository(
+        &self,
+        repository_id: &str,
+        action: RepositoryAction,
+        cancel: &CancellationHandle,
+    ) -> Result<(), PluginExecutionError> {
+        let _ = (repository_id, action, cancel);
+        Err(PluginExecutionError::Unsupported)
+    }
+
+    /// Installs `app_id`; the host only routes apps whose `source` this
+    /// backend reported.
+    fn install(&self, app_id: &str, cancel: CancellationHandle) -> JobStream;
+
+    fn remove(&self, app_id: &str, cancel: CancellationHandle) -> JobStream;
+
+    /// Updates `app_id` to the version reported by
+    /// [`PluginBackend::list_updates`].
+    fn update(&self, app_id: &str, cancel: CancellationHandle) -> JobStream {
+        let _ = (app_id, cancel);
+        failed_job(PluginExecutionError::Unsupported)
+    }
 }
 
 pub struct LegacyPluginAdapter {
     descriptor: PluginDescriptor,
     #[cfg_attr(not(feature = "legacy-ffi"), allow(dead_code))]
     plugin_name: Arc<String>,
+    #[cfg_attr(not(feature = "legacy-ffi"), allow(dead_code))]
+    metadata_max_age: Duration,
     #[cfg(feature = "legacy-ffi")]
     loader: OnceCell<Result<Arc<legacy::FlatpakLoader>, PluginExecutionError>>,
 }
@@ -79,10 +472,44 @@ impl LegacyPluginAdapter {
                 kind: PluginKind::Legacy,
             },
             plugin_name: Arc::new(name),
+            metadata_max_age: DEFAULT_METADATA_MAX_AGE,
             #[cfg(feature = "legacy-ffi")]
             loader: OnceCell::new(),
         }
     }
+
+    /// Age after which gnome-software plugins re-download their metadata
+    /// when apps are listed. Defaults to one day.
+    pub fn with_metadata_max_age(mut self, max_age: Duration) -> Self {
+        self.metadata_max_age = max_age;
+        self
+    }
+
+    #[cfg(feature = "legacy-ffi")]
+    fn loader(&self) -> Result<Arc<legacy::FlatpakLoader>, PluginExecutionError> {
+        self.loader
+            .get_or_init(|| {
+                legacy::FlatpakLoader::new(self.plugin_name.as_ref(), self.metadata_max_age)
+                    .map(Arc::new)
+            })
+            .clone()
+    }
+
+    fn app_job(&self, app_id: &str, kind: JobKind, cancel: CancellationHandle) -> JobStream {
+        #[cfg(feature = "legacy-ffi")]
+        {
+            match self.loader() {
+                Ok(loader) => legacy::app_job(loader, app_id.to_string(), kind, cancel),
+                Err(err) => failed_job(err),
+            }
+        }
+
+        #[cfg(not(feature = "legacy-ffi"))]
+        {
+            let _ = (app_id, kind, cancel);
+            failed_job(PluginExecutionError::LegacyUnavailable)
+        }
+    }
 }
 
 #[async_trait]
@@ -91,41 +518,199 @@ impl PluginBackend for LegacyPluginAdapter {
         &self.descriptor
     }
 
-    async fn list_popular_apps(&self) -> Result<Vec<AppSummary>, PluginExecutionError> {
+    async fn list_popular_apps(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        #[cfg(feature = "legacy-ffi")]
+        {
+            let loader = self.loader()?;
+            return legacy::list_all_apps(loader, cancel.clone()).await;
+        }
+
+        #[cfg(not(feature = "legacy-ffi"))]
+        {
+            let _ = cancel;
+            Err(PluginExecutionError::LegacyUnavailable)
+        }
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        #[cfg(feature = "legacy-ffi")]
+        {
+            let loader = self.loader()?;
+            return legacy::search_apps(loader, query, cancel.clone()).await;
+        }
+
+        #[cfg(not(feature = "legacy-ffi"))]
+        {
+            let _ = (query, cancel);
+            Err(PluginExecutionError::LegacyUnavailable)
+        }
+    }
+
+    async fn app_details(
+        &self,
+        app_id: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<AppDetails, PluginExecutionError> {
+        #[cfg(feature = "legacy-ffi")]
+        {
+            let loader = self.loader()?;
+            return legacy::app_details(loader, app_id, cancel.clone()).await;
+        }
+
+        #[cfg(not(feature = "legacy-ffi"))]
+        {
+            let _ = (app_id, cancel);
+            Err(PluginExecutionError::LegacyUnavailable)
+        }
+    }
+
+    async fn list_categories(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<Category>, PluginExecutionError> {
+        #[cfg(feature = "legacy-ffi")]
+        {
+            let loader = self.loader()?;
+            return legacy::list_categories(loader, cancel.clone()).await;
+        }
+
+        #[cfg(not(feature = "legacy-ffi"))]
+        {
+            let _ = cancel;
+            Err(PluginExecutionError::LegacyUnavailable)
+        }
+    }
+
+    async fn list_category_apps(
+        &self,
+        category_id: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        #[cfg(feature = "legacy-ffi")]
+        {
+            let loader = self.loader()?;
+            return legacy::list_category_apps(loader, category_id, cancel.clone()).await;
+        }
+
+        #[cfg(not(feature = "legacy-ffi"))]
+        {
+            let _ = (category_id, cancel);
+            Err(PluginExecutionError::LegacyUnavailable)
+        }
+    }
+
+    async fn list_updates(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppUpdate>, PluginExecutionError> {
+        #[cfg(feature = "legacy-ffi")]
+        {
+            let loader = self.loader()?;
+            return legacy::list_updates(loader, cancel.clone()).await;
+        }
+
+        #[cfg(not(feature = "legacy-ffi"))]
+        {
+            let _ = cancel;
+            Err(PluginExecutionError::LegacyUnavailable)
+        }
+    }
+
+    async fn list_repositories(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<Repository>, PluginExecutionError> {
         #[cfg(feature = "legacy-ffi")]
         {
-            let loader_entry = self.loader.get_or_init(|| {
-                legacy::FlatpakLoader::new(self.plugin_name.as_ref()).map(Arc::new)
-            });
+            let loader = self.loader()?;
+            return legacy::list_repositories(loader, cancel.clone()).await;
+        }
 
-            let loader = match loader_entry {
-                Ok(handle) => handle.clone(),
-                Err(err) => return Err(err.clone()),
-            };
+        #[cfg(not(feature = "legacy-ffi"))]
+        {
+            let _ = cancel;
+            Err(PluginExecutionError::LegacyUnavailable)
+        }
+    }
 
-            return legacy::list_all_apps(loader).await;
+    async fn add_repository(
+        &self,
+        file: &Path,
+        cancel: &CancellationHandle,
+    ) -> Result<Repository, PluginExecutionError> {
+        #[cfg(feature = "legacy-ffi")]
+        {
+            let loader = self.loader()?;
+            return legacy::add_repository(loader, file, cancel.clone()).await;
         }
 
         #[cfg(not(feature = "legacy-ffi"))]
         {
+            let _ = (file, cancel);
             Err(PluginExecutionError::LegacyUnavailable)
         }
     }
+
+    async fn manage_repository(
+        &self,
+        repository_id: &str,
+        action: RepositoryAction,
+        cancel: &CancellationHandle,
+    ) -> Result<(), PluginExecutionError> {
+        #[cfg(feature = "legacy-ffi")]
+        {
+            let loader = self.loader()?;
+            return legacy::manage_repository(loader, repository_id, action, cancel.clone()).await;
+        }
+
+        #[cfg(not(feature = "legacy-ffi"))]
+        {
+            let _ = (repository_id, action, cancel);
+            Err(PluginExecutionError::LegacyUnavailable)
+        }
+    }
+
+    fn install(&self, app_id: &str, cancel: CancellationHandle) -> JobStream {
+        self.app_job(app_id, JobKind::Install, cancel)
+    }
+
+    fn remove(&self, app_id: &str, cancel: CancellationHandle) -> JobStream {
+        self.app_job(app_id, JobKind::Remove, cancel)
+    }
+
+    fn update(&self, app_id: &str, cancel: CancellationHandle) -> JobStream {
+        self.app_job(app_id, JobKind::Update, cancel)
+    }
 }
 
 pub struct NativeMockPlugin {
     descriptor: PluginDescriptor,
     delay: Duration,
+    installed: Arc<RwLock<HashSet<String>>>,
+    /// Pending updates as `(app_id, version_to)`.
+    updates: Arc<RwLock<Vec<(String, String)>>>,
+    repositories: Arc<RwLock<Vec<Repository>>>,
 }
 
 impl NativeMockPlugin {
     pub fn new(id: impl Into<String>) -> Self {
+        let id = id.into();
         Self {
+            repositories: Arc::new(RwLock::new(mock_repositories(&id))),
             descriptor: PluginDescriptor {
-                id: id.into(),
+                id,
                 kind: PluginKind::Native,
             },
             delay: Duration::from_millis(250),
+            installed: Arc::new(RwLock::new(HashSet::new())),
+            updates: Arc::new(RwLock::new(Vec::new())),
         }
     }
 
@@ -133,6 +718,103 @@ impl NativeMockPlugin {
         self.delay = delay;
         self
     }
+
+    /// Marks `app_id` as already installed, e.g. to exercise removal.
+    pub fn with_installed(self, app_id: impl Into<String>) -> Self {
+        self.installed.write().insert(app_id.into());
+        self
+    }
+
+    /// Scripts an update of `app_id` to `version`, marking the app installed.
+    pub fn with_update(self, app_id: impl Into<String>, version: impl Into<String>) -> Self {
+        let app_id = app_id.into();
+        self.installed.write().insert(app_id.clone());
+        self.updates.write().push((app_id, version.into()));
+        self
+    }
+
+    pub fn is_installed(&self, app_id: &str) -> bool {
+        self.installed.read().contains(app_id)
+    }
+
+    fn pending_update(&self, app_id: &str) -> Option<String> {
+        pending_update(&self.updates.read(), app_id)
+    }
+
+    /// Simulates a job in five steps of `delay / 5`, updating the installed
+    /// set and pending updates once the last step completes.
+    fn simulated_job(&self, app_id: &str, kind: JobKind, cancel: CancellationHandle) -> JobStream {
+        let app_id = app_id.to_string();
+        let installed = self.installed.clone();
+        let updates = self.updates.clone();
+        let step = self.delay / 5;
+
+        job_stream(move |reporter| async move {
+            if !mock_catalog().iter().any(|app| app.app_id == app_id) {
+                return Err(PluginExecutionError::Operation(format!("unknown app {app_id}")));
+            }
+            let is_installed = installed.read().contains(&app_id);
+            match kind {
+                JobKind::Install if is_installed => {
+                    return Err(PluginExecutionError::Operation(format!(
+                        "{app_id} is already installed"
+                    )));
+                }
+                JobKind::Remove | JobKind::Update if !is_installed => {
+                    return Err(PluginExecutionError::Operation(format!(
+                        "{app_id} is not installed"
+                    )));
+                }
+                JobKind::Update if pending_update(&updates.read(), &app_id).is_none() => {
+                    return Err(PluginExecutionError::Operation(format!(
+                        "{app_id} is up to date"
+                    )));
+                }
+                _ => {}
+            }
+
+            reporter.report(JobProgress::Queued).await;
+            for percent in [0u8, 25, 50, 75, 100] {
+                tokio::select! {
+                    _ = tokio::time::sleep(step) => {}
+                    _ = cancel.cancelled() => return Err(PluginExecutionError::Cancelled),
+                }
+                reporter
+                    .report(JobProgress::Running {
+                        percent: Some(percent),
+                    })
+                    .await;
+            }
+
+            match kind {
+                JobKind::Install => {
+                    installed.write().insert(app_id);
+                }
+                JobKind::Remove => {
+                    installed.write().remove(&app_id);
+                    updates.write().retain(|(pending, _)| *pending != app_id);
+                }
+                JobKind::Update => updates.write().retain(|(pending, _)| *pending != app_id),
+            }
+            reporter.report(JobProgress::Finished).await;
+            Ok(())
+        })
+    }
+
+    fn with_install_flags(&self, mut apps: Vec<AppSummary>) -> Vec<AppSummary> {
+        let installed = self.installed.read();
+        for app in &mut apps {
+            app.installed = installed.contains(&app.app_id);
+        }
+        apps
+    }
+
+    async fn simulate_latency(&self, cancel: &CancellationHandle) -> Result<(), PluginExecutionError> {
+        tokio::select! {
+            _ = tokio::time::sleep(self.delay) => Ok(()),
+            _ = cancel.cancelled() => Err(PluginExecutionError::Cancelled),
+        }
+    }
 }
 
 #[async_trait]
@@ -141,27 +823,423 @@ impl PluginBackend for NativeMockPlugin {
         &self.descriptor
     }
 
-    async fn list_popular_apps(&self) -> Result<Vec<AppSummary>, PluginExecutionError> {
-        tokio::time::sleep(self.delay).await;
-        Ok(vec![
-            AppSummary {
-                app_id: "org.gnome.Fractal".to_string(),
-                name: "Fractal".to_string(),
-                summary: "Matrix messaging client for GNOME.".to_string(),
-                source: "mock::flatpak".to_string(),
-            },
-            AppSummary {
-                app_id: "org.gimp.GIMP".to_string(),
-                name: "GNU Image Manipulation Program".to_string(),
-                summary: "Powerful graphics editor.".to_string(),
-                source: "mock::flatpak".to_string(),
-            },
-            AppSummary {
-                app_id: "org.mozilla.firefox".to_string(),
-                name: "Firefox".to_string(),
-                summary: "Web browser focused on privacy.".to_string(),
-                source: "mock::packagekit".to_string(),
-            },
-        ])
+    async fn list_popular_apps(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        self.simulate_latency(cancel).await?;
+        Ok(self.with_install_flags(mock_catalog()))
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        self.simulate_latency(cancel).await?;
+        let terms = query
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+        let matches = mock_catalog()
+            .into_iter()
+            .filter(|app| {
+                let haystack =
+                    format!("{} {} {}", app.app_id, app.name, app.summary).to_lowercase();
+                terms.iter().any(|term| haystack.contains(term.as_str()))
+            })
+            .collect();
+        Ok(self.with_install_flags(matches))
+    }
+
+    async fn list_installed_apps(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        self.simulate_latency(cancel).await?;
+        Ok(self
+            .with_install_flags(mock_catalog())
+            .into_iter()
+            .filter(|app| app.installed)
+            .collect())
+    }
+
+    async fn list_categories(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<Category>, PluginExecutionError> {
+        self.simulate_latency(cancel).await?;
+        let details = mock_catalog()
+            .iter()
+            .filter_map(|app| mock_details(&app.app_id))
+            .collect::<Vec<_>>();
+        Ok(main_categories(
+            details.iter().flat_map(|details| &details.categories),
+        ))
+    }
+
+    async fn list_category_apps(
+        &self,
+        category_id: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        self.simulate_latency(cancel).await?;
+        let apps = mock_catalog()
+            .into_iter()
+            .filter(|app| {
+                mock_details(&app.app_id).is_some_and(|details| {
+                    details.categories.iter().any(|id| id == category_id)
+                })
+            })
+            .collect();
+        Ok(self.with_install_flags(apps))
+    }
+
+    async fn list_updates(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppUpdate>, PluginExecutionError> {
+        self.simulate_latency(cancel).await?;
+        let installed = self.installed.read();
+        Ok(mock_catalog()
+            .into_iter()
+            .filter(|app| installed.contains(&app.app_id))
+            .filter_map(|app| {
+                let version_to = self.pending_update(&app.app_id)?;
+                let details = mock_details(&app.app_id)?;
+                Some(AppUpdate {
+                    app: AppSummary {
+                        installed: true,
+                        ..app
+                    },
+                    version_from: details.version,
+                    version_to: Some(version_to),
+                    // Roughly what a delta download would take.
+                    download_size: details.download_size.map(|size| size / 4),
+                    plugin: self.descriptor.id.clone(),
+                })
+            })
+            .collect())
+    }
+
+    async fn app_details(
+        &self,
+        app_id: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<AppDetails, PluginExecutionError> {
+        self.simulate_latency(cancel).await?;
+        let mut details = mock_details(app_id)
+            .ok_or_else(|| PluginExecutionError::Operation(format!("unknown app {app_id}")))?;
+        details.app.installed = self.is_installed(app_id);
+        details.install_state = if !details.app.installed {
+            InstallState::Available
+        } else if self.pending_update(app_id).is_some() {
+            InstallState::Updatable
+        } else {
+            InstallState::Installed
+        };
+        Ok(details)
+    }
+
+    async fn list_repositories(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<Repository>, PluginExecutionError> {
+        self.simulate_latency(cancel).await?;
+        Ok(self.repositories.read().clone())
+    }
+
+    /// Reads `Title` and `Url` from the file; the id is the file stem, as
+    /// `flatpak remote-add --from` would name the remote.
+    async fn add_repository(
+        &self,
+        file: &Path,
+        cancel: &CancellationHandle,
+    ) -> Result<Repository, PluginExecutionError> {
+        self.simulate_latency(cancel).await?;
+        let repository = read_flatpakrepo(file, &self.descriptor.id)?;
+
+        let mut repositories = self.repositories.write();
+        if repositories.iter().any(|known| known.id == repository.id) {
+            return Err(PluginExecutionError::Operation(format!(
+                "repository {} already exists",
+                repository.id
+            )));
+        }
+        repositories.push(repository.clone());
+        Ok(repository)
+    }
+
+    async fn manage_repository(
+        &self,
+        repository_id: &str,
+        action: RepositoryAction,
+        cancel: &CancellationHandle,
+    ) -> Result<(), PluginExecutionError> {
+        self.simulate_latency(cancel).await?;
+        let mut repositories = self.repositories.write();
+        let index = repositories
+            .iter()
+            .position(|repository| repository.id == repository_id)
+            .ok_or_else(|| {
+                PluginExecutionError::Operation(format!("unknown repository {repository_id}"))
+            })?;
+        match action {
+            RepositoryAction::Enable => repositories[index].enabled = true,
+            RepositoryAction::Disable => repositories[index].enabled = false,
+            RepositoryAction::Remove => {
+                repositories.remove(index);
+            }
+        }
+        Ok(())
+    }
+
+    fn install(&self, app_id: &str, cancel: CancellationHandle) -> JobStream {
+        self.simulated_job(app_id, JobKind::Install, cancel)
+    }
+
+    fn remove(&self, app_id: &str, cancel: CancellationHandle) -> JobStream {
+        self.simulated_job(app_id, JobKind::Remove, cancel)
+    }
+
+    fn update(&self, app_id: &str, cancel: CancellationHandle) -> JobStream {
+        self.simulated_job(app_id, JobKind::Update, cancel)
+    }
+}
+
+fn pending_update(updates: &[(String, String)], app_id: &str) -> Option<String> {
+    updates
+        .iter()
+        .find(|(pending, _)| pending == app_id)
+        .map(|(_, version)| version.clone())
+}
+
+fn mock_catalog() -> Vec<AppSummary> {
+    vec![
+        AppSummary {
+            app_id: "org.gnome.Fractal".to_string(),
+            name: "Fractal".to_string(),
+            summary: "Matrix messaging client for GNOME.".to_string(),
+            source: "mock::flatpak".to_string(),
+            installed: false,
+        },
+        AppSummary {
+            app_id: "org.gimp.GIMP".to_string(),
+            name: "GNU Image Manipulation Program".to_string(),
+            summary: "Powerful graphics editor.".to_string(),
+            source: "mock::flatpak".to_string(),
+            installed: false,
+        },
+        AppSummary {
+            app_id: "org.mozilla.firefox".to_string(),
+            name: "Firefox".to_string(),
+            summary: "Web browser focused on privacy.".to_string(),
+            source: "mock::packagekit".to_string(),
+            installed: false,
+        },
+    ]
+}
+
+/// Parses the `[Flatpak Repo]` group of a `.flatpakrepo` file.
+fn read_flatpakrepo(file: &Path, plugin: &str) -> Result<Repository, PluginExecutionError> {
+    const GROUP: &str = "Flatpak Repo";
+
+    let invalid = |reason: String| {
+        PluginExecutionError::Operation(format!("{}: {reason}", file.display()))
+    };
+    let key_file = glib::KeyFile::new();
+    key_file
+        .load_from_file(file, glib::KeyFileFlags::NONE)
+        .map_err(|err| invalid(err.to_string()))?;
+
+    let id = file
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|stem| !stem.is_empty())
+        .ok_or_else(|| invalid("cannot derive a repository name".to_string()))?
+        .to_string();
+    let url = key_file
+        .string(GROUP, "Url")
+        .map_err(|err| invalid(err.to_string()))?
+        .to_string();
+    let name = key_file
+        .locale_string(GROUP, "Title", None)
+        .map(String::from)
+        .unwrap_or_else(|_| id.clone());
+
+    Ok(Repository {
+        id,
+        name,
+        url: Some(url),
+        enabled: true,
+        plugin: plugin.to_string(),
+    })
+}
+
+fn mock_repositories(plugin: &str) -> Vec<Repository> {
+    vec![
+        Repository {
+            id: "flathub".to_string(),
+            name: "Flathub".to_string(),
+            url: Some("https://dl.flathub.org/repo/".to_string()),
+            enabled: true,
+            plugin: plugin.to_string(),
+        },
+        Repository {
+            id: "flathub-beta".to_string(),
+            name: "Flathub Beta".to_string(),
+            url: Some("https://dl.flathub.org/beta-repo/".to_string()),
+            enabled: false,
+            plugin: plugin.to_string(),
+        },
+        Repository {
+            id: "packagekit".to_string(),
+            name: "Distribution packages".to_string(),
+            url: None,
+            enabled: true,
+            plugin: plugin.to_string(),
+        },
+    ]
+}
+
+fn mock_details(app_id: &str) -> Option<AppDetails> {
+    let app = mock_catalog().into_iter().find(|app| app.app_id == app_id)?;
+    let mut details = AppDetails::from_summary(app);
+    details.icon = Some(app_id.to_string());
+
+    match app_id {
+        "org.gnome.Fractal" => {
+            details.description = Some(
+                "Fractal is a Matrix messaging app for GNOME written in Rust.".to_string(),
+            );
+            details.version = Some("9".to_string());
+            details.license = Some("GPL-3.0-or-later".to_string());
+            details.developer = Some("The Fractal Team".to_string());
+            details.homepage = Some("https://gitlab.gnome.org/World/fractal".to_string());
+            details.categories = vec!["Network".to_string(), "Chat".to_string()];
+            details.download_size = Some(9_400_000);
+            details.installed_size = Some(31_200_000);
+            details.content_rating = Some(12);
+        }
+        "org.gimp.GIMP" => {
+            details.description = Some(
+                "GIMP is an advanced picture editor for retouching photos and creating art."
+                    .to_string(),
+            );
+            details.version = Some("2.10.38".to_string());
+            details.license = Some("GPL-3.0-or-later".to_string());
+            details.developer = Some("The GIMP Team".to_string());
+            details.homepage = Some("https://www.gimp.org/".to_string());
+            details.categories = vec!["Graphics".to_string(), "2DGraphics".to_string()];
+            details.download_size = Some(98_000_000);
+            details.installed_size = Some(312_000_000);
+            details.content_rating = Some(0);
+        }
+        "org.mozilla.firefox" => {
+            details.description =
+                Some("Firefox is a fast, private web browser from Mozilla.".to_string());
+            details.version = Some("131.0".to_string());
+            details.license = Some("MPL-2.0".to_string());
+            details.developer = Some("Mozilla".to_string());
+            details.homepage = Some("https://www.mozilla.org/firefox/".to_string());
+            details.categories = vec!["Network".to_string(), "WebBrowser".to_string()];
+            details.download_size = Some(84_000_000);
+            details.installed_size = Some(250_000_000);
+            details.content_rating = Some(0);
+        }
+        _ => {}
+    }
+
+    Some(details)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run<T>(future: impl Future<Output = T>) -> T {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn repositories(plugin: &NativeMockPlugin) -> Vec<(String, bool)> {
+        run(plugin.list_repositories(&CancellationHandle::new()))
+            .unwrap()
+            .into_iter()
+            .map(|repository| (repository.id, repository.enabled))
+            .collect()
+    }
+
+    fn manage(
+        plugin: &NativeMockPlugin,
+        repository_id: &str,
+        action: RepositoryAction,
+    ) -> Result<(), PluginExecutionError> {
+        run(plugin.manage_repository(repository_id, action, &CancellationHandle::new()))
+    }
+
+    #[test]
+    fn mock_repositories_are_enabled_disabled_and_removed() {
+        let plugin = NativeMockPlugin::new("native::mock").with_delay(Duration::ZERO);
+        let known = |id: &str, enabled| (id.to_string(), enabled);
+        assert_eq!(
+            repositories(&plugin),
+            [known("flathub", true), known("flathub-beta", false), known("packagekit", true)]
+        );
+
+        manage(&plugin, "flathub-beta", RepositoryAction::Enable).unwrap();
+        manage(&plugin, "flathub", RepositoryAction::Disable).unwrap();
+        manage(&plugin, "packagekit", RepositoryAction::Remove).unwrap();
+        assert_eq!(
+            repositories(&plugin),
+            [known("flathub", false), known("flathub-beta", true)]
+        );
+
+        assert!(matches!(
+            manage(&plugin, "packagekit", RepositoryAction::Enable),
+            Err(PluginExecutionError::Operation(_))
+        ));
+    }
+
+    #[test]
+    fn mock_repositories_are_added_from_flatpakrepo_files() {
+        let plugin = NativeMockPlugin::new("native::mock").with_delay(Duration::ZERO);
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("gnome-nightly.flatpakrepo");
+        std::fs::write(
+            &file,
+            "[Flatpak Repo]\nTitle=GNOME Nightly\nUrl=https://nightly.gnome.org/repo/\n",
+        )
+        .unwrap();
+
+        let added = run(plugin.add_repository(&file, &CancellationHandle::new())).unwrap();
+        assert_eq!(
+            added,
+            Repository {
+                id: "gnome-nightly".to_string(),
+                name: "GNOME Nightly".to_string(),
+                url: Some("https://nightly.gnome.org/repo/".to_string()),
+                enabled: true,
+                plugin: "native::mock".to_string(),
+            }
+        );
+        assert!(run(plugin.list_repositories(&CancellationHandle::new()))
+            .unwrap()
+            .contains(&added));
+
+        // Adding it again, or a file without a URL, fails.
+        assert!(matches!(
+            run(plugin.add_repository(&file, &CancellationHandle::new())),
+            Err(PluginExecutionError::Operation(_))
+        ));
+        let no_url = dir.path().join("broken.flatpakrepo");
+        std::fs::write(&no_url, "[Flatpak Repo]\nTitle=Broken\n").unwrap();
+        assert!(matches!(
+            run(plugin.add_repository(&no_url, &CancellationHandle::new())),
+            Err(PluginExecutionError::Operation(_))
+        ));
     }
 }
@@ -0,0 +1,1148 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use gio::prelude::*;
+use parking_lot::Mutex;
+
+use crate::cancellation::CancellationHandle;
+
+use super::{
+    failed_job, main_categories, AppDetails, AppSummary, Category, InstallState, JobStream,
+    PluginBackend, PluginDescriptor, PluginExecutionError, PluginKind, Repository,
+};
+
+/// Kudo or custom key that marks an app as popular in GNOME Software's own
+/// curated metadata.
+const DEFAULT_POPULAR_TAG: &str = "GnomeSoftware::popular";
+/// Apps returned by `list_popular_apps` when no component is tagged popular.
+const FALLBACK_POPULAR_LIMIT: usize = 50;
+/// How deep to look below each configured directory; Flatpak nests catalogues
+/// as `<remote>/<arch>/active/appstream.xml.gz`.
+const MAX_SCAN_DEPTH: usize = 5;
+const INCLUDED_TYPES: &[&str] = &["desktop-application", "desktop", "console-application"];
+
+/// Reads AppStream catalogues, XML or DEP-11 YAML and optionally gzipped,
+/// from a set of directories.
+///
+/// Directories are rescanned on every call, but only files added or
+/// modified since the last call are parsed again.
+pub struct AppStreamPlugin {
+    descriptor: PluginDescriptor,
+    dirs: Vec<PathBuf>,
+    popular_tags: Arc<HashSet<String>>,
+    catalog: Arc<Mutex<Option<Arc<Catalog>>>>,
+}
+
+impl AppStreamPlugin {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            descriptor: PluginDescriptor {
+                id: id.into(),
+                kind: PluginKind::Native,
+            },
+            dirs: Vec::new(),
+            popular_tags: Arc::new(HashSet::from([DEFAULT_POPULAR_TAG.to_string()])),
+            catalog: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Well-known system and per-user catalogue locations.
+    pub fn default_dirs() -> Vec<PathBuf> {
+        vec![
+            PathBuf::from("/var/lib/flatpak/appstream"),
+            glib::user_data_dir().join("flatpak").join("appstream"),
+            PathBuf::from("/usr/share/swcatalog"),
+            PathBuf::from("/var/cache/swcatalog"),
+            PathBuf::from("/usr/share/app-info"),
+            PathBuf::from("/var/cache/app-info"),
+        ]
+    }
+
+    pub fn with_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dirs.push(dir.into());
+        self
+    }
+
+    pub fn with_dirs<I, P>(mut self, dirs: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.dirs.extend(dirs.into_iter().map(Into::into));
+        self
+    }
+
+    /// Kudos or `<custom>` keys that mark a component as popular, replacing
+    /// the default `GnomeSoftware::popular`.
+    pub fn with_popular_tags<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.popular_tags = Arc::new(tags.into_iter().map(Into::into).collect());
+        self
+    }
+
+    async fn catalog(&self, cancel: &CancellationHandle) -> Result<Arc<Catalog>, PluginExecutionError> {
+        let dirs = self.dirs.clone();
+        let popular_tags = self.popular_tags.clone();
+        let cached = self.catalog.clone();
+
+        let load = tokio::task::spawn_blocking(move || {
+            let files = scan_dirs(&dirs);
+            if files.is_empty() {
+                return Err(PluginExecutionError::Operation(format!(
+                    "no AppStream catalogues found in {}",
+                    dirs.iter()
+                        .map(|dir| dir.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )));
+            }
+
+            let mut cached = cached.lock();
+            if let Some(catalog) = cached.as_ref() {
+                if catalog.files == files {
+                    return Ok(catalog.clone());
+                }
+            }
+            let catalog = Arc::new(Catalog::load(files, cached.as_deref(), &popular_tags)?);
+            *cached = Some(catalog.clone());
+            Ok(catalog)
+        });
+
+        tokio::select! {
+            joined = load => joined.map_err(|err| {
+                PluginExecutionError::Operation(format!("AppStream loader join error: {err}"))
+            })?,
+            _ = cancel.cancelled() => Err(PluginExecutionError::Cancelled),
+        }
+    }
+}
+
+#[async_trait]
+impl PluginBackend for AppStreamPlugin {
+    fn descriptor(&self) -> &PluginDescriptor {
+        &self.descriptor
+    }
+
+    /// Components tagged popular, or the most-awarded ones when the
+    /// catalogues carry no popular tags.
+    async fn list_popular_apps(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        let catalog = self.catalog(cancel).await?;
+
+        let popular = catalog
+            .entries
+            .iter()
+            .filter(|entry| entry.popular)
+            .map(|entry| entry.details.app.clone())
+            .collect::<Vec<_>>();
+        if !popular.is_empty() {
+            return Ok(popular);
+        }
+
+        let mut ranked = catalog.entries.iter().collect::<Vec<_>>();
+        ranked.sort_by(|left, right| {
+            right
+                .kudos
+                .len()
+                .cmp(&left.kudos.len())
+                .then_with(|| left.details.app.name.cmp(&right.details.app.name))
+        });
+        Ok(ranked
+            .into_iter()
+            .take(FALLBACK_POPULAR_LIMIT)
+            .map(|entry| entry.details.app.clone())
+            .collect())
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        let catalog = self.catalog(cancel).await?;
+        let terms = query
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        Ok(catalog
+            .entries
+            .iter()
+            .filter(|entry| {
+                terms
+                    .iter()
+                    .any(|term| entry.haystack.contains(term.as_str()))
+            })
+            .map(|entry| entry.details.app.clone())
+            .collect())
+    }
+
+    async fn app_details(
+        &self,
+        app_id: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<AppDetails, PluginExecutionError> {
+        let catalog = self.catalog(cancel).await?;
+        catalog
+            .entries
+            .iter()
+            .find(|entry| entry.details.app.app_id == app_id)
+            .map(|entry| entry.details.clone())
+            .ok_or_else(|| PluginExecutionError::Operation(format!("unknown app {app_id}")))
+    }
+
+    /// Main categories named in the components' `<categories>`.
+    async fn list_categories(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<Category>, PluginExecutionError> {
+        let catalog = self.catalog(cancel).await?;
+        Ok(main_categories(
+            catalog
+                .entries
+                .iter()
+                .flat_map(|entry| &entry.details.categories),
+        ))
+    }
+
+    async fn list_category_apps(
+        &self,
+        category_id: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        let catalog = self.catalog(cancel).await?;
+        Ok(catalog
+            .entries
+            .iter()
+            .filter(|entry| entry.details.categories.iter().any(|id| id == category_id))
+            .map(|entry| entry.details.app.clone())
+            .collect())
+    }
+
+    /// Every origin named by the loaded catalogues. Whatever is on disk is
+    /// in use, so all of them are enabled.
+    async fn list_repositories(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<Repository>, PluginExecutionError> {
+        let catalog = self.catalog(cancel).await?;
+        Ok(catalog
+            .origins
+            .iter()
+            .map(|origin| Repository {
+                id: origin.clone(),
+                name: origin.clone(),
+                url: None,
+                enabled: true,
+                plugin: self.descriptor.id.clone(),
+            })
+            .collect())
+    }
+
+    fn install(&self, _app_id: &str, _cancel: CancellationHandle) -> JobStream {
+        failed_job(read_only())
+    }
+
+    fn remove(&self, _app_id: &str, _cancel: CancellationHandle) -> JobStream {
+        failed_job(read_only())
+    }
+}
+
+fn read_only() -> PluginExecutionError {
+    PluginExecutionError::Operation("AppStream catalogues are read-only".to_string())
+}
+
+/// Identity of a catalogue file; a change in any of them triggers a reload.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CatalogFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+struct Catalog {
+    files: Vec<CatalogFile>,
+    /// Components read from each file, reused while the file is unchanged.
+    parsed: HashMap<CatalogFile, Arc<Vec<Component>>>,
+    entries: Vec<CatalogEntry>,
+    /// Origins of every component read, in catalogue order.
+    origins: Vec<String>,
+}
+
+struct CatalogEntry {
+    details: AppDetails,
+    kudos: Vec<String>,
+    popular: bool,
+    /// Lower-cased id, name, summary and keywords for search.
+    haystack: String,
+}
+
+impl Catalog {
+    /// Reads `files`, taking the components of files that did not change
+    /// from `previous`.
+    fn load(
+        files: Vec<CatalogFile>,
+        previous: Option<&Catalog>,
+        popular_tags: &HashSet<String>,
+    ) -> Result<Self, PluginExecutionError> {
+        let mut parsed = HashMap::new();
+        let mut entries: Vec<CatalogEntry> = Vec::new();
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        let mut origins: Vec<String> = Vec::new();
+
+        for file in &files {
+            let reused = previous.and_then(|previous| previous.parsed.get(file)).cloned();
+            let components = match reused {
+                Some(components) => components,
+                None => match read_catalog_file(&file.path) {
+                    Ok(components) => Arc::new(components),
+                    Err(err) => {
+                        eprintln!(
+                            "InstallGrid: skipping AppStream catalogue {}: {err}",
+                            file.path.display()
+                        );
+                        continue;
+                    }
+                },
+            };
+            parsed.insert(file.clone(), components.clone());
+
+            for component in components.iter() {
+                if !origins.contains(&component.origin) {
+                    origins.push(component.origin.clone());
+                }
+                let entry = component.clone().into_entry(popular_tags);
+                // The first catalogue to describe an app wins, matching the
+                // directory order given to the plugin.
+                if let Some(&index) = seen.get(&entry.details.app.app_id) {
+                    entries[index].popular |= entry.popular;
+                    continue;
+                }
+                seen.insert(entry.details.app.app_id.clone(), entries.len());
+                entries.push(entry);
+            }
+        }
+
+        if parsed.is_empty() {
+            return Err(PluginExecutionError::Operation(
+                "no readable AppStream catalogues".to_string(),
+            ));
+        }
+        Ok(Self {
+            files,
+            parsed,
+            entries,
+            origins,
+        })
+    }
+}
+
+/// Component fields shared by the XML and DEP-11 readers.
+#[derive(Clone, Default)]
+struct Component {
+    id: String,
+    origin: String,
+    name: Option<String>,
+    summary: Option<String>,
+    description: Option<String>,
+    developer: Option<String>,
+    license: Option<String>,
+    homepage: Option<String>,
+    icon: Option<String>,
+    version: Option<String>,
+    categories: Vec<String>,
+    screenshots: Vec<String>,
+    keywords: Vec<String>,
+    kudos: Vec<String>,
+    custom: HashMap<String, String>,
+}
+
+impl Component {
+    fn into_entry(self, popular_tags: &HashSet<String>) -> CatalogEntry {
+        let popular = self
+            .kudos
+            .iter()
+            .chain(self.custom.keys())
+            .any(|tag| popular_tags.contains(tag));
+
+        let name = self.name.unwrap_or_else(|| self.id.clone());
+        let summary = self.summary.unwrap_or_default();
+        let haystack = format!(
+            "{} {} {} {}",
+            self.id,
+            name,
+            summary,
+            self.keywords.join(" ")
+        )
+        .to_lowercase();
+
+        let mut details = AppDetails::from_summary(AppSummary {
+            app_id: self.id,
+            name,
+            summary,
+            source: format!("appstream::{}", self.origin),
+            installed: false,
+        });
+        details.description = self.description;
+        details.developer = self.developer;
+        details.license = self.license;
+        details.homepage = self.homepage;
+        details.icon = self.icon;
+        details.version = self.version;
+        details.categories = self.categories;
+        details.screenshots = self.screenshots;
+        details.install_state = InstallState::Unknown;
+
+        CatalogEntry {
+            details,
+            kudos: self.kudos,
+            popular,
+            haystack,
+        }
+    }
+}
+
+fn scan_dirs(dirs: &[PathBuf]) -> Vec<CatalogFile> {
+    let mut files = Vec::new();
+    for dir in dirs {
+        scan_dir(dir, 0, &mut files);
+    }
+    files
+}
+
+fn scan_dir(dir: &Path, depth: usize, files: &mut Vec<CatalogFile>) {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut paths = read_dir
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    for path in paths {
+        // `metadata` follows symlinks, e.g. Flatpak's `active` link.
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if depth < MAX_SCAN_DEPTH {
+                scan_dir(&path, depth + 1, files);
+            }
+        } else if catalog_format(&path).is_some() {
+            files.push(CatalogFile {
+                path,
+                modified: metadata.modified().ok(),
+                len: metadata.len(),
+            });
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Xml,
+    Yaml,
+}
+
+/// Detects the format from the file name, ignoring a trailing `.gz`.
+fn catalog_format(path: &Path) -> Option<Format> {
+    let name = path.file_name()?.to_str()?;
+    let name = name.strip_suffix(".gz").unwrap_or(name);
+    if name.ends_with(".xml") {
+        Some(Format::Xml)
+    } else if name.ends_with(".yml") || name.ends_with(".yaml") {
+        Some(Format::Yaml)
+    } else {
+        None
+    }
+}
+
+fn read_catalog_file(path: &Path) -> Result<Vec<Component>, String> {
+    let format = catalog_format(path).ok_or("unsupported file name")?;
+    let text = read_text(path)?;
+    // Fall back to the file stem when the collection names no origin.
+    let fallback_origin = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.split('.').next())
+        .unwrap_or("local")
+        .to_string();
+
+    match format {
+        Format::Xml => parse_xml(&text, &fallback_origin),
+        Format::Yaml => parse_dep11(&text, &fallback_origin),
+    }
+}
+
+/// Reads `path` as UTF-8, transparently inflating `.gz` files through GIO.
+fn read_text(path: &Path) -> Result<String, String> {
+    let is_gzip = path.extension().is_some_and(|extension| extension == "gz");
+    if !is_gzip {
+        return std::fs::read_to_string(path).map_err(|err| err.to_string());
+    }
+
+    let file = gio::File::for_path(path);
+    let input = file
+        .read(gio::Cancellable::NONE)
+        .map_err(|err| err.to_string())?;
+    let decompressor = gio::ZlibDecompressor::new(gio::ZlibCompressorFormat::Gzip);
+    let converted = gio::ConverterInputStream::new(&input, &decompressor);
+
+    let mut text = String::new();
+    converted
+        .into_read()
+        .read_to_string(&mut text)
+        .map_err(|err| err.to_string())?;
+    Ok(text)
+}
+
+fn parse_xml(text: &str, fallback_origin: &str) -> Result<Vec<Component>, String> {
+    let document = roxmltree::Document::parse(text).map_err(|err| err.to_string())?;
+    let root = document.root_element();
+
+    let (origin, media_base, nodes) = match root.tag_name().name() {
+        "components" => (
+            root.attribute("origin").unwrap_or(fallback_origin),
+            root.attribute("media_baseurl"),
+            root.children().filter(|node| node.has_tag_name("component")).collect(),
+        ),
+        // A lone metainfo file.
+        "component" => (fallback_origin, None, vec![root]),
+        other => return Err(format!("unexpected root element <{other}>")),
+    };
+
+    Ok(nodes
+        .into_iter()
+        .filter(|node| {
+            INCLUDED_TYPES.contains(&node.attribute("type").unwrap_or("desktop-application"))
+                && node.attribute("merge").is_none()
+        })
+        .filter_map(|node| xml_component(node, origin, media_base))
+        .collect())
+}
+
+fn xml_component(
+    node: roxmltree::Node<'_, '_>,
+    origin: &str,
+    media_base: Option<&str>,
+) -> Option<Component> {
+    let id = xml_child_text(node, "id")?;
+    let children = |name: &'static str| {
+        node.children()
+            .filter(move |child| child.has_tag_name(name))
+    };
+    let list = |parent: &'static str, item: &'static str| {
+        children(parent)
+            .flat_map(|parent| parent.children().filter(|child| child.has_tag_name(item)))
+            .filter(|child| is_unlocalized(*child))
+            .filter_map(|child| child.text())
+            .map(|text| text.trim().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let developer = xml_child_text(node, "developer_name").or_else(|| {
+        children("developer")
+            .next()
+            .and_then(|developer| xml_child_text(developer, "name"))
+    });
+    let homepage = children("url")
+        .find(|url| url.attribute("type") == Some("homepage"))
+        .and_then(|url| url.text())
+        .map(|text| text.trim().to_string());
+    let icon = ["stock", "remote", "cached", "local"].iter().find_map(|kind| {
+        children("icon")
+            .find(|icon| icon.attribute("type") == Some(*kind))
+            .and_then(|icon| icon.text())
+            .map(|text| text.trim().to_string())
+    });
+    let version = children("releases")
+        .flat_map(|releases| releases.children().filter(|child| child.has_tag_name("release")))
+        .find_map(|release| release.attribute("version"))
+        .map(str::to_string);
+    let description = children("description")
+        .find(|description| is_unlocalized(*description))
+        .map(|description| {
+            description
+                .children()
+                .filter(|child| child.is_element() && is_unlocalized(*child))
+                .map(|paragraph| {
+                    paragraph
+                        .descendants()
+                        .filter(|node| node.is_text())
+                        .filter_map(|text| text.text())
+                        .collect::<String>()
+                        .split_whitespace()
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .filter(|paragraph| !paragraph.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        })
+        .filter(|description| !description.is_empty());
+    // The source image of each screenshot, or its first thumbnail.
+    let mut screenshots = children("screenshots")
+        .flat_map(|screenshots| {
+            screenshots
+                .children()
+                .filter(|child| child.has_tag_name("screenshot"))
+        })
+        .filter_map(|screenshot| {
+            let images = screenshot
+                .children()
+                .filter(|child| child.has_tag_name("image"))
+                .collect::<Vec<_>>();
+            let image = images
+                .iter()
+                .find(|image| image.attribute("type") == Some("source"))
+                .or_else(|| images.first())?;
+            let url = media_url(media_base, image.text()?.trim());
+            Some((screenshot.attribute("type") == Some("default"), url))
+        })
+        .collect::<Vec<_>>();
+    screenshots.sort_by_key(|(default, _)| !default);
+    let custom = children("custom")
+        .flat_map(|custom| custom.children().filter(|child| child.has_tag_name("value")))
+        .filter_map(|value| {
+            Some((
+                value.attribute("key")?.to_string(),
+                value.text().unwrap_or_default().trim().to_string(),
+            ))
+        })
+        .collect();
+
+    Some(Component {
+        id,
+        origin: origin.to_string(),
+        name: xml_child_text(node, "name"),
+        summary: xml_child_text(node, "summary"),
+        description,
+        developer,
+        license: xml_child_text(node, "project_license"),
+        homepage,
+        icon,
+        version,
+        categories: list("categories", "category"),
+        screenshots: screenshots.into_iter().map(|(_, url)| url).collect(),
+        keywords: list("keywords", "keyword"),
+        kudos: list("kudos", "kudo"),
+        custom,
+    })
+}
+
+/// Text of the first untranslated `<name>` child.
+fn xml_child_text(node: roxmltree::Node<'_, '_>, name: &str) -> Option<String> {
+    node.children()
+        .filter(|child| child.has_tag_name(name) && is_unlocalized(*child))
+        .find_map(|child| child.text())
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+fn is_unlocalized(node: roxmltree::Node<'_, '_>) -> bool {
+    node.attribute(("http://www.w3.org/XML/1998/namespace", "lang"))
+        .is_none()
+}
+
+fn parse_dep11(text: &str, fallback_origin: &str) -> Result<Vec<Component>, String> {
+    use serde::Deserialize;
+    use serde_yaml::Value;
+
+    let mut origin = fallback_origin.to_string();
+    let mut media_base = None;
+    let mut components = Vec::new();
+
+    for (index, document) in serde_yaml::Deserializer::from_str(text).enumerate() {
+        let value = Value::deserialize(document).map_err(|err| err.to_string())?;
+        // The first document is the collection header.
+        if index == 0 {
+            if value.get("File").and_then(Value::as_str) != Some("DEP-11") {
+                return Err("missing DEP-11 header".to_string());
+            }
+            if let Some(header_origin) = value.get("Origin").and_then(Value::as_str) {
+                origin = header_origin.to_string();
+            }
+            media_base = value
+                .get("MediaBaseUrl")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            continue;
+        }
+
+        let kind = value
+            .get("Type")
+            .and_then(Value::as_str)
+            .unwrap_or("desktop-application");
+        if !INCLUDED_TYPES.contains(&kind) || value.get("Merge").is_some() {
+            continue;
+        }
+        let Some(id) = value.get("ID").and_then(Value::as_str) else {
+            continue;
+        };
+
+        let strings = |value: Option<&Value>| {
+            value
+                .and_then(Value::as_sequence)
+                .map(|items| {
+                    items
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(str::to_string)
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+
+        let icon = value.get("Icon").and_then(|icon| {
+            icon.get("stock")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .or_else(|| {
+                    icon.get("remote")
+                        .and_then(Value::as_sequence)
+                        .and_then(|remote| remote.first())
+                        .and_then(|remote| remote.get("url"))
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                })
+                .or_else(|| {
+                    icon.get("cached")
+                        .and_then(Value::as_sequence)
+                        .and_then(|cached| cached.first())
+                        .and_then(|cached| cached.get("name"))
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                })
+        });
+        let mut screenshots = value
+            .get("Screenshots")
+            .and_then(Value::as_sequence)
+            .map(|screenshots| {
+                screenshots
+                    .iter()
+                    .filter_map(|screenshot| {
+                        let url = screenshot
+                            .get("source-image")
+                            .or_else(|| {
+                                screenshot
+                                    .get("thumbnails")
+                                    .and_then(Value::as_sequence)
+                                    .and_then(|thumbnails| thumbnails.first())
+                            })
+                            .and_then(|image| image.get("url"))
+                            .and_then(Value::as_str)?;
+                        let default = screenshot.get("default").and_then(Value::as_bool);
+                        Some((default == Some(true), media_url(media_base.as_deref(), url)))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        screenshots.sort_by_key(|(default, _)| !default);
+
+        let custom = value
+            .get("Custom")
+            .and_then(Value::as_mapping)
+            .map(|custom| {
+                custom
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        Some((key.as_str()?.to_string(), yaml_scalar(value).unwrap_or_default()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        components.push(Component {
+            id: id.to_string(),
+            origin: origin.clone(),
+            name: localized(value.get("Name")),
+            summary: localized(value.get("Summary")),
+            description: localized(value.get("Description")).map(|html| strip_markup(&html)),
+            developer: localized(value.get("DeveloperName")),
+            license: value
+                .get("ProjectLicense")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            homepage: value
+                .get("Url")
+                .and_then(|url| url.get("homepage"))
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            icon,
+            version: value
+                .get("Releases")
+                .and_then(Value::as_sequence)
+                .and_then(|releases| releases.first())
+                .and_then(|release| release.get("version"))
+                .and_then(yaml_scalar),
+            categories: strings(value.get("Categories")),
+            screenshots: screenshots.into_iter().map(|(_, url)| url).collect(),
+            keywords: value
+                .get("Keywords")
+                .map(|keywords| {
+                    strings(keywords.get("C").or_else(|| keywords.get("en")))
+                })
+                .unwrap_or_default(),
+            kudos: strings(value.get("Kudos")),
+            custom,
+        });
+    }
+
+    Ok(components)
+}
+
+/// Resolves a media path against the catalogue's base URL; absolute URLs
+/// are kept.
+fn media_url(base: Option<&str>, url: &str) -> String {
+    match base {
+        Some(base) if !url.contains("://") => {
+            format!("{}/{}", base.trim_end_matches('/'), url.trim_start_matches('/'))
+        }
+        _ => url.to_string(),
+    }
+}
+
+/// Untranslated value of a DEP-11 `{C: ..., de: ...}` map.
+fn localized(value: Option<&serde_yaml::Value>) -> Option<String> {
+    let value = value?;
+    value
+        .get("C")
+        .or_else(|| value.get("en"))
+        .or_else(|| value.as_mapping().and_then(|map| map.values().next()))
+        .and_then(yaml_scalar)
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+fn yaml_scalar(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(text) => Some(text.clone()),
+        serde_yaml::Value::Number(number) => Some(number.to_string()),
+        serde_yaml::Value::Bool(flag) => Some(flag.to_string()),
+        _ => None,
+    }
+}
+
+/// Flattens DEP-11 description HTML into paragraphs of plain text.
+fn strip_markup(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    let mut tag = String::new();
+    for ch in html.chars() {
+        match ch {
+            '<' => {
+                in_tag = true;
+                tag.clear();
+            }
+            '>' if in_tag => {
+                in_tag = false;
+                if matches!(tag.trim_start_matches('/'), "p" | "li" | "ul" | "ol") {
+                    text.push('\n');
+                }
+            }
+            _ if in_tag => tag.push(ch),
+            _ => text.push(ch),
+        }
+    }
+
+    text.split('\n')
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+        .replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<components version="0.14" origin="flathub" media_baseurl="https://dl.flathub.org/media">
+  <component type="desktop-application">
+    <id>org.gnome.Maps</id>
+    <name>Maps</name>
+    <name xml:lang="de">Karten</name>
+    <summary>Find places around the world</summary>
+    <description>
+      <p>Maps gives you quick access
+         to maps all across the world.</p>
+      <p xml:lang="de">Karten.</p>
+    </description>
+    <project_license>GPL-2.0+</project_license>
+    <developer_name>The GNOME Project</developer_name>
+    <url type="homepage">https://apps.gnome.org/Maps</url>
+    <icon type="remote">https://example.org/maps.png</icon>
+    <icon type="stock">org.gnome.Maps</icon>
+    <categories><category>Utility</category><category>Maps</category></categories>
+    <keywords><keyword>navigation</keyword></keywords>
+    <kudos><kudo>HiDpiIcon</kudo><kudo>GnomeSoftware::popular</kudo></kudos>
+    <screenshots>
+      <screenshot><image type="source">maps/extra.png</image></screenshot>
+      <screenshot type="default"><image type="thumbnail">maps/main-small.png</image><image type="source">maps/main.png</image></screenshot>
+    </screenshots>
+    <releases><release version="46.1" timestamp="1712000000"/></releases>
+  </component>
+  <component type="desktop-application">
+    <id>org.gnome.Weather</id>
+    <name>Weather</name>
+    <custom><value key="GnomeSoftware::popular">true</value></custom>
+  </component>
+  <component type="desktop-application">
+    <id>org.gnome.Clocks</id>
+    <name>Clocks</name>
+    <kudos><kudo>HiDpiIcon</kudo></kudos>
+  </component>
+  <component type="addon">
+    <id>org.gnome.Maps.Plugin</id>
+    <name>Maps plugin</name>
+  </component>
+  <component type="desktop-application" merge="append">
+    <id>org.gnome.Maps</id>
+    <name>Merged</name>
+  </component>
+</components>
+"#;
+
+    const DEP11: &str = "---
+File: DEP-11
+Version: '0.12'
+Origin: bookworm-main
+MediaBaseUrl: https://appstream.debian.org/media/pool
+---
+Type: desktop-application
+ID: org.kde.kate
+Name:
+  C: Kate
+  de: Kate-Editor
+Summary:
+  C: Advanced text editor
+Description:
+  C: <p>Kate is a <em>multi-document</em> editor.</p><ul><li>Fast &amp; small</li></ul>
+ProjectLicense: LGPL-2.0-only
+Categories:
+  - Utility
+  - TextEditor
+Keywords:
+  C:
+    - text
+Kudos:
+  - GnomeSoftware::popular
+Icon:
+  cached:
+    - name: kate_kate.png
+Screenshots:
+  - thumbnails:
+      - url: kate/thumb.png
+  - default: true
+    source-image:
+      url: https://example.org/kate.png
+Releases:
+  - version: 23.04
+---
+Type: font
+ID: org.example.Font
+Name:
+  C: Font
+";
+
+    fn ids(components: &[Component]) -> Vec<&str> {
+        components.iter().map(|component| component.id.as_str()).collect()
+    }
+
+    fn gzip(text: &str) -> Vec<u8> {
+        let compressor = gio::ZlibCompressor::new(gio::ZlibCompressorFormat::Gzip, -1);
+        let output = gio::MemoryOutputStream::new_resizable();
+        let converter = gio::ConverterOutputStream::new(&output, &compressor);
+        converter
+            .write_all(text.as_bytes(), gio::Cancellable::NONE)
+            .unwrap();
+        converter.close(gio::Cancellable::NONE).unwrap();
+        output.steal_as_bytes().to_vec()
+    }
+
+    fn run<T>(future: impl std::future::Future<Output = T>) -> T {
+        tokio::runtime::Runtime::new().unwrap().block_on(future)
+    }
+
+    #[test]
+    fn xml_collections_are_parsed() {
+        let components = parse_xml(XML, "fallback").unwrap();
+        assert_eq!(
+            ids(&components),
+            ["org.gnome.Maps", "org.gnome.Weather", "org.gnome.Clocks"]
+        );
+
+        let maps = &components[0];
+        assert_eq!(maps.origin, "flathub");
+        assert_eq!(maps.name.as_deref(), Some("Maps"));
+        assert_eq!(maps.summary.as_deref(), Some("Find places around the world"));
+        assert_eq!(
+            maps.description.as_deref(),
+            Some("Maps gives you quick access to maps all across the world.")
+        );
+        assert_eq!(maps.license.as_deref(), Some("GPL-2.0+"));
+        assert_eq!(maps.developer.as_deref(), Some("The GNOME Project"));
+        assert_eq!(maps.homepage.as_deref(), Some("https://apps.gnome.org/Maps"));
+        assert_eq!(maps.icon.as_deref(), Some("org.gnome.Maps"));
+        assert_eq!(maps.version.as_deref(), Some("46.1"));
+        assert_eq!(maps.categories, ["Utility", "Maps"]);
+        assert_eq!(maps.keywords, ["navigation"]);
+        assert_eq!(maps.kudos, ["HiDpiIcon", "GnomeSoftware::popular"]);
+        assert_eq!(
+            maps.screenshots,
+            [
+                "https://dl.flathub.org/media/maps/main.png",
+                "https://dl.flathub.org/media/maps/extra.png",
+            ]
+        );
+
+        // A lone metainfo file takes its origin from the caller.
+        let metainfo = "<component><id>org.example.App</id><name>App</name></component>";
+        let components = parse_xml(metainfo, "local").unwrap();
+        assert_eq!(ids(&components), ["org.example.App"]);
+        assert_eq!(components[0].origin, "local");
+        assert!(parse_xml("<releases/>", "local").is_err());
+    }
+
+    #[test]
+    fn dep11_collections_are_parsed() {
+        let components = parse_dep11(DEP11, "fallback").unwrap();
+        assert_eq!(ids(&components), ["org.kde.kate"]);
+
+        let kate = &components[0];
+        assert_eq!(kate.origin, "bookworm-main");
+        assert_eq!(kate.name.as_deref(), Some("Kate"));
+        assert_eq!(kate.summary.as_deref(), Some("Advanced text editor"));
+        assert_eq!(
+            kate.description.as_deref(),
+            Some("Kate is a multi-document editor.\n\nFast & small")
+        );
+        assert_eq!(kate.license.as_deref(), Some("LGPL-2.0-only"));
+        assert_eq!(kate.icon.as_deref(), Some("kate_kate.png"));
+        assert_eq!(kate.version.as_deref(), Some("23.04"));
+        assert_eq!(kate.categories, ["Utility", "TextEditor"]);
+        assert_eq!(kate.keywords, ["text"]);
+        assert_eq!(kate.kudos, ["GnomeSoftware::popular"]);
+        assert_eq!(
+            kate.screenshots,
+            [
+                "https://example.org/kate.png",
+                "https://appstream.debian.org/media/pool/kate/thumb.png",
+            ]
+        );
+
+        assert!(parse_dep11("---\nFile: something-else\n", "local").is_err());
+    }
+
+    #[test]
+    fn gzipped_catalogues_are_inflated() {
+        let dir = tempfile::tempdir().unwrap();
+        let xml = dir.path().join("flathub.xml.gz");
+        std::fs::write(&xml, gzip(XML)).unwrap();
+        let yaml = dir.path().join("bookworm_Components-amd64.yml.gz");
+        std::fs::write(&yaml, gzip(DEP11)).unwrap();
+
+        let components = read_catalog_file(&xml).unwrap();
+        assert_eq!(
+            ids(&components),
+            ["org.gnome.Maps", "org.gnome.Weather", "org.gnome.Clocks"]
+        );
+        let components = read_catalog_file(&yaml).unwrap();
+        assert_eq!(ids(&components), ["org.kde.kate"]);
+
+        let broken = dir.path().join("broken.xml.gz");
+        std::fs::write(&broken, XML).unwrap();
+        assert!(read_catalog_file(&broken).is_err());
+    }
+
+    #[test]
+    fn popular_tags_come_from_kudos_and_custom_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("flathub.xml"), XML).unwrap();
+        let popular = |plugin: AppStreamPlugin| {
+            run(plugin.list_popular_apps(&CancellationHandle::new()))
+                .unwrap()
+                .into_iter()
+                .map(|app| app.app_id)
+                .collect::<Vec<_>>()
+        };
+
+        let plugin = AppStreamPlugin::new("native::appstream").with_dir(dir.path());
+        assert_eq!(popular(plugin), ["org.gnome.Maps", "org.gnome.Weather"]);
+
+        let plugin = AppStreamPlugin::new("native::appstream")
+            .with_dir(dir.path())
+            .with_popular_tags(["HiDpiIcon"]);
+        assert_eq!(popular(plugin), ["org.gnome.Maps", "org.gnome.Clocks"]);
+
+        // Without tagged components, the most-awarded come first.
+        let plugin = AppStreamPlugin::new("native::appstream")
+            .with_dir(dir.path())
+            .with_popular_tags(["Unused"]);
+        assert_eq!(
+            popular(plugin),
+            ["org.gnome.Maps", "org.gnome.Clocks", "org.gnome.Weather"]
+        );
+    }
+
+    #[test]
+    fn only_changed_files_are_parsed_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let stable = dir.path().join("a-stable.xml");
+        let changing = dir.path().join("b-changing.xml");
+        std::fs::write(&stable, XML).unwrap();
+        std::fs::write(
+            &changing,
+            "<component><id>org.example.Old</id><name>Old</name></component>",
+        )
+        .unwrap();
+        let dirs = [dir.path().to_path_buf()];
+        let tags = HashSet::new();
+
+        let first = Catalog::load(scan_dirs(&dirs), None, &tags).unwrap();
+        std::fs::write(
+            &changing,
+            "<component><id>org.example.Renamed</id><name>Renamed app</name></component>",
+        )
+        .unwrap();
+        let second = Catalog::load(scan_dirs(&dirs), Some(&first), &tags).unwrap();
+
+        let parsed = |catalog: &Catalog, path: &Path| {
+            catalog
+                .parsed
+                .iter()
+                .find(|(file, _)| file.path == path)
+                .map(|(_, components)| components.clone())
+                .unwrap()
+        };
+        assert!(Arc::ptr_eq(&parsed(&first, &stable), &parsed(&second, &stable)));
+        assert!(!Arc::ptr_eq(&parsed(&first, &changing), &parsed(&second, &changing)));
+        let app_ids = second
+            .entries
+            .iter()
+            .map(|entry| entry.details.app.app_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            app_ids,
+            ["org.gnome.Maps", "org.gnome.Weather", "org.gnome.Clocks", "org.example.Renamed"]
+        );
+    }
+}
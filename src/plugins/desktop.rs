@@ -0,0 +1,440 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+
+use crate::cancellation::CancellationHandle;
+
+use super::{
+    failed_job, AppDetails, AppSummary, InstallState, JobStream, PluginBackend, PluginDescriptor,
+    PluginExecutionError, PluginKind,
+};
+
+const DESKTOP_GROUP: &str = "Desktop Entry";
+const DESKTOP_SUFFIX: &str = ".desktop";
+/// Source reported for every entry; the backend cannot install or remove, so
+/// it only serves to route details requests back here.
+const SOURCE: &str = "desktop::local";
+/// Vendor subdirectories such as `applications/kde4/` are part of the
+/// desktop file id; nothing real nests deeper than this.
+const MAX_SCAN_DEPTH: usize = 3;
+
+/// Lists the applications installed on this system.
+///
+/// Directories are rescanned on every call; parsing a few hundred key files
+/// is cheap next to the backends this one is merged with.
+pub struct DesktopPlugin {
+    descriptor: PluginDescriptor,
+    dirs: Vec<PathBuf>,
+    desktops: Vec<String>,
+}
+
+impl DesktopPlugin {
+    /// Scans nothing until directories are added; see
+    /// [`DesktopPlugin::default_dirs`]. The current desktops are read from
+    /// `$XDG_CURRENT_DESKTOP`.
+    pub fn new(id: impl Into<String>) -> Self {
+        let desktops = std::env::var("XDG_CURRENT_DESKTOP")
+            .map(|value| parse_list(&value, ':'))
+            .unwrap_or_default();
+
+        Self {
+            descriptor: PluginDescriptor {
+                id: id.into(),
+                kind: PluginKind::Native,
+            },
+            dirs: Vec::new(),
+            desktops,
+        }
+    }
+
+    /// `applications/` below the user data dir and every system data dir, in
+    /// lookup order.
+    pub fn default_dirs() -> Vec<PathBuf> {
+        std::iter::once(glib::user_data_dir())
+            .chain(glib::system_data_dirs())
+            .map(|dir| dir.join("applications"))
+            .collect()
+    }
+
+    /// Adds `dir` after the directories added so far; entries found there are
+    /// shadowed by earlier directories.
+    pub fn with_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dirs.push(dir.into());
+        self
+    }
+
+    pub fn with_dirs<I, P>(mut self, dirs: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.dirs.extend(dirs.into_iter().map(Into::into));
+        self
+    }
+
+    /// Desktop environment names matched against `OnlyShowIn` and
+    /// `NotShowIn`, replacing `$XDG_CURRENT_DESKTOP`.
+    pub fn with_desktops<I, S>(mut self, desktops: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.desktops = desktops.into_iter().map(Into::into).collect();
+        self
+    }
+
+    async fn entries(&self, cancel: &CancellationHandle) -> Result<Vec<DesktopEntry>, PluginExecutionError> {
+        let dirs = self.dirs.clone();
+        let desktops = self.desktops.clone();
+        let scan = tokio::task::spawn_blocking(move || scan_dirs(&dirs, &desktops));
+
+        tokio::select! {
+            joined = scan => joined.map_err(|err| {
+                PluginExecutionError::Operation(format!("desktop entry scan join error: {err}"))
+            }),
+            _ = cancel.cancelled() => Err(PluginExecutionError::Cancelled),
+        }
+    }
+}
+
+#[async_trait]
+impl PluginBackend for DesktopPlugin {
+    fn descriptor(&self) -> &PluginDescriptor {
+        &self.descriptor
+    }
+
+    /// Installed apps are not a catalogue; they are reported through
+    /// [`PluginBackend::list_installed_apps`] and marked on other backends'
+    /// results instead.
+    async fn list_popular_apps(
+        &self,
+        _cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        Ok(Vec::new())
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        let terms = query
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        Ok(self
+            .entries(cancel)
+            .await?
+            .into_iter()
+            .filter(|entry| {
+                terms
+                    .iter()
+                    .any(|term| entry.haystack.contains(term.as_str()))
+            })
+            .map(|entry| entry.details.app)
+            .collect())
+    }
+
+    async fn list_installed_apps(
+        &self,
+        cancel: &CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        Ok(self
+            .entries(cancel)
+            .await?
+            .into_iter()
+            .map(|entry| entry.details.app)
+            .collect())
+    }
+
+    async fn app_details(
+        &self,
+        app_id: &str,
+        cancel: &CancellationHandle,
+    ) -> Result<AppDetails, PluginExecutionError> {
+        self.entries(cancel)
+            .await?
+            .into_iter()
+            .find(|entry| entry.details.app.app_id == app_id)
+            .map(|entry| entry.details)
+            .ok_or_else(|| PluginExecutionError::Operation(format!("unknown app {app_id}")))
+    }
+
+    fn install(&self, _app_id: &str, _cancel: CancellationHandle) -> JobStream {
+        failed_job(read_only())
+    }
+
+    fn remove(&self, _app_id: &str, _cancel: CancellationHandle) -> JobStream {
+        failed_job(read_only())
+    }
+}
+
+fn read_only() -> PluginExecutionError {
+    PluginExecutionError::Operation(
+        "desktop entries only describe installed apps; use the backend that installed them"
+            .to_string(),
+    )
+}
+
+struct DesktopEntry {
+    details: AppDetails,
+    /// Lower-cased id, name, generic name, comment and keywords for search.
+    haystack: String,
+}
+
+/// Visible entries from `dirs`, first directory winning for each desktop
+/// file id. Hidden and undisplayed entries still shadow later directories.
+fn scan_dirs(dirs: &[PathBuf], desktops: &[String]) -> Vec<DesktopEntry> {
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
+
+    for dir in dirs {
+        let mut files = Vec::new();
+        collect_files(dir, "", 0, &mut files);
+        files.sort();
+
+        for (desktop_id, path) in files {
+            if !seen.insert(desktop_id.clone()) {
+                continue;
+            }
+            match read_entry(&path, &desktop_id, desktops) {
+                Ok(Some(entry)) => entries.push(entry),
+                Ok(None) => {}
+                Err(err) => {
+                    eprintln!("InstallGrid: skipping desktop entry {}: {err}", path.display());
+                }
+            }
+        }
+    }
+
+    entries
+}
+
+/// Collects `(desktop file id, path)` pairs. Per the menu spec, the id of
+/// `applications/kde4/foo.desktop` is `kde4-foo.desktop`.
+fn collect_files(dir: &Path, prefix: &str, depth: usize, files: &mut Vec<(String, PathBuf)>) {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if path.is_dir() {
+            if depth < MAX_SCAN_DEPTH {
+                collect_files(&path, &format!("{prefix}{name}-"), depth + 1, files);
+            }
+        } else if name.ends_with(DESKTOP_SUFFIX) {
+            files.push((format!("{prefix}{name}"), path));
+        }
+    }
+}
+
+/// Parses one desktop entry; `Ok(None)` for entries a launcher would not show.
+fn read_entry(
+    path: &Path,
+    desktop_id: &str,
+    desktops: &[String],
+) -> Result<Option<DesktopEntry>, glib::Error> {
+    let key_file = glib::KeyFile::new();
+    key_file.load_from_file(path, glib::KeyFileFlags::NONE)?;
+
+    let string = |key: &str| key_file.string(DESKTOP_GROUP, key).ok().map(String::from);
+    let localized = |key: &str| {
+        key_file
+            .locale_string(DESKTOP_GROUP, key, None)
+            .ok()
+            .map(String::from)
+    };
+    let flag = |key: &str| key_file.boolean(DESKTOP_GROUP, key).unwrap_or(false);
+    let list = |key: &str| string(key).map(|value| parse_list(&value, ';')).unwrap_or_default();
+
+    if string("Type").as_deref() != Some("Application") || flag("Hidden") || flag("NoDisplay") {
+        return Ok(None);
+    }
+    let only_show_in = list("OnlyShowIn");
+    if !only_show_in.is_empty() && !desktops.iter().any(|desktop| only_show_in.contains(desktop)) {
+        return Ok(None);
+    }
+    let not_show_in = list("NotShowIn");
+    if desktops.iter().any(|desktop| not_show_in.contains(desktop)) {
+        return Ok(None);
+    }
+    let Some(name) = localized("Name") else {
+        return Ok(None);
+    };
+
+    let app_id = desktop_id
+        .strip_suffix(DESKTOP_SUFFIX)
+        .unwrap_or(desktop_id)
+        .to_string();
+    let summary = localized("Comment")
+        .or_else(|| localized("GenericName"))
+        .unwrap_or_default();
+    let keywords = localized("Keywords")
+        .map(|value| parse_list(&value, ';'))
+        .unwrap_or_default();
+    let haystack = format!(
+        "{} {} {} {} {}",
+        app_id,
+        name,
+        localized("GenericName").unwrap_or_default(),
+        summary,
+        keywords.join(" ")
+    )
+    .to_lowercase();
+
+    let mut details = AppDetails::from_summary(AppSummary {
+        app_id,
+        name,
+        summary,
+        source: SOURCE.to_string(),
+        installed: true,
+    });
+    details.icon = string("Icon");
+    details.categories = list("Categories");
+    details.install_state = InstallState::Installed;
+
+    Ok(Some(DesktopEntry { details, haystack }))
+}
+
+/// Splits a `;`- or `:`-separated list, dropping empty items.
+fn parse_list(value: &str, separator: char) -> Vec<String> {
+    value
+        .split(separator)
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_entry(dir: &Path, file: &str, extra: &str) {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let name = file.trim_end_matches(DESKTOP_SUFFIX);
+        std::fs::write(
+            path,
+            format!("[Desktop Entry]\nType=Application\nName={name}\nExec={name}\n{extra}"),
+        )
+        .unwrap();
+    }
+
+    fn scanned(dirs: &[PathBuf], desktops: &[&str]) -> Vec<(String, String)> {
+        let desktops = desktops.iter().map(|desktop| desktop.to_string()).collect::<Vec<_>>();
+        scan_dirs(dirs, &desktops)
+            .into_iter()
+            .map(|entry| (entry.details.app.app_id, entry.details.app.summary))
+            .collect()
+    }
+
+    fn ids(entries: &[(String, String)]) -> Vec<&str> {
+        entries.iter().map(|(app_id, _)| app_id.as_str()).collect()
+    }
+
+    #[test]
+    fn hidden_and_undisplayed_entries_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        write_entry(dir.path(), "visible.desktop", "");
+        write_entry(dir.path(), "hidden.desktop", "Hidden=true\n");
+        write_entry(dir.path(), "nodisplay.desktop", "NoDisplay=true\n");
+        write_entry(dir.path(), "shown.desktop", "NoDisplay=false\n");
+
+        let entries = scanned(&[dir.path().to_path_buf()], &[]);
+        assert_eq!(ids(&entries), ["shown", "visible"]);
+    }
+
+    #[test]
+    fn show_in_lists_are_matched_against_the_desktops() {
+        let dir = tempfile::tempdir().unwrap();
+        write_entry(dir.path(), "anywhere.desktop", "");
+        write_entry(dir.path(), "gnome-only.desktop", "OnlyShowIn=GNOME;\n");
+        write_entry(dir.path(), "not-kde.desktop", "NotShowIn=KDE;\n");
+        let dirs = [dir.path().to_path_buf()];
+
+        assert_eq!(
+            ids(&scanned(&dirs, &["GNOME"])),
+            ["anywhere", "gnome-only", "not-kde"]
+        );
+        assert_eq!(ids(&scanned(&dirs, &["KDE"])), ["anywhere"]);
+        // Without a known desktop, `OnlyShowIn` never matches.
+        assert_eq!(ids(&scanned(&dirs, &[])), ["anywhere", "not-kde"]);
+
+        let plugin = DesktopPlugin::new("native::desktop")
+            .with_dir(dir.path())
+            .with_desktops(["KDE"]);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let installed = runtime
+            .block_on(plugin.list_installed_apps(&CancellationHandle::new()))
+            .unwrap();
+        let installed = installed
+            .iter()
+            .map(|app| app.app_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(installed, ["anywhere"]);
+    }
+
+    #[test]
+    fn earlier_dirs_shadow_later_ones() {
+        let user = tempfile::tempdir().unwrap();
+        let system = tempfile::tempdir().unwrap();
+        write_entry(user.path(), "editor.desktop", "Comment=user copy\n");
+        write_entry(system.path(), "editor.desktop", "Comment=system copy\n");
+        write_entry(user.path(), "removed.desktop", "Hidden=true\n");
+        write_entry(system.path(), "removed.desktop", "");
+        write_entry(system.path(), "system-only.desktop", "");
+
+        let entries = scanned(&[user.path().to_path_buf(), system.path().to_path_buf()], &[]);
+        assert_eq!(
+            entries,
+            [
+                ("editor".to_string(), "user copy".to_string()),
+                ("system-only".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn vendor_subdirectories_are_part_of_the_id() {
+        let dir = tempfile::tempdir().unwrap();
+        write_entry(dir.path(), "kde4/viewer.desktop", "");
+
+        let entries = scanned(&[dir.path().to_path_buf()], &[]);
+        assert_eq!(ids(&entries), ["kde4-viewer"]);
+    }
+
+    #[test]
+    fn only_applications_with_a_name_are_listed() {
+        let dir = tempfile::tempdir().unwrap();
+        write_entry(dir.path(), "app.desktop", "");
+        std::fs::write(
+            dir.path().join("link.desktop"),
+            "[Desktop Entry]\nType=Link\nName=Link\nURL=https://example.org\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("folder.desktop"),
+            "[Desktop Entry]\nType=Directory\nName=Folder\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("nameless.desktop"),
+            "[Desktop Entry]\nType=Application\nExec=nameless\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("broken.desktop"), "not a key file").unwrap();
+
+        let entries = scanned(&[dir.path().to_path_buf()], &[]);
+        assert_eq!(ids(&entries), ["app"]);
+    }
+}
@@ -1,32 +1,61 @@
+use std::collections::HashMap;
 use std::env;
 use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_uint};
+use std::path::{Path, PathBuf};
 use std::ptr::{self, NonNull};
 use std::sync::Arc;
+use std::time::Duration;
 
 use gio::ffi::{g_bus_get_sync, G_BUS_TYPE_SYSTEM};
 use glib::ffi::g_error_free;
-use glib::gobject_ffi::{g_object_unref, GObject};
+use glib::gobject_ffi::{g_object_ref, g_object_unref, GObject};
+use glib::translate::{FromGlibPtrFull, ToGlibPtr};
+use gio::prelude::CancellableExt;
 use parking_lot::Mutex;
 use tokio::task;
 
+use crate::cancellation::CancellationHandle;
 use crate::ffi;
 
-use super::{AppSummary, PluginExecutionError};
+use super::{
+    job_stream, AppDetails, AppSummary, AppUpdate, Category, InstallState, JobKind, JobProgress,
+    JobReporter, JobStream, PluginExecutionError, Repository, RepositoryAction,
+};
 
 const DEFAULT_LIST_LIMIT: u32 = 0; // 0 means "no limit" in gs_app_query
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_millis(250);
+const DETAILS_ICON_SIZE: c_uint = 128;
 
 pub struct FlatpakLoader {
     loader: NonNull<ffi::GsPluginLoader>,
+    /// Serialises the jobs that rewrite plugin configuration: metadata
+    /// refreshes and repository changes. Listing, refining and app jobs run
+    /// concurrently, as they do in GNOME Software. Functions that need the
+    /// lock take it themselves.
     lock: Mutex<()>,
     plugin_name: String,
+    /// Metadata older than this is re-downloaded before listing apps.
+    metadata_max_age: Duration,
+    /// Apps seen in list/search results since the last full listing, keyed
+    /// by id, so jobs can be run on the exact `GsApp` the plugin produced.
+    known_apps: Mutex<HashMap<String, AppRef>>,
+    /// Apps with a pending update from the last update check, kept apart so
+    /// a full listing does not forget them.
+    known_updates: Mutex<HashMap<String, AppRef>>,
+    /// Repository `GsApp`s from the last listing, keyed by id, for
+    /// `manage_repository` jobs.
+    known_repositories: Mutex<HashMap<String, AppRef>>,
+    /// Top-level `GsCategory`s from the last listing, keyed by id, for
+    /// category queries.
+    known_categories: Mutex<HashMap<String, CategoryRef>>,
 }
 
 unsafe impl Send for FlatpakLoader {}
 unsafe impl Sync for FlatpakLoader {}
 
 impl FlatpakLoader {
-    pub fn new(plugin_name: &str) -> Result<Self, PluginExecutionError> {
+    pub fn new(plugin_name: &str, metadata_max_age: Duration) -> Result<Self, PluginExecutionError> {
         Self::check_environment()?;
 
         let raw_loader =
@@ -49,9 +78,14 @@ impl FlatpakLoader {
             loader,
             lock: Mutex::new(()),
             plugin_name: plugin_name.to_string(),
+            metadata_max_age,
+            known_apps: Mutex::new(HashMap::new()),
+            known_updates: Mutex::new(HashMap::new()),
+            known_repositories: Mutex::new(HashMap::new()),
+            known_categories: Mutex::new(HashMap::new()),
         };
 
-        instance.refresh_metadata_blocking()?;
+        instance.refresh_metadata_blocking(None)?;
 
         Ok(instance)
     }
@@ -65,7 +99,7 @@ impl FlatpakLoader {
                 if !error.is_null() {
                     g_error_free(error);
                 }
-                return Err(PluginExecutionError::Operation(format!(
+                return Err(PluginExecutionError::BackendUnavailable(format!(
                     "Unable to connect to the system D-Bus (required by gnome-software Flatpak plugin): {}\n\
 Confirm that you are running inside a desktop session or export DBUS_SYSTEM_BUS_ADDRESS=unix:path=/run/dbus/system_bus_socket.",
                     message
@@ -79,14 +113,207 @@ Confirm that you are running inside a desktop session or export DBUS_SYSTEM_BUS_
 
     pub async fn list_all_async(
         self: Arc<Self>,
+        cancel: CancellationHandle,
     ) -> Result<Vec<AppSummary>, PluginExecutionError> {
-        task::spawn_blocking(move || self.list_all_blocking(DEFAULT_LIST_LIMIT))
+        self.run_blocking(cancel, |loader, cancellable| {
+            // Plugins skip the download while their metadata is younger than
+            // the max age; a failed download still lists what is on disk.
+            if let Err(err) = loader.refresh_metadata_blocking(Some(cancellable)) {
+                eprintln!(
+                    "InstallGrid: {} metadata refresh failed, listing cached metadata: {err}",
+                    loader.plugin_name
+                );
+            }
+            loader.list_all_blocking(DEFAULT_LIST_LIMIT, cancellable)
+        })
+        .await
+    }
+
+    pub async fn search_async(
+        self: Arc<Self>,
+        keywords: Vec<String>,
+        cancel: CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        self.run_blocking(cancel, move |loader, cancellable| {
+            loader.search_blocking(&keywords, DEFAULT_LIST_LIMIT, cancellable)
+        })
+        .await
+    }
+
+    pub async fn list_categories_async(
+        self: Arc<Self>,
+        cancel: CancellationHandle,
+    ) -> Result<Vec<Category>, PluginExecutionError> {
+        self.run_blocking(cancel, |loader, cancellable| {
+            loader.list_categories_blocking(cancellable)
+        })
+        .await
+    }
+
+    pub async fn list_category_apps_async(
+        self: Arc<Self>,
+        category_id: String,
+        cancel: CancellationHandle,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        self.run_blocking(cancel, move |loader, cancellable| {
+            loader.list_category_apps_blocking(&category_id, cancellable)
+        })
+        .await
+    }
+
+    pub async fn list_updates_async(
+        self: Arc<Self>,
+        cancel: CancellationHandle,
+    ) -> Result<Vec<AppUpdate>, PluginExecutionError> {
+        self.run_blocking(cancel, |loader, cancellable| {
+            loader.list_updates_blocking(cancellable)
+        })
+        .await
+    }
+
+    pub async fn list_repositories_async(
+        self: Arc<Self>,
+        cancel: CancellationHandle,
+    ) -> Result<Vec<Repository>, PluginExecutionError> {
+        self.run_blocking(cancel, |loader, cancellable| {
+            loader.list_repositories_blocking(cancellable)
+        })
+        .await
+    }
+
+    pub async fn add_repository_async(
+        self: Arc<Self>,
+        file: PathBuf,
+        cancel: CancellationHandle,
+    ) -> Result<Repository, PluginExecutionError> {
+        self.run_blocking(cancel, move |loader, cancellable| {
+            loader.add_repository_blocking(&file, cancellable)
+        })
+        .await
+    }
+
+    pub async fn manage_repository_async(
+        self: Arc<Self>,
+        repository_id: String,
+        action: RepositoryAction,
+        cancel: CancellationHandle,
+    ) -> Result<(), PluginExecutionError> {
+        let repository = self
+            .known_repositories
+            .lock()
+            .get(&repository_id)
+            .cloned()
+            .ok_or_else(|| {
+                PluginExecutionError::Operation(format!(
+                    "{repository_id} was not listed by {} yet; refresh first",
+                    self.plugin_name
+                ))
+            })?;
+        self.run_blocking(cancel, move |loader, cancellable| {
+            loader.manage_repository_blocking(&repository, action, cancellable)?;
+            if action == RepositoryAction::Remove {
+                loader.known_repositories.lock().remove(&repository_id);
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Refines a previously listed app with everything [`AppDetails`] needs.
+    pub async fn app_details_async(
+        self: Arc<Self>,
+        app_id: String,
+        cancel: CancellationHandle,
+    ) -> Result<AppDetails, PluginExecutionError> {
+        let app = self.known_app(&app_id)?;
+        self.run_blocking(cancel, move |loader, cancellable| {
+            loader.app_details_blocking(&app, cancellable)
+        })
+        .await
+    }
+
+    /// Installs, removes or updates a previously listed app, polling the `GsApp`
+    /// progress while the job runs.
+    pub async fn app_job_async(
+        self: Arc<Self>,
+        app_id: String,
+        kind: JobKind,
+        cancel: CancellationHandle,
+        reporter: JobReporter,
+    ) -> Result<(), PluginExecutionError> {
+        let app = self.known_app(&app_id)?;
+
+        reporter.report(JobProgress::Queued).await;
+
+        let poll = {
+            let app = app.clone();
+            let reporter = reporter.clone();
+            tokio::spawn(async move {
+                let mut last = None;
+                loop {
+                    let raw = unsafe { ffi::gs_app_get_progress(app.as_ptr()) };
+                    let percent = (raw != ffi::GS_APP_PROGRESS_UNKNOWN).then(|| raw.min(100) as u8);
+                    if last != Some(percent) {
+                        reporter.report(JobProgress::Running { percent }).await;
+                        last = Some(percent);
+                    }
+                    tokio::time::sleep(PROGRESS_POLL_INTERVAL).await;
+                }
+            })
+        };
+
+        let result = self
+            .run_blocking(cancel, move |loader, cancellable| {
+                loader.app_job_blocking(&app, kind, cancellable)
+            })
+            .await;
+        poll.abort();
+
+        result?;
+        reporter.report(JobProgress::Finished).await;
+        Ok(())
+    }
+
+    fn known_app(&self, app_id: &str) -> Result<AppRef, PluginExecutionError> {
+        let known = self.known_apps.lock().get(app_id).cloned();
+        known.or_else(|| self.known_updates.lock().get(app_id).cloned()).ok_or_else(|| {
+            PluginExecutionError::Operation(format!(
+                "{app_id} was not returned by {} yet; refresh first",
+                self.plugin_name
+            ))
+        })
+    }
+
+    /// Runs `work` on the blocking pool with a `GCancellable` that fires when
+    /// `cancel` does.
+    async fn run_blocking<T, F>(
+        self: Arc<Self>,
+        cancel: CancellationHandle,
+        work: F,
+    ) -> Result<T, PluginExecutionError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Self, &gio::Cancellable) -> Result<T, PluginExecutionError> + Send + 'static,
+    {
+        let cancellable = gio::Cancellable::new();
+        let forward = {
+            let cancellable = cancellable.clone();
+            tokio::spawn(async move {
+                cancel.cancelled().await;
+                cancellable.cancel();
+            })
+        };
+
+        let result = task::spawn_blocking(move || work(&self, &cancellable))
             .await
             .map_err(|err| {
                 PluginExecutionError::Operation(format!(
                     "legacy Flatpak worker join error: {err}"
                 ))
-            })?
+            });
+
+        forward.abort();
+        result?
     }
 
     fn initialise_loader(loader: NonNull<ffi::GsPluginLoader>) -> Result<(), PluginExecutionError> {
@@ -124,7 +351,7 @@ Confirm that you are running inside a desktop session or export DBUS_SYSTEM_BUS_
                     (Vec::new(), None)
                 } else {
                     let storage = to_c_string_array(&entries)
-                        .map_err(|msg| PluginExecutionError::Operation(msg))?;
+                        .map_err(PluginExecutionError::Operation)?;
                     let mut ptrs = storage.iter().map(|value| value.as_ptr()).collect::<Vec<_>>();
                     ptrs.push(ptr::null());
                     (storage, Some(ptrs))
@@ -145,7 +372,7 @@ Confirm that you are running inside a desktop session or export DBUS_SYSTEM_BUS_
                     (Vec::new(), None)
                 } else {
                     let storage = to_c_string_array(&entries)
-                        .map_err(|msg| PluginExecutionError::Operation(msg))?;
+                        .map_err(PluginExecutionError::Operation)?;
                     let mut ptrs = storage.iter().map(|value| value.as_ptr()).collect::<Vec<_>>();
                     ptrs.push(ptr::null());
                     (storage, Some(ptrs))
@@ -192,42 +419,422 @@ Confirm that you are running inside a desktop session or export DBUS_SYSTEM_BUS_
         Ok(())
     }
 
-    fn list_all_blocking(&self, max_results: u32) -> Result<Vec<AppSummary>, PluginExecutionError> {
-        let _guard = self.lock.lock();
+    fn list_all_blocking(
+        &self,
+        max_results: u32,
+        cancellable: &gio::Cancellable,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        if cancellable.is_cancelled() {
+            return Err(PluginExecutionError::Cancelled);
+        }
 
         let query = self.create_list_query(max_results)?;
-        let _query_guard = GObjectGuard(query.as_ptr() as *mut GObject);
+        self.list_apps_blocking(query, true, cancellable)
+    }
+
+    fn search_blocking(
+        &self,
+        keywords: &[String],
+        max_results: u32,
+        cancellable: &gio::Cancellable,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        if cancellable.is_cancelled() {
+            return Err(PluginExecutionError::Cancelled);
+        }
+
+        let query = self.create_search_query(keywords, max_results)?;
+        self.list_apps_blocking(query, false, cancellable)
+    }
+
+    /// Lists the top-level categories and remembers them for
+    /// `list_category_apps_blocking`.
+    fn list_categories_blocking(
+        &self,
+        cancellable: &gio::Cancellable,
+    ) -> Result<Vec<Category>, PluginExecutionError> {
+        if cancellable.is_cancelled() {
+            return Err(PluginExecutionError::Cancelled);
+        }
 
         let job_ptr = unsafe {
-            ffi::gs_plugin_job_list_apps_new(
-                query.as_ptr(),
-                ffi::GS_PLUGIN_LIST_APPS_FLAGS_INTERACTIVE,
+            ffi::gs_plugin_job_list_categories_new(ffi::GS_PLUGIN_LIST_CATEGORIES_FLAGS_NONE)
+        };
+        let job = NonNull::new(job_ptr).ok_or_else(|| {
+            PluginExecutionError::Operation(
+                "gs_plugin_job_list_categories_new returned null".to_string(),
             )
+        })?;
+        let _job_guard = GObjectGuard(job.as_ptr() as *mut GObject);
+
+        self.process_job(job, cancellable)?;
+
+        let array = unsafe { ffi::gs_plugin_job_list_categories_get_result_list(job.as_ptr()) };
+        if array.is_null() {
+            return Ok(Vec::new());
+        }
+
+        let (pdata, length) = unsafe { ((*array).pdata, (*array).len) };
+        let mut categories = Vec::with_capacity(length as usize);
+        let mut known = self.known_categories.lock();
+        known.clear();
+        for index in 0..length as usize {
+            let category_ptr = unsafe { *pdata.add(index) } as *mut ffi::GsCategory;
+            let Some(category_ref) = CategoryRef::retain(category_ptr) else {
+                continue;
+            };
+
+            let category = unsafe { category_from_ptr(category_ptr) };
+            known.insert(category.id.clone(), category_ref);
+            categories.push(category);
+        }
+
+        Ok(categories)
+    }
+
+    /// Lists the apps in a category, using its "all" subcategory when it has
+    /// one as GNOME Software does. Categories are listed first when
+    /// `category_id` has not been seen yet.
+    fn list_category_apps_blocking(
+        &self,
+        category_id: &str,
+        cancellable: &gio::Cancellable,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        if cancellable.is_cancelled() {
+            return Err(PluginExecutionError::Cancelled);
+        }
+
+        let known = self.known_categories.lock().get(category_id).cloned();
+        let category = match known {
+            Some(category) => category,
+            None => {
+                self.list_categories_blocking(cancellable)?;
+                self.known_categories
+                    .lock()
+                    .get(category_id)
+                    .cloned()
+                    .ok_or_else(|| {
+                        PluginExecutionError::Operation(format!(
+                            "{} has no category {category_id}",
+                            self.plugin_name
+                        ))
+                    })?
+            }
+        };
+
+        let all = CString::new("all").unwrap();
+        let child = unsafe { ffi::gs_category_find_child(category.as_ptr(), all.as_ptr()) };
+        let target = if child.is_null() { category.as_ptr() } else { child };
+
+        let query = self.create_category_query(target)?;
+        self.list_apps_blocking(query, false, cancellable)
+    }
+
+    /// Lists installed apps with a pending update. They are remembered until
+    /// the next check so update jobs can run on them.
+    fn list_updates_blocking(
+        &self,
+        cancellable: &gio::Cancellable,
+    ) -> Result<Vec<AppUpdate>, PluginExecutionError> {
+        if cancellable.is_cancelled() {
+            return Err(PluginExecutionError::Cancelled);
+        }
+
+        let query = self.create_update_query()?;
+        let _query_guard = GObjectGuard(query.as_ptr() as *mut GObject);
+
+        let job_ptr = unsafe {
+            ffi::gs_plugin_job_list_apps_new(query.as_ptr(), ffi::GS_PLUGIN_LIST_APPS_FLAGS_NONE)
         };
         let job = NonNull::new(job_ptr).ok_or_else(|| {
             PluginExecutionError::Operation("gs_plugin_job_list_apps_new returned null".to_string())
         })?;
         let _job_guard = GObjectGuard(job.as_ptr() as *mut GObject);
 
-        let mut error: *mut ffi::GError = ptr::null_mut();
-        let ok = unsafe {
-            ffi::gs_plugin_loader_job_process(
-                self.loader.as_ptr(),
-                job.as_ptr(),
-                ptr::null_mut(),
-                &mut error,
+        self.process_job(job, cancellable)?;
+
+        let list_ptr = unsafe { ffi::gs_plugin_job_list_apps_get_result_list(job.as_ptr()) };
+        if list_ptr.is_null() {
+            return Ok(Vec::new());
+        }
+
+        let length = unsafe { ffi::gs_app_list_length(list_ptr) };
+        let mut updates = Vec::with_capacity(length as usize);
+        let mut refs = HashMap::with_capacity(length as usize);
+        for index in 0..length {
+            let app_ptr = unsafe { ffi::gs_app_list_index(list_ptr, index) };
+            if app_ptr.is_null() {
+                continue;
+            }
+
+            let update = unsafe { self.update_from_app(app_ptr) };
+            if let Some(app_ref) = AppRef::retain(app_ptr) {
+                refs.insert(update.app.app_id.clone(), app_ref);
+            }
+            updates.push(update);
+        }
+        *self.known_updates.lock() = refs;
+
+        Ok(updates)
+    }
+
+    /// Lists the plugin's sources: `GsApp`s of kind repository, installed
+    /// when enabled and available when disabled.
+    fn list_repositories_blocking(
+        &self,
+        cancellable: &gio::Cancellable,
+    ) -> Result<Vec<Repository>, PluginExecutionError> {
+        if cancellable.is_cancelled() {
+            return Err(PluginExecutionError::Cancelled);
+        }
+
+        let query = self.create_repository_query()?;
+        let _query_guard = GObjectGuard(query.as_ptr() as *mut GObject);
+
+        let job_ptr = unsafe {
+            ffi::gs_plugin_job_list_apps_new(query.as_ptr(), ffi::GS_PLUGIN_LIST_APPS_FLAGS_NONE)
+        };
+        let job = NonNull::new(job_ptr).ok_or_else(|| {
+            PluginExecutionError::Operation("gs_plugin_job_list_apps_new returned null".to_string())
+        })?;
+        let _job_guard = GObjectGuard(job.as_ptr() as *mut GObject);
+
+        self.process_job(job, cancellable)?;
+
+        let list_ptr = unsafe { ffi::gs_plugin_job_list_apps_get_result_list(job.as_ptr()) };
+        if list_ptr.is_null() {
+            return Ok(Vec::new());
+        }
+
+        let length = unsafe { ffi::gs_app_list_length(list_ptr) };
+        let mut repositories = Vec::with_capacity(length as usize);
+        for index in 0..length {
+            let app_ptr = unsafe { ffi::gs_app_list_index(list_ptr, index) };
+            if app_ptr.is_null() {
+                continue;
+            }
+
+            let repository = unsafe { self.repository_from_app(app_ptr) };
+            if let Some(app_ref) = AppRef::retain(app_ptr) {
+                self.known_repositories
+                    .lock()
+                    .insert(repository.id.clone(), app_ref);
+            }
+            repositories.push(repository);
+        }
+
+        Ok(repositories)
+    }
+
+    /// Turns a `.flatpakrepo` file into a repository `GsApp` with a
+    /// file-to-app job, then installs it.
+    fn add_repository_blocking(
+        &self,
+        file: &Path,
+        cancellable: &gio::Cancellable,
+    ) -> Result<Repository, PluginExecutionError> {
+        if cancellable.is_cancelled() {
+            return Err(PluginExecutionError::Cancelled);
+        }
+
+        let gfile = gio::File::for_path(file);
+        let job_ptr = unsafe {
+            ffi::gs_plugin_job_file_to_app_new(
+                gfile.to_glib_none().0,
+                ffi::GS_PLUGIN_FILE_TO_APP_FLAGS_INTERACTIVE,
+                ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_URL,
             )
         };
-        if ok == 0 {
-            let message = unsafe { gerror_to_message(error) };
-            unsafe {
-                if !error.is_null() {
-                    g_error_free(error);
-                }
+        let job = NonNull::new(job_ptr).ok_or_else(|| {
+            PluginExecutionError::Operation(
+                "gs_plugin_job_file_to_app_new returned null".to_string(),
+            )
+        })?;
+        let _job_guard = GObjectGuard(job.as_ptr() as *mut GObject);
+
+        self.process_job(job, cancellable)?;
+
+        let list_ptr = unsafe { ffi::gs_plugin_job_file_to_app_get_result_list(job.as_ptr()) };
+        let app_ptr = if list_ptr.is_null() || unsafe { ffi::gs_app_list_length(list_ptr) } == 0 {
+            ptr::null_mut()
+        } else {
+            unsafe { ffi::gs_app_list_index(list_ptr, 0) }
+        };
+        let app = AppRef::retain(app_ptr).ok_or_else(|| {
+            PluginExecutionError::Operation(format!(
+                "{} did not recognise {} as a repository file",
+                self.plugin_name,
+                file.display()
+            ))
+        })?;
+
+        self.manage_repository_blocking(&app, RepositoryAction::Enable, cancellable)?;
+
+        let mut repository = unsafe { self.repository_from_app(app.as_ptr()) };
+        // The state is only refined on the next listing.
+        repository.enabled = true;
+        self.known_repositories
+            .lock()
+            .insert(repository.id.clone(), app);
+        Ok(repository)
+    }
+
+    /// Runs a manage-repository job. Enabling also installs a repository
+    /// that is not configured yet.
+    fn manage_repository_blocking(
+        &self,
+        repository: &AppRef,
+        action: RepositoryAction,
+        cancellable: &gio::Cancellable,
+    ) -> Result<(), PluginExecutionError> {
+        let _guard = self.lock.lock();
+        if cancellable.is_cancelled() {
+            return Err(PluginExecutionError::Cancelled);
+        }
+
+        let action_flags = match action {
+            RepositoryAction::Enable => {
+                ffi::GS_PLUGIN_MANAGE_REPOSITORY_FLAGS_INSTALL
+                    | ffi::GS_PLUGIN_MANAGE_REPOSITORY_FLAGS_ENABLE
             }
-            return Err(PluginExecutionError::Operation(message));
+            RepositoryAction::Disable => ffi::GS_PLUGIN_MANAGE_REPOSITORY_FLAGS_DISABLE,
+            RepositoryAction::Remove => ffi::GS_PLUGIN_MANAGE_REPOSITORY_FLAGS_REMOVE,
+        };
+
+        let job_ptr = unsafe {
+            ffi::gs_plugin_job_manage_repository_new(
+                repository.as_ptr(),
+                action_flags | ffi::GS_PLUGIN_MANAGE_REPOSITORY_FLAGS_INTERACTIVE,
+            )
+        };
+        let job = NonNull::new(job_ptr).ok_or_else(|| {
+            PluginExecutionError::Operation(
+                "gs_plugin_job_manage_repository_new returned null".to_string(),
+            )
+        })?;
+        let _job_guard = GObjectGuard(job.as_ptr() as *mut GObject);
+
+        self.process_job(job, cancellable)
+    }
+
+    fn app_details_blocking(
+        &self,
+        app: &AppRef,
+        cancellable: &gio::Cancellable,
+    ) -> Result<AppDetails, PluginExecutionError> {
+        if cancellable.is_cancelled() {
+            return Err(PluginExecutionError::Cancelled);
         }
 
+        let refine_flags: c_uint = ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_DESCRIPTION
+            | ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_VERSION
+            | ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_LICENSE
+            | ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_DEVELOPER_NAME
+            | ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_URL
+            | ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_ICON
+            | ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_CATEGORIES
+            | ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_SIZE
+            | ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_CONTENT_RATING
+            | ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_SCREENSHOTS
+            | ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_ORIGIN;
+
+        let job_ptr = unsafe { ffi::gs_plugin_job_refine_new_for_app(app.as_ptr(), refine_flags) };
+        let job = NonNull::new(job_ptr).ok_or_else(|| {
+            PluginExecutionError::Operation(
+                "gs_plugin_job_refine_new_for_app returned null".to_string(),
+            )
+        })?;
+        let _job_guard = GObjectGuard(job.as_ptr() as *mut GObject);
+
+        self.process_job(job, cancellable)?;
+
+        // Refining updates the GsApp in place.
+        Ok(unsafe { self.details_from_app(app.as_ptr()) })
+    }
+
+    fn app_job_blocking(
+        &self,
+        app: &AppRef,
+        kind: JobKind,
+        cancellable: &gio::Cancellable,
+    ) -> Result<(), PluginExecutionError> {
+        if cancellable.is_cancelled() {
+            return Err(PluginExecutionError::Cancelled);
+        }
+
+        let list_ptr = unsafe { ffi::gs_app_list_new() };
+        if list_ptr.is_null() {
+            return Err(PluginExecutionError::Operation(
+                "gs_app_list_new returned null".to_string(),
+            ));
+        }
+        let _list_guard = GObjectGuard(list_ptr as *mut GObject);
+        unsafe { ffi::gs_app_list_add(list_ptr, app.as_ptr()) };
+
+        let (job_ptr, constructor) = unsafe {
+            match kind {
+                #[cfg(gs_install_apps_job)]
+                JobKind::Install => (
+                    ffi::gs_plugin_job_install_apps_new(
+                        list_ptr,
+                        ffi::GS_PLUGIN_INSTALL_APPS_FLAGS_INTERACTIVE,
+                    ),
+                    "gs_plugin_job_install_apps_new",
+                ),
+                #[cfg(gs_install_apps_job)]
+                JobKind::Remove => (
+                    ffi::gs_plugin_job_uninstall_apps_new(
+                        list_ptr,
+                        ffi::GS_PLUGIN_UNINSTALL_APPS_FLAGS_INTERACTIVE,
+                    ),
+                    "gs_plugin_job_uninstall_apps_new",
+                ),
+                // gnome-software 46 has no install or uninstall job.
+                #[cfg(not(gs_install_apps_job))]
+                JobKind::Install | JobKind::Remove => {
+                    return Err(PluginExecutionError::Unsupported);
+                }
+                JobKind::Update => (
+                    ffi::gs_plugin_job_update_apps_new(
+                        list_ptr,
+                        ffi::GS_PLUGIN_UPDATE_APPS_FLAGS_INTERACTIVE,
+                    ),
+                    "gs_plugin_job_update_apps_new",
+                ),
+            }
+        };
+        let job = NonNull::new(job_ptr).ok_or_else(|| {
+            PluginExecutionError::Operation(format!("{constructor} returned null"))
+        })?;
+        let _job_guard = GObjectGuard(job.as_ptr() as *mut GObject);
+
+        self.process_job(job, cancellable)
+    }
+
+    /// Runs a list-apps job for `query`, taking ownership of the query.
+    /// A full listing (`replace_known`) replaces the remembered apps, so
+    /// apps the plugin no longer returns are released; other listings only
+    /// add to them.
+    fn list_apps_blocking(
+        &self,
+        query: NonNull<ffi::GsAppQuery>,
+        replace_known: bool,
+        cancellable: &gio::Cancellable,
+    ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+        let _query_guard = GObjectGuard(query.as_ptr() as *mut GObject);
+
+        let job_ptr = unsafe {
+            ffi::gs_plugin_job_list_apps_new(
+                query.as_ptr(),
+                ffi::GS_PLUGIN_LIST_APPS_FLAGS_INTERACTIVE,
+            )
+        };
+        let job = NonNull::new(job_ptr).ok_or_else(|| {
+            PluginExecutionError::Operation("gs_plugin_job_list_apps_new returned null".to_string())
+        })?;
+        let _job_guard = GObjectGuard(job.as_ptr() as *mut GObject);
+
+        self.process_job(job, cancellable)?;
+
         let list_ptr = unsafe { ffi::gs_plugin_job_list_apps_get_result_list(job.as_ptr()) };
         if list_ptr.is_null() {
             return Ok(Vec::new());
@@ -235,41 +842,208 @@ Confirm that you are running inside a desktop session or export DBUS_SYSTEM_BUS_
 
         let length = unsafe { ffi::gs_app_list_length(list_ptr) };
         let mut apps = Vec::with_capacity(length as usize);
+        let mut refs = HashMap::with_capacity(length as usize);
         for index in 0..length {
             let app_ptr = unsafe { ffi::gs_app_list_index(list_ptr, index) };
             if app_ptr.is_null() {
                 continue;
             }
 
-            let id = unsafe { cstring_ptr_to_string(ffi::gs_app_get_id(app_ptr)) }
-                .unwrap_or_else(|| "unknown".to_string());
-            let name =
-                unsafe { cstring_ptr_to_string(ffi::gs_app_get_name(app_ptr)) }.unwrap_or_else(|| id.clone());
-            let summary =
-                unsafe { cstring_ptr_to_string(ffi::gs_app_get_summary(app_ptr)) }.unwrap_or_default();
-            let source = unsafe { cstring_ptr_to_string(ffi::gs_app_get_origin(app_ptr)) }
-                .unwrap_or_else(|| self.plugin_name.clone());
-
-            apps.push(AppSummary {
-                app_id: id,
-                name,
-                summary,
-                source,
-            });
+            let app = unsafe { self.summary_from_app(app_ptr) };
+            if let Some(app_ref) = AppRef::retain(app_ptr) {
+                refs.insert(app.app_id.clone(), app_ref);
+            }
+            apps.push(app);
         }
 
-        if apps.is_empty() {
-            return Ok(apps);
+        let mut known = self.known_apps.lock();
+        if replace_known {
+            *known = refs;
+        } else {
+            known.extend(refs);
         }
 
         Ok(apps)
     }
 
-    fn refresh_metadata_blocking(&self) -> Result<(), PluginExecutionError> {
+    /// # Safety
+    ///
+    /// `app_ptr` must point to a live `GsApp`.
+    unsafe fn summary_from_app(&self, app_ptr: *mut ffi::GsApp) -> AppSummary {
+        let id = cstring_ptr_to_string(ffi::gs_app_get_id(app_ptr))
+            .unwrap_or_else(|| "unknown".to_string());
+        let name = cstring_ptr_to_string(ffi::gs_app_get_name(app_ptr)).unwrap_or_else(|| id.clone());
+        let summary = cstring_ptr_to_string(ffi::gs_app_get_summary(app_ptr)).unwrap_or_default();
+        let source = cstring_ptr_to_string(ffi::gs_app_get_origin(app_ptr))
+            .unwrap_or_else(|| self.plugin_name.clone());
+
+        let installed = matches!(
+            ffi::gs_app_get_state(app_ptr),
+            ffi::GS_APP_STATE_INSTALLED
+                | ffi::GS_APP_STATE_REMOVING
+                | ffi::GS_APP_STATE_UPDATABLE
+                | ffi::GS_APP_STATE_UPDATABLE_LIVE
+        );
+
+        AppSummary {
+            app_id: id,
+            name,
+            summary,
+            source,
+            installed,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `app_ptr` must point to a live `GsApp` refined with version, size and
+    /// update details.
+    unsafe fn update_from_app(&self, app_ptr: *mut ffi::GsApp) -> AppUpdate {
+        let mut download_size = 0u64;
+        let download_size = (ffi::gs_app_get_size_download(app_ptr, &mut download_size)
+            == ffi::GS_SIZE_TYPE_VALID)
+            .then_some(download_size);
+
+        AppUpdate {
+            app: self.summary_from_app(app_ptr),
+            version_from: cstring_ptr_to_string(ffi::gs_app_get_version(app_ptr)),
+            version_to: cstring_ptr_to_string(ffi::gs_app_get_update_version(app_ptr)),
+            download_size,
+            plugin: format!("legacy::{}", self.plugin_name),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `app_ptr` must point to a live `GsApp` of kind repository.
+    unsafe fn repository_from_app(&self, app_ptr: *mut ffi::GsApp) -> Repository {
+        let id = cstring_ptr_to_string(ffi::gs_app_get_id(app_ptr))
+            .unwrap_or_else(|| "unknown".to_string());
+        let name = cstring_ptr_to_string(ffi::gs_app_get_name(app_ptr)).unwrap_or_else(|| id.clone());
+        // Sources carry the remote URL as their homepage.
+        let url = cstring_ptr_to_string(ffi::gs_app_get_url(app_ptr, ffi::AS_URL_KIND_HOMEPAGE));
+
+        Repository {
+            id,
+            name,
+            url,
+            enabled: ffi::gs_app_get_state(app_ptr) == ffi::GS_APP_STATE_INSTALLED,
+            plugin: format!("legacy::{}", self.plugin_name),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `app_ptr` must point to a live `GsApp`.
+    unsafe fn details_from_app(&self, app_ptr: *mut ffi::GsApp) -> AppDetails {
+        let mut details = AppDetails::from_summary(self.summary_from_app(app_ptr));
+        details.description = cstring_ptr_to_string(ffi::gs_app_get_description(app_ptr));
+        details.version = cstring_ptr_to_string(ffi::gs_app_get_version(app_ptr));
+        details.license = cstring_ptr_to_string(ffi::gs_app_get_license(app_ptr));
+        details.developer = cstring_ptr_to_string(ffi::gs_app_get_developer_name(app_ptr));
+        details.homepage =
+            cstring_ptr_to_string(ffi::gs_app_get_url(app_ptr, ffi::AS_URL_KIND_HOMEPAGE));
+
+        let icon_ptr =
+            ffi::gs_app_get_icon_for_size(app_ptr, DETAILS_ICON_SIZE, 1, ptr::null());
+        if !icon_ptr.is_null() {
+            let icon = gio::Icon::from_glib_full(icon_ptr);
+            details.icon = gio::prelude::IconExt::to_string(&icon).map(|value| value.to_string());
+        }
+
+        let categories = ffi::gs_app_get_categories(app_ptr);
+        if !categories.is_null() {
+            let categories = &*categories;
+            for index in 0..categories.len as usize {
+                let category = *categories.pdata.add(index) as *const c_char;
+                if let Some(category) = cstring_ptr_to_string(category) {
+                    details.categories.push(category);
+                }
+            }
+        }
+
+        // GNOME Software lists the default screenshot first already.
+        let screenshots = ffi::gs_app_get_screenshots(app_ptr);
+        if !screenshots.is_null() {
+            let screenshots = &*screenshots;
+            for index in 0..screenshots.len as usize {
+                let screenshot = *screenshots.pdata.add(index) as *mut GObject;
+                if let Some(url) = screenshot_url(screenshot) {
+                    details.screenshots.push(url);
+                }
+            }
+        }
+
+        details.install_state = match ffi::gs_app_get_state(app_ptr) {
+            ffi::GS_APP_STATE_INSTALLED | ffi::GS_APP_STATE_REMOVING => InstallState::Installed,
+            ffi::GS_APP_STATE_UPDATABLE | ffi::GS_APP_STATE_UPDATABLE_LIVE => {
+                InstallState::Updatable
+            }
+            ffi::GS_APP_STATE_AVAILABLE | ffi::GS_APP_STATE_AVAILABLE_LOCAL => {
+                InstallState::Available
+            }
+            _ => InstallState::Unknown,
+        };
+
+        let mut size = 0u64;
+        if ffi::gs_app_get_size_download(app_ptr, &mut size) == ffi::GS_SIZE_TYPE_VALID {
+            details.download_size = Some(size);
+        }
+        if ffi::gs_app_get_size_installed(app_ptr, &mut size) == ffi::GS_SIZE_TYPE_VALID {
+            details.installed_size = Some(size);
+        }
+
+        let rating = ffi::gs_app_dup_content_rating(app_ptr);
+        if !rating.is_null() {
+            let _rating_guard = GObjectGuard(rating);
+            details.content_rating = Some(ffi::as_content_rating_get_minimum_age(rating));
+        }
+
+        details
+    }
+
+    /// Runs `job` to completion on the loader.
+    fn process_job(
+        &self,
+        job: NonNull<ffi::GsPluginJob>,
+        cancellable: &gio::Cancellable,
+    ) -> Result<(), PluginExecutionError> {
+        let mut error: *mut ffi::GError = ptr::null_mut();
+        let ok = unsafe {
+            ffi::gs_plugin_loader_job_process(
+                self.loader.as_ptr(),
+                job.as_ptr(),
+                cancellable.to_glib_none().0,
+                &mut error,
+            )
+        };
+        if ok == 0 {
+            let message = unsafe { gerror_to_message(error) };
+            unsafe {
+                if !error.is_null() {
+                    g_error_free(error);
+                }
+            }
+            if cancellable.is_cancelled() {
+                return Err(PluginExecutionError::Cancelled);
+            }
+            return Err(PluginExecutionError::Operation(message));
+        }
+
+        Ok(())
+    }
+
+    fn refresh_metadata_blocking(
+        &self,
+        cancellable: Option<&gio::Cancellable>,
+    ) -> Result<(), PluginExecutionError> {
         let _guard = self.lock.lock();
 
         let job_ptr = unsafe {
-            ffi::gs_plugin_job_refresh_metadata_new(u64::MAX, ffi::GS_PLUGIN_REFRESH_METADATA_FLAGS_NONE)
+            ffi::gs_plugin_job_refresh_metadata_new(
+                self.metadata_max_age.as_secs(),
+                ffi::GS_PLUGIN_REFRESH_METADATA_FLAGS_NONE,
+            )
         };
         let job = NonNull::new(job_ptr).ok_or_else(|| {
             PluginExecutionError::Operation(
@@ -283,7 +1057,7 @@ Confirm that you are running inside a desktop session or export DBUS_SYSTEM_BUS_
             ffi::gs_plugin_loader_job_process(
                 self.loader.as_ptr(),
                 job.as_ptr(),
-                ptr::null_mut(),
+                cancellable.to_glib_none().0,
                 &mut error,
             )
         };
@@ -344,6 +1118,132 @@ Confirm that you are running inside a desktop session or export DBUS_SYSTEM_BUS_
 
         Ok(query)
     }
+
+    fn create_repository_query(&self) -> Result<NonNull<ffi::GsAppQuery>, PluginExecutionError> {
+        let is_source = CString::new("is-source").unwrap();
+
+        let query_ptr = unsafe {
+            ffi::gs_app_query_new(
+                is_source.as_ptr(),
+                ffi::GS_APP_QUERY_TRISTATE_TRUE,
+                ptr::null::<c_char>(),
+            )
+        };
+
+        NonNull::new(query_ptr).ok_or_else(|| {
+            PluginExecutionError::Operation("gs_app_query_new returned null".to_string())
+        })
+    }
+
+    fn create_category_query(
+        &self,
+        category: *mut ffi::GsCategory,
+    ) -> Result<NonNull<ffi::GsAppQuery>, PluginExecutionError> {
+        let category_key = CString::new("category").unwrap();
+        let refine_flags_key = CString::new("refine-flags").unwrap();
+        let dedupe_flags_key = CString::new("dedupe-flags").unwrap();
+        let license_type_key = CString::new("license-type").unwrap();
+
+        let refine_flags: c_uint = ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_RATING
+            | ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_CATEGORIES
+            | ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_ICON
+            | ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_ORIGIN;
+
+        let dedupe_flags: c_uint = ffi::GS_APP_LIST_FILTER_FLAG_PREFER_INSTALLED
+            | ffi::GS_APP_LIST_FILTER_FLAG_KEY_ID_PROVIDES;
+
+        // The query keeps its own reference to `category`.
+        let query_ptr = unsafe {
+            ffi::gs_app_query_new(
+                category_key.as_ptr(),
+                category,
+                refine_flags_key.as_ptr(),
+                refine_flags,
+                dedupe_flags_key.as_ptr(),
+                dedupe_flags,
+                license_type_key.as_ptr(),
+                ffi::GS_APP_QUERY_LICENSE_ANY,
+                ptr::null::<c_char>(),
+            )
+        };
+
+        NonNull::new(query_ptr).ok_or_else(|| {
+            PluginExecutionError::Operation("gs_app_query_new returned null".to_string())
+        })
+    }
+
+    fn create_update_query(&self) -> Result<NonNull<ffi::GsAppQuery>, PluginExecutionError> {
+        let is_for_update = CString::new("is-for-update").unwrap();
+        let refine_flags_key = CString::new("refine-flags").unwrap();
+
+        let refine_flags: c_uint = ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_VERSION
+            | ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_SIZE
+            | ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_UPDATE_DETAILS
+            | ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_ORIGIN;
+
+        let query_ptr = unsafe {
+            ffi::gs_app_query_new(
+                is_for_update.as_ptr(),
+                ffi::GS_APP_QUERY_TRISTATE_TRUE,
+                refine_flags_key.as_ptr(),
+                refine_flags,
+                ptr::null::<c_char>(),
+            )
+        };
+
+        NonNull::new(query_ptr).ok_or_else(|| {
+            PluginExecutionError::Operation("gs_app_query_new returned null".to_string())
+        })
+    }
+
+    fn create_search_query(
+        &self,
+        keywords: &[String],
+        max_results: u32,
+    ) -> Result<NonNull<ffi::GsAppQuery>, PluginExecutionError> {
+        let keywords_key = CString::new("keywords").unwrap();
+        let max_results_key = CString::new("max-results").unwrap();
+        let refine_flags_key = CString::new("refine-flags").unwrap();
+        let dedupe_flags_key = CString::new("dedupe-flags").unwrap();
+        let license_type_key = CString::new("license-type").unwrap();
+
+        let keyword_storage =
+            to_c_string_array(keywords).map_err(PluginExecutionError::Operation)?;
+        let mut keyword_ptrs = keyword_storage
+            .iter()
+            .map(|value| value.as_ptr())
+            .collect::<Vec<_>>();
+        keyword_ptrs.push(ptr::null());
+
+        let refine_flags: c_uint = ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_RATING
+            | ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_ICON
+            | ffi::GS_PLUGIN_REFINE_FLAGS_REQUIRE_ORIGIN;
+
+        let dedupe_flags: c_uint = ffi::GS_APP_LIST_FILTER_FLAG_PREFER_INSTALLED
+            | ffi::GS_APP_LIST_FILTER_FLAG_KEY_ID_PROVIDES;
+
+        // `keywords` is copied by the query, so the storage only has to
+        // outlive this call.
+        let query_ptr = unsafe {
+            ffi::gs_app_query_new(
+                keywords_key.as_ptr(),
+                keyword_ptrs.as_ptr(),
+                max_results_key.as_ptr(),
+                max_results as c_uint,
+                refine_flags_key.as_ptr(),
+                refine_flags,
+                dedupe_flags_key.as_ptr(),
+                dedupe_flags,
+                license_type_key.as_ptr(),
+                ffi::GS_APP_QUERY_LICENSE_ANY,
+                ptr::null::<c_char>(),
+            )
+        };
+
+        NonNull::new(query_ptr).ok_or_else(|| {
+            PluginExecutionError::Operation("gs_app_query_new returned null".to_string())
+        })
+    }
 }
 
 impl Drop for FlatpakLoader {
@@ -356,8 +1256,91 @@ impl Drop for FlatpakLoader {
 
 pub async fn list_all_apps(
     loader: Arc<FlatpakLoader>,
+    cancel: CancellationHandle,
 ) -> Result<Vec<AppSummary>, PluginExecutionError> {
-    loader.list_all_async().await
+    loader.list_all_async(cancel).await
+}
+
+pub async fn list_categories(
+    loader: Arc<FlatpakLoader>,
+    cancel: CancellationHandle,
+) -> Result<Vec<Category>, PluginExecutionError> {
+    loader.list_categories_async(cancel).await
+}
+
+pub async fn list_category_apps(
+    loader: Arc<FlatpakLoader>,
+    category_id: &str,
+    cancel: CancellationHandle,
+) -> Result<Vec<AppSummary>, PluginExecutionError> {
+    loader
+        .list_category_apps_async(category_id.to_string(), cancel)
+        .await
+}
+
+pub async fn list_updates(
+    loader: Arc<FlatpakLoader>,
+    cancel: CancellationHandle,
+) -> Result<Vec<AppUpdate>, PluginExecutionError> {
+    loader.list_updates_async(cancel).await
+}
+
+pub async fn list_repositories(
+    loader: Arc<FlatpakLoader>,
+    cancel: CancellationHandle,
+) -> Result<Vec<Repository>, PluginExecutionError> {
+    loader.list_repositories_async(cancel).await
+}
+
+pub async fn add_repository(
+    loader: Arc<FlatpakLoader>,
+    file: &Path,
+    cancel: CancellationHandle,
+) -> Result<Repository, PluginExecutionError> {
+    loader.add_repository_async(file.to_path_buf(), cancel).await
+}
+
+pub async fn manage_repository(
+    loader: Arc<FlatpakLoader>,
+    repository_id: &str,
+    action: RepositoryAction,
+    cancel: CancellationHandle,
+) -> Result<(), PluginExecutionError> {
+    loader
+        .manage_repository_async(repository_id.to_string(), action, cancel)
+        .await
+}
+
+pub fn app_job(
+    loader: Arc<FlatpakLoader>,
+    app_id: String,
+    kind: JobKind,
+    cancel: CancellationHandle,
+) -> JobStream {
+    job_stream(move |reporter| loader.app_job_async(app_id, kind, cancel, reporter))
+}
+
+pub async fn app_details(
+    loader: Arc<FlatpakLoader>,
+    app_id: &str,
+    cancel: CancellationHandle,
+) -> Result<AppDetails, PluginExecutionError> {
+    loader.app_details_async(app_id.to_string(), cancel).await
+}
+
+pub async fn search_apps(
+    loader: Arc<FlatpakLoader>,
+    query: &str,
+    cancel: CancellationHandle,
+) -> Result<Vec<AppSummary>, PluginExecutionError> {
+    let keywords = query
+        .split_whitespace()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    if keywords.is_empty() {
+        return Ok(Vec::new());
+    }
+    loader.search_async(keywords, cancel).await
 }
 
 fn to_c_string_array(values: &[String]) -> Result<Vec<CString>, String> {
@@ -386,6 +1369,42 @@ unsafe fn gerror_to_message(error: *mut ffi::GError) -> String {
         .into_owned()
 }
 
+/// # Safety
+///
+/// `category` must point to a live `GsCategory`.
+unsafe fn category_from_ptr(category: *mut ffi::GsCategory) -> Category {
+    let id = cstring_ptr_to_string(ffi::gs_category_get_id(category))
+        .unwrap_or_else(|| "unknown".to_string());
+    let name = cstring_ptr_to_string(ffi::gs_category_get_name(category))
+        .unwrap_or_else(|| id.clone());
+    Category {
+        id,
+        name,
+        icon: cstring_ptr_to_string(ffi::gs_category_get_icon_name(category)),
+    }
+}
+
+/// URL of the source image of `screenshot`, or else its first image.
+///
+/// # Safety
+///
+/// `screenshot` must point to a live `AsScreenshot`.
+unsafe fn screenshot_url(screenshot: *mut GObject) -> Option<String> {
+    let images = ffi::as_screenshot_get_images(screenshot);
+    if images.is_null() {
+        return None;
+    }
+    let images = &*images;
+    let images = (0..images.len as usize)
+        .map(|index| *images.pdata.add(index) as *mut GObject)
+        .collect::<Vec<_>>();
+    let image = images
+        .iter()
+        .find(|image| ffi::as_image_get_kind(**image) == ffi::AS_IMAGE_KIND_SOURCE)
+        .or_else(|| images.first())?;
+    cstring_ptr_to_string(ffi::as_image_get_url(*image))
+}
+
 unsafe fn cstring_ptr_to_string(ptr: *const std::os::raw::c_char) -> Option<String> {
     if ptr.is_null() {
         None
@@ -405,3 +1424,44 @@ impl Drop for GObjectGuard {
         }
     }
 }
+
+/// Strong reference to a GObject such as a `GsApp` that can be handed
+/// between threads.
+struct ObjectRef<T>(NonNull<T>);
+
+type AppRef = ObjectRef<ffi::GsApp>;
+type CategoryRef = ObjectRef<ffi::GsCategory>;
+
+unsafe impl<T> Send for ObjectRef<T> {}
+unsafe impl<T> Sync for ObjectRef<T> {}
+
+impl<T> ObjectRef<T> {
+    fn retain(object: *mut T) -> Option<Self> {
+        let object = NonNull::new(object)?;
+        unsafe {
+            g_object_ref(object.as_ptr() as *mut GObject);
+        }
+        Some(Self(object))
+    }
+
+    fn as_ptr(&self) -> *mut T {
+        self.0.as_ptr()
+    }
+}
+
+impl<T> Clone for ObjectRef<T> {
+    fn clone(&self) -> Self {
+        unsafe {
+            g_object_ref(self.0.as_ptr() as *mut GObject);
+        }
+        Self(self.0)
+    }
+}
+
+impl<T> Drop for ObjectRef<T> {
+    fn drop(&mut self) {
+        unsafe {
+            g_object_unref(self.0.as_ptr() as *mut GObject);
+        }
+    }
+}
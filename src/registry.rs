@@ -0,0 +1,405 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::plugins::{PluginBackend, PluginExecutionError, PluginFailureKind, PluginKind};
+
+/// Circuit-breaker state of a plugin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HealthState {
+    /// The last call succeeded (or no call has been made yet).
+    Healthy,
+    /// The last call failed, but the plugin is still being called.
+    Degraded,
+    /// Too many consecutive panics or helper crashes; calls are skipped until
+    /// the cooldown ends.
+    Quarantined,
+    /// The cooldown ended; a single probe call decides whether the plugin
+    /// recovers, and other calls are skipped until it returns.
+    HalfOpen,
+    /// Switched off by the user; calls are skipped until it is enabled again.
+    Disabled,
+}
+
+/// When to stop calling a misbehaving plugin and when to try it again.
+#[derive(Debug, Clone, Copy)]
+pub struct QuarantinePolicy {
+    pub panic_threshold: u32,
+    pub cooldown: Duration,
+}
+
+impl Default for QuarantinePolicy {
+    fn default() -> Self {
+        Self {
+            panic_threshold: 3,
+            cooldown: Duration::from_secs(300),
+        }
+    }
+}
+
+/// Point-in-time view of a plugin's health, suitable for the UI.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginHealth {
+    pub plugin: String,
+    pub plugin_kind: PluginKind,
+    pub state: HealthState,
+    pub successes: u64,
+    pub failures: u64,
+    pub consecutive_panics: u32,
+    pub last_error: Option<PluginFailureKind>,
+    pub last_latency: Option<Duration>,
+    /// How long the plugin took to list popular apps the last time it did.
+    pub last_refresh_latency: Option<Duration>,
+    /// Time left before a quarantined plugin is retried.
+    pub retry_in: Option<Duration>,
+}
+
+#[derive(Default)]
+struct HealthRecord {
+    successes: u64,
+    failures: u64,
+    consecutive_panics: u32,
+    last_error: Option<PluginFailureKind>,
+    last_latency: Option<Duration>,
+    last_refresh_latency: Option<Duration>,
+    quarantined_until: Option<Instant>,
+    /// A probe call was let through after the cooldown and has not returned.
+    half_open: bool,
+    disabled: bool,
+}
+
+impl HealthRecord {
+    fn state(&self, now: Instant) -> HealthState {
+        match self.quarantined_until {
+            _ if self.disabled => HealthState::Disabled,
+            Some(until) if now < until => HealthState::Quarantined,
+            Some(_) => HealthState::HalfOpen,
+            None if self.half_open => HealthState::HalfOpen,
+            None if self.last_error.is_some() => HealthState::Degraded,
+            None => HealthState::Healthy,
+        }
+    }
+}
+
+struct PluginEntry {
+    backend: Arc<dyn PluginBackend>,
+    health: Mutex<HealthRecord>,
+}
+
+/// Owns the host's backends and tracks their health across calls.
+pub struct PluginRegistry {
+    entries: Vec<PluginEntry>,
+    policy: QuarantinePolicy,
+}
+
+impl PluginRegistry {
+    pub fn new(backends: Vec<Arc<dyn PluginBackend>>, policy: QuarantinePolicy) -> Self {
+        Self {
+            entries: backends
+                .into_iter()
+                .map(|backend| PluginEntry {
+                    backend,
+                    health: Mutex::new(HealthRecord::default()),
+                })
+                .collect(),
+            policy,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn backends(&self) -> impl Iterator<Item = &Arc<dyn PluginBackend>> + '_ {
+        self.entries.iter().map(|entry| &entry.backend)
+    }
+
+    pub fn find(&self, plugin_id: &str) -> Option<Arc<dyn PluginBackend>> {
+        self.entry(plugin_id).map(|entry| entry.backend.clone())
+    }
+
+    /// Refuses calls while the plugin is disabled or quarantined (unknown
+    /// plugins count as quarantined). Once the cooldown has elapsed the plugin
+    /// moves to half-open and exactly one call is let through as a probe;
+    /// the others are refused until its outcome is recorded.
+    pub fn admit(&self, plugin_id: &str) -> Result<(), PluginFailureKind> {
+        let Some(entry) = self.entry(plugin_id) else {
+            return Err(PluginFailureKind::Quarantined);
+        };
+        let mut health = entry.health.lock();
+        if health.disabled {
+            return Err(PluginFailureKind::Disabled);
+        }
+        if health.half_open {
+            return Err(PluginFailureKind::Quarantined);
+        }
+        match health.quarantined_until {
+            Some(until) if Instant::now() < until => Err(PluginFailureKind::Quarantined),
+            Some(_) => {
+                health.quarantined_until = None;
+                health.half_open = true;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Ends a quarantine early: the next call is let through as if the
+    /// cooldown had elapsed. Returns `false` for unknown plugins.
+    pub fn retry(&self, plugin_id: &str) -> bool {
+        let Some(entry) = self.entry(plugin_id) else {
+            return false;
+        };
+        let mut health = entry.health.lock();
+        if let Some(until) = health.quarantined_until.as_mut() {
+            *until = Instant::now();
+        }
+        true
+    }
+
+    /// Enables or disables a plugin for the rest of the session. Returns
+    /// `false` for unknown plugins.
+    pub fn set_enabled(&self, plugin_id: &str, enabled: bool) -> bool {
+        let Some(entry) = self.entry(plugin_id) else {
+            return false;
+        };
+        entry.health.lock().disabled = !enabled;
+        true
+    }
+
+    /// Records how long a completed popular-apps listing took.
+    pub fn record_refresh(&self, plugin_id: &str, latency: Duration) {
+        if let Some(entry) = self.entry(plugin_id) {
+            entry.health.lock().last_refresh_latency = Some(latency);
+        }
+    }
+
+    /// Records the outcome of an admitted call. Cancelled and unsupported
+    /// calls do not count; if one was the half-open probe, the next call
+    /// probes again.
+    pub fn record(
+        &self,
+        plugin_id: &str,
+        outcome: Result<(), &PluginFailureKind>,
+        latency: Duration,
+    ) {
+        let Some(entry) = self.entry(plugin_id) else {
+            return;
+        };
+        let mut health = entry.health.lock();
+
+        match outcome {
+            Ok(()) => {
+                health.successes += 1;
+                health.consecutive_panics = 0;
+                health.last_error = None;
+                health.half_open = false;
+            }
+            Err(PluginFailureKind::Execution(
+                PluginExecutionError::Cancelled | PluginExecutionError::Unsupported,
+            )) => {
+                if health.half_open {
+                    health.half_open = false;
+                    health.quarantined_until = Some(Instant::now());
+                }
+                return;
+            }
+            Err(kind) => {
+                health.failures += 1;
+                health.last_error = Some(kind.clone());
+
+                // A crashed helper process is as fatal as an in-process panic.
+                if matches!(kind, PluginFailureKind::Panic | PluginFailureKind::Crashed(_)) {
+                    health.consecutive_panics += 1;
+                    if health.half_open
+                        || health.consecutive_panics >= self.policy.panic_threshold
+                    {
+                        health.quarantined_until = Some(Instant::now() + self.policy.cooldown);
+                    }
+                } else {
+                    health.consecutive_panics = 0;
+                }
+                health.half_open = false;
+            }
+        }
+        health.last_latency = Some(latency);
+    }
+
+    pub fn snapshot(&self) -> Vec<PluginHealth> {
+        let now = Instant::now();
+        self.entries
+            .iter()
+            .map(|entry| {
+                let descriptor = entry.backend.descriptor();
+                let health = entry.health.lock();
+                PluginHealth {
+                    plugin: descriptor.id.clone(),
+                    plugin_kind: descriptor.kind,
+                    state: health.state(now),
+                    successes: health.successes,
+                    failures: health.failures,
+                    consecutive_panics: health.consecutive_panics,
+                    last_error: health.last_error.clone(),
+                    last_latency: health.last_latency,
+                    last_refresh_latency: health.last_refresh_latency,
+                    retry_in: health
+                        .quarantined_until
+                        .and_then(|until| until.checked_duration_since(now)),
+                }
+            })
+            .collect()
+    }
+
+    fn entry(&self, plugin_id: &str) -> Option<&PluginEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.backend.descriptor().id == plugin_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::NativeMockPlugin;
+
+    const PLUGIN: &str = "native::mock";
+    const COOLDOWN: Duration = Duration::from_secs(3600);
+
+    fn registry(panic_threshold: u32, cooldown: Duration) -> PluginRegistry {
+        PluginRegistry::new(
+            vec![Arc::new(NativeMockPlugin::new(PLUGIN))],
+            QuarantinePolicy {
+                panic_threshold,
+                cooldown,
+            },
+        )
+    }
+
+    fn state(registry: &PluginRegistry) -> HealthState {
+        registry.snapshot()[0].state
+    }
+
+    fn record(registry: &PluginRegistry, outcome: Result<(), PluginFailureKind>) {
+        registry.record(PLUGIN, outcome.as_ref().map(|_| ()), Duration::ZERO);
+    }
+
+    fn cancelled() -> PluginFailureKind {
+        PluginFailureKind::Execution(PluginExecutionError::Cancelled)
+    }
+
+    #[test]
+    fn consecutive_panics_quarantine_the_plugin() {
+        let registry = registry(3, COOLDOWN);
+
+        for _ in 0..2 {
+            assert_eq!(registry.admit(PLUGIN), Ok(()));
+            record(&registry, Err(PluginFailureKind::Panic));
+            assert_eq!(state(&registry), HealthState::Degraded);
+        }
+        assert_eq!(registry.admit(PLUGIN), Ok(()));
+        record(&registry, Err(PluginFailureKind::Crashed("signal 11".to_string())));
+
+        assert_eq!(state(&registry), HealthState::Quarantined);
+        assert_eq!(registry.admit(PLUGIN), Err(PluginFailureKind::Quarantined));
+        let health = &registry.snapshot()[0];
+        assert_eq!(health.consecutive_panics, 3);
+        assert!(health.retry_in.is_some());
+    }
+
+    #[test]
+    fn other_failures_reset_the_panic_count() {
+        let registry = registry(2, COOLDOWN);
+
+        record(&registry, Err(PluginFailureKind::Panic));
+        record(
+            &registry,
+            Err(PluginFailureKind::Execution(PluginExecutionError::Timeout(
+                Duration::from_secs(1),
+            ))),
+        );
+        record(&registry, Err(PluginFailureKind::Panic));
+
+        assert_eq!(state(&registry), HealthState::Degraded);
+        assert_eq!(registry.admit(PLUGIN), Ok(()));
+    }
+
+    #[test]
+    fn elapsed_cooldown_admits_a_single_probe() {
+        let registry = registry(1, Duration::ZERO);
+        record(&registry, Err(PluginFailureKind::Panic));
+        assert_eq!(state(&registry), HealthState::HalfOpen);
+
+        assert_eq!(registry.admit(PLUGIN), Ok(()));
+        assert_eq!(registry.admit(PLUGIN), Err(PluginFailureKind::Quarantined));
+        assert_eq!(state(&registry), HealthState::HalfOpen);
+    }
+
+    #[test]
+    fn successful_probe_closes_the_breaker() {
+        let registry = registry(1, COOLDOWN);
+        record(&registry, Err(PluginFailureKind::Panic));
+        assert!(registry.retry(PLUGIN));
+
+        assert_eq!(registry.admit(PLUGIN), Ok(()));
+        record(&registry, Ok(()));
+
+        assert_eq!(state(&registry), HealthState::Healthy);
+        assert_eq!(registry.admit(PLUGIN), Ok(()));
+        assert_eq!(registry.admit(PLUGIN), Ok(()));
+    }
+
+    #[test]
+    fn failed_probe_quarantines_again() {
+        let registry = registry(3, COOLDOWN);
+        for _ in 0..3 {
+            record(&registry, Err(PluginFailureKind::Panic));
+        }
+        assert!(registry.retry(PLUGIN));
+
+        assert_eq!(registry.admit(PLUGIN), Ok(()));
+        // A single panic is enough while half-open.
+        record(&registry, Err(PluginFailureKind::Panic));
+
+        assert_eq!(state(&registry), HealthState::Quarantined);
+        assert_eq!(registry.admit(PLUGIN), Err(PluginFailureKind::Quarantined));
+    }
+
+    #[test]
+    fn cancelled_probe_lets_the_next_call_probe() {
+        let registry = registry(1, COOLDOWN);
+        record(&registry, Err(PluginFailureKind::Panic));
+        assert!(registry.retry(PLUGIN));
+
+        assert_eq!(registry.admit(PLUGIN), Ok(()));
+        record(&registry, Err(cancelled()));
+        assert_eq!(state(&registry), HealthState::HalfOpen);
+
+        assert_eq!(registry.admit(PLUGIN), Ok(()));
+        assert_eq!(registry.admit(PLUGIN), Err(PluginFailureKind::Quarantined));
+        record(&registry, Ok(()));
+        assert_eq!(state(&registry), HealthState::Healthy);
+    }
+
+    #[test]
+    fn disabled_plugins_are_refused() {
+        let registry = registry(1, COOLDOWN);
+
+        assert!(registry.set_enabled(PLUGIN, false));
+        assert_eq!(registry.admit(PLUGIN), Err(PluginFailureKind::Disabled));
+        assert_eq!(state(&registry), HealthState::Disabled);
+
+        assert!(registry.set_enabled(PLUGIN, true));
+        assert_eq!(registry.admit(PLUGIN), Ok(()));
+        assert!(!registry.set_enabled("native::unknown", false));
+        assert_eq!(
+            registry.admit("native::unknown"),
+            Err(PluginFailureKind::Quarantined)
+        );
+    }
+}
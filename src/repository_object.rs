@@ -0,0 +1,64 @@
+use std::cell::{Cell, RefCell};
+
+use glib::prelude::*;
+use glib::subclass::prelude::*;
+use glib::Object;
+
+use crate::plugins::Repository;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::RepositoryObject)]
+    pub struct RepositoryObject {
+        #[property(get, construct_only)]
+        pub id: RefCell<String>,
+        #[property(get, construct_only)]
+        pub name: RefCell<String>,
+        #[property(get, construct_only)]
+        pub url: RefCell<Option<String>>,
+        #[property(get, construct_only)]
+        pub enabled: Cell<bool>,
+        /// Id of the backend that reported the repository.
+        #[property(get, construct_only)]
+        pub plugin: RefCell<String>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for RepositoryObject {
+        const NAME: &'static str = "InstallGridRepository";
+        type Type = super::RepositoryObject;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for RepositoryObject {}
+}
+
+glib::wrapper! {
+    /// Immutable snapshot of a [`Repository`], passed to `repository-changed`
+    /// handlers on [`crate::application::InstallGridApplication`].
+    pub struct RepositoryObject(ObjectSubclass<imp::RepositoryObject>);
+}
+
+impl RepositoryObject {
+    pub fn new(repository: &Repository) -> Self {
+        Object::builder::<Self>()
+            .property("id", &repository.id)
+            .property("name", &repository.name)
+            .property("url", &repository.url)
+            .property("enabled", repository.enabled)
+            .property("plugin", &repository.plugin)
+            .build()
+    }
+
+    pub fn repository(&self) -> Repository {
+        Repository {
+            id: self.id(),
+            name: self.name(),
+            url: self.url(),
+            enabled: self.enabled(),
+            plugin: self.plugin(),
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use tokio::sync::{broadcast, Notify};
+
+use crate::cancellation::CancellationHandle;
+use crate::host::{AppStoreService, HostError, RefreshOutcome};
+
+/// Lower bound on the refresh interval, whatever the cache max age.
+const MIN_INTERVAL: Duration = Duration::from_secs(60);
+const EVENT_CAPACITY: usize = 16;
+
+/// Timing knobs of a [`RefreshScheduler`]. The interval itself is the
+/// service's cache max age.
+#[derive(Debug, Clone)]
+pub struct RefreshSchedule {
+    /// Up to this fraction of each delay is added at random, so clients
+    /// started together do not refresh in lockstep.
+    pub jitter: f64,
+    /// Delay after the first failed refresh (usually
+    /// [`HostError::AllFailed`]); doubled after each further one.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RefreshSchedule {
+    fn default() -> Self {
+        Self {
+            jitter: 0.1,
+            initial_backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(30 * 60),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SchedulerEvent {
+    /// The next refresh is due in `delay`.
+    Scheduled { delay: Duration },
+    RefreshStarted,
+    /// The refresh completed and has been written to the cache.
+    RefreshFinished(RefreshOutcome),
+    /// The refresh failed; a [`SchedulerEvent::Scheduled`] with the retry
+    /// delay follows.
+    RefreshFailed(HostError),
+    Paused,
+    Resumed,
+}
+
+/// Handle to the background refresh loop of an [`AppStoreService`]. The
+/// loop refreshes whenever the cache reaches the service's max age, so a
+/// refresh done by hand pushes the next scheduled one back.
+#[derive(Clone)]
+pub struct RefreshScheduler {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    schedule: RefreshSchedule,
+    started: AtomicBool,
+    paused: AtomicBool,
+    /// Interrupts the current wait after a pause or resume.
+    wake: Notify,
+    stop: CancellationHandle,
+    /// The scheduled refresh in flight, cancelled by [`RefreshScheduler::pause`].
+    current: Mutex<Option<CancellationHandle>>,
+    events: broadcast::Sender<SchedulerEvent>,
+}
+
+impl RefreshScheduler {
+    pub(crate) fn new(schedule: RefreshSchedule) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            inner: Arc::new(Inner {
+                schedule,
+                started: AtomicBool::new(false),
+                paused: AtomicBool::new(false),
+                wake: Notify::new(),
+                stop: CancellationHandle::new(),
+                current: Mutex::new(None),
+                events,
+            }),
+        }
+    }
+
+    /// Spawns the refresh loop on `handle` unless it is already running.
+    pub(crate) fn start(&self, service: AppStoreService, handle: &tokio::runtime::Handle) {
+        if !self.inner.started.swap(true, Ordering::SeqCst) {
+            handle.spawn(run(self.inner.clone(), service));
+        }
+    }
+
+    /// Events from now on. Slow receivers skip the oldest events and see
+    /// `RecvError::Lagged`.
+    pub fn subscribe(&self) -> broadcast::Receiver<SchedulerEvent> {
+        self.inner.events.subscribe()
+    }
+
+    /// Stops scheduling refreshes and cancels a scheduled refresh in flight.
+    /// Refreshes started by other callers are not affected.
+    pub fn pause(&self) {
+        if !self.inner.paused.swap(true, Ordering::SeqCst) {
+            if let Some(cancel) = self.inner.current.lock().take() {
+                cancel.cancel();
+            }
+            self.inner.send(SchedulerEvent::Paused);
+            self.inner.wake.notify_one();
+        }
+    }
+
+    /// Schedules again, refreshing straight away if the cache went stale
+    /// while paused.
+    pub fn resume(&self) {
+        if self.inner.paused.swap(false, Ordering::SeqCst) {
+            self.inner.send(SchedulerEvent::Resumed);
+            self.inner.wake.notify_one();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.inner.paused.load(Ordering::SeqCst)
+    }
+
+    /// Ends the refresh loop for good.
+    pub fn stop(&self) {
+        self.inner.stop.cancel();
+    }
+}
+
+impl Inner {
+    fn send(&self, event: SchedulerEvent) {
+        // No subscribers is fine.
+        let _ = self.events.send(event);
+    }
+
+    fn backoff(&self, failures: u32) -> Duration {
+        let doublings = failures.saturating_sub(1).min(16);
+        self.schedule
+            .initial_backoff
+            .saturating_mul(1 << doublings)
+            .min(self.schedule.max_backoff)
+    }
+
+    fn jittered(&self, delay: Duration) -> Duration {
+        if self.schedule.jitter <= 0.0 || delay.is_zero() {
+            return delay;
+        }
+        delay + delay.mul_f64(glib::random_double_range(0.0, self.schedule.jitter))
+    }
+}
+
+async fn run(inner: Arc<Inner>, service: AppStoreService) {
+    let mut failures = 0u32;
+    let mut attempted = false;
+
+    loop {
+        if inner.paused.load(Ordering::SeqCst) {
+            tokio::select! {
+                _ = inner.wake.notified() => continue,
+                _ = inner.stop.cancelled() => return,
+            }
+        }
+
+        let delay = if failures > 0 {
+            inner.backoff(failures)
+        } else {
+            let interval = service.max_age().max(MIN_INTERVAL);
+            // An empty cache is refreshed at once, but only the first time:
+            // with no backends registered it stays empty.
+            let age = service.cache_age().unwrap_or(if attempted {
+                Duration::ZERO
+            } else {
+                interval
+            });
+            interval.saturating_sub(age)
+        };
+        let delay = inner.jittered(delay);
+        inner.send(SchedulerEvent::Scheduled { delay });
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = inner.wake.notified() => continue,
+            _ = inner.stop.cancelled() => return,
+        }
+        if failures == 0 && !service.is_stale() {
+            // Someone else refreshed while we slept.
+            continue;
+        }
+
+        let cancel = CancellationHandle::new();
+        {
+            let mut current = inner.current.lock();
+            if inner.paused.load(Ordering::SeqCst) {
+                continue;
+            }
+            *current = Some(cancel.clone());
+        }
+        inner.send(SchedulerEvent::RefreshStarted);
+
+        let result = tokio::select! {
+            result = service.refresh_popular(&cancel) => result,
+            _ = inner.stop.cancelled() => {
+                cancel.cancel();
+                return;
+            }
+        };
+        inner.current.lock().take();
+        attempted |= !matches!(result, Err(HostError::Cancelled));
+
+        match result {
+            Ok(outcome) => {
+                failures = 0;
+                inner.send(SchedulerEvent::RefreshFinished(outcome));
+            }
+            // Paused mid-refresh; `Paused` has already been sent.
+            Err(HostError::Cancelled) => {}
+            // The cache is still stale, so anything but backing off would
+            // retry immediately.
+            Err(err) => {
+                failures += 1;
+                inner.send(SchedulerEvent::RefreshFailed(err));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::future::Future;
+    use std::time::SystemTime;
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::cache::{CacheStore, MemoryCacheStore};
+    use crate::host::{PluginHostBuilder, StoreEvent};
+    use crate::plugins::{
+        failed_job, AppDetails, AppSummary, JobStream, NativeMockPlugin, PluginBackend,
+        PluginDescriptor, PluginExecutionError, PluginKind,
+    };
+
+    /// Fails every call.
+    struct Failing {
+        descriptor: PluginDescriptor,
+    }
+
+    #[async_trait]
+    impl PluginBackend for Failing {
+        fn descriptor(&self) -> &PluginDescriptor {
+            &self.descriptor
+        }
+
+        async fn list_popular_apps(
+            &self,
+            _cancel: &CancellationHandle,
+        ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+            Err(PluginExecutionError::Operation("failing".to_string()))
+        }
+
+        async fn search(
+            &self,
+            _query: &str,
+            _cancel: &CancellationHandle,
+        ) -> Result<Vec<AppSummary>, PluginExecutionError> {
+            Err(PluginExecutionError::Operation("failing".to_string()))
+        }
+
+        async fn app_details(
+            &self,
+            _app_id: &str,
+            _cancel: &CancellationHandle,
+        ) -> Result<AppDetails, PluginExecutionError> {
+            Err(PluginExecutionError::Operation("failing".to_string()))
+        }
+
+        fn install(&self, _app_id: &str, _cancel: CancellationHandle) -> JobStream {
+            failed_job(PluginExecutionError::Unsupported)
+        }
+
+        fn remove(&self, _app_id: &str, _cancel: CancellationHandle) -> JobStream {
+            failed_job(PluginExecutionError::Unsupported)
+        }
+    }
+
+    fn schedule(initial_backoff: u64, max_backoff: u64) -> RefreshSchedule {
+        RefreshSchedule {
+            jitter: 0.0,
+            initial_backoff: Duration::from_secs(initial_backoff),
+            max_backoff: Duration::from_secs(max_backoff),
+        }
+    }
+
+    fn service(backend: impl PluginBackend + 'static, store: MemoryCacheStore) -> AppStoreService {
+        let host = PluginHostBuilder::new().with_backend(backend).build().unwrap();
+        AppStoreService::new(host, Arc::new(store))
+    }
+
+    /// Runs `future` with the clock paused; sleeps then end as soon as
+    /// nothing else is runnable.
+    fn run_paused<T>(future: impl Future<Output = T>) -> T {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .start_paused(true)
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    async fn next_event(events: &mut broadcast::Receiver<SchedulerEvent>) -> SchedulerEvent {
+        events.recv().await.expect("scheduler event")
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let scheduler = RefreshScheduler::new(schedule(30, 200));
+        let delays = (1..=5)
+            .map(|failures| scheduler.inner.backoff(failures).as_secs())
+            .collect::<Vec<_>>();
+        assert_eq!(delays, [30, 60, 120, 200, 200]);
+        assert_eq!(scheduler.inner.backoff(u32::MAX), Duration::from_secs(200));
+    }
+
+    #[test]
+    fn jitter_only_lengthens_delays() {
+        let scheduler = RefreshScheduler::new(schedule(30, 200));
+        let delay = Duration::from_secs(100);
+        assert_eq!(scheduler.inner.jittered(delay), delay);
+
+        let scheduler = RefreshScheduler::new(RefreshSchedule {
+            jitter: 0.5,
+            ..schedule(30, 200)
+        });
+        assert_eq!(scheduler.inner.jittered(Duration::ZERO), Duration::ZERO);
+        for _ in 0..100 {
+            let jittered = scheduler.inner.jittered(delay);
+            assert!(jittered >= delay && jittered <= delay.mul_f64(1.5), "{jittered:?}");
+        }
+    }
+
+    #[test]
+    fn failed_refreshes_back_off_exponentially() {
+        let failing = Failing {
+            descriptor: PluginDescriptor {
+                id: "native::failing".to_string(),
+                kind: PluginKind::Native,
+            },
+        };
+        let service = service(failing, MemoryCacheStore::new());
+        let scheduler = RefreshScheduler::new(schedule(10, 40));
+
+        let delays = run_paused(async {
+            let mut events = scheduler.subscribe();
+            let task = tokio::spawn(run(scheduler.inner.clone(), service.clone()));
+            let mut delays = Vec::new();
+            while delays.len() < 5 {
+                match next_event(&mut events).await {
+                    SchedulerEvent::Scheduled { delay } => delays.push(delay.as_secs()),
+                    SchedulerEvent::RefreshFailed(err) => {
+                        assert!(matches!(err, HostError::AllFailed(_)))
+                    }
+                    SchedulerEvent::RefreshStarted => {}
+                    other => panic!("unexpected {other:?}"),
+                }
+            }
+            scheduler.stop();
+            task.await.unwrap();
+            delays
+        });
+
+        // The empty cache is refreshed at once.
+        assert_eq!(delays, [0, 10, 20, 40, 40]);
+    }
+
+    #[test]
+    fn pausing_cancels_the_refresh_in_flight() {
+        let slow = NativeMockPlugin::new("native::slow").with_delay(Duration::from_secs(3600));
+        let service = service(slow, MemoryCacheStore::new());
+        let scheduler = RefreshScheduler::new(schedule(10, 40));
+
+        run_paused(async {
+            let mut events = scheduler.subscribe();
+            let mut store_events = service.subscribe();
+            let task = tokio::spawn(run(scheduler.inner.clone(), service.clone()));
+            assert!(matches!(next_event(&mut events).await, SchedulerEvent::Scheduled { .. }));
+            assert!(matches!(next_event(&mut events).await, SchedulerEvent::RefreshStarted));
+
+            scheduler.pause();
+            assert!(matches!(next_event(&mut events).await, SchedulerEvent::Paused));
+            loop {
+                if let StoreEvent::RefreshFinished(result) = store_events.recv().await.unwrap() {
+                    assert!(matches!(result, Err(HostError::Cancelled)));
+                    break;
+                }
+            }
+            assert!(scheduler.inner.current.lock().is_none());
+
+            scheduler.stop();
+            task.await.unwrap();
+        });
+    }
+
+    #[test]
+    fn resuming_refreshes_at_once_when_the_cache_went_stale() {
+        let store = MemoryCacheStore::new();
+        let fetched = SystemTime::now() - Duration::from_secs(2 * 3600);
+        let times = HashMap::from([("native::mock".to_string(), fetched)]);
+        store.store_refresh_times(&times).unwrap();
+        let mock = NativeMockPlugin::new("native::mock").with_delay(Duration::ZERO);
+        let service = service(mock, store);
+        let scheduler = RefreshScheduler::new(schedule(10, 40));
+        scheduler.pause();
+
+        run_paused(async {
+            let mut events = scheduler.subscribe();
+            let task = tokio::spawn(run(scheduler.inner.clone(), service.clone()));
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+            assert!(events.try_recv().is_err(), "a paused scheduler stays quiet");
+
+            let resumed_at = tokio::time::Instant::now();
+            scheduler.resume();
+            assert!(matches!(next_event(&mut events).await, SchedulerEvent::Resumed));
+            assert!(matches!(
+                next_event(&mut events).await,
+                SchedulerEvent::Scheduled { delay } if delay.is_zero()
+            ));
+            assert!(matches!(next_event(&mut events).await, SchedulerEvent::RefreshStarted));
+            assert!(matches!(
+                next_event(&mut events).await,
+                SchedulerEvent::RefreshFinished(_)
+            ));
+            assert_eq!(tokio::time::Instant::now(), resumed_at);
+
+            scheduler.stop();
+            task.await.unwrap();
+        });
+    }
+}
@@ -1,16 +1,31 @@
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
+use std::time::Duration;
 
 use adw::prelude::*;
+use futures::StreamExt;
 use glib::clone;
-use glib::ControlFlow;
-use glib::Priority;
 use gtk4 as gtk;
 use gtk::prelude::*;
 use gtk::{gio, glib};
 use libadwaita as adw;
 
 use crate::application::InstallGridApplication;
-use crate::host::{AppStoreService, HostError, RefreshOutcome};
+use crate::cancellation::CancellationHandle;
+use crate::host::{AppStoreService, HostError, RefreshEvent, StoreEvent};
+use crate::plugins::MergedApp;
+use crate::registry::HealthState;
+use crate::repository_object::RepositoryObject;
+use crate::scheduler::SchedulerEvent;
+
+mod app_list;
+mod categories;
+mod details;
+mod diagnostics;
+mod notices;
+mod preferences;
+mod search;
+mod updates;
 
 pub fn run(app_store: AppStoreService) -> glib::ExitCode {
     let application =
@@ -34,7 +49,7 @@ pub fn run(app_store: AppStoreService) -> glib::ExitCode {
 fn build_ui(app: &InstallGridApplication, service: Rc<AppStoreService>) {
     let window = adw::ApplicationWindow::builder()
         .application(app)
-        .default_width(480)
+        .default_width(800)
         .default_height(640)
         .title("InstallGrid")
         .build();
@@ -43,137 +58,405 @@ fn build_ui(app: &InstallGridApplication, service: Rc<AppStoreService>) {
     let refresh_button = gtk::Button::from_icon_name("view-refresh-symbolic");
     refresh_button.set_tooltip_text(Some("Refresh application list"));
 
+    let cancel_button = gtk::Button::with_label("Cancel");
+    cancel_button.set_tooltip_text(Some("Stop the running refresh"));
+    cancel_button.set_visible(false);
+
+    let preferences_button = gtk::Button::from_icon_name("emblem-system-symbolic");
+    preferences_button.set_tooltip_text(Some("Preferences"));
+
+    let search_button = gtk::ToggleButton::new();
+    search_button.set_icon_name("system-search-symbolic");
+    search_button.set_tooltip_text(Some("Search (Ctrl+F)"));
+
     let spinner = gtk::Spinner::new();
     spinner.set_spinning(false);
     spinner.set_visible(false);
 
+    header_bar.pack_end(&preferences_button);
     header_bar.pack_end(&spinner);
     header_bar.pack_end(&refresh_button);
-
-    let list_box = gtk::ListBox::new();
-    list_box.set_margin_top(12);
-    list_box.set_margin_bottom(12);
-    list_box.set_margin_start(12);
-    list_box.set_margin_end(12);
+    header_bar.pack_start(&search_button);
+    header_bar.pack_start(&cancel_button);
 
     let status_label = gtk::Label::new(None);
     status_label.set_halign(gtk::Align::Start);
 
-    let warning_label = gtk::Label::new(None);
-    warning_label.set_halign(gtk::Align::Start);
-    warning_label.add_css_class("dim-label");
-    warning_label.set_visible(false);
+    let updated_label = gtk::Label::new(None);
+    updated_label.set_halign(gtk::Align::Start);
+    updated_label.add_css_class("dim-label");
+    updated_label.add_css_class("caption");
+
+    let navigation = adw::NavigationView::new();
+    let notices = notices::Notices::new(&navigation);
+    let open_app: Rc<dyn Fn(MergedApp)> = Rc::new(
+        clone!(@weak service, @weak navigation => move |app| {
+            details::show(&navigation, service, app);
+        }),
+    );
+    let app_list = app_list::AppList::new(open_app.clone());
+
+    let apps_page = gtk::Box::new(gtk::Orientation::Vertical, 12);
+    apps_page.set_margin_top(12);
+    apps_page.set_margin_bottom(12);
+    apps_page.set_margin_start(12);
+    apps_page.set_margin_end(12);
+    apps_page.append(&status_label);
+    apps_page.append(&updated_label);
+    apps_page.append(app_list.widget());
+
+    let category_browser = categories::CategoryBrowser::new(
+        service.clone(),
+        &apps_page,
+        notices.clone(),
+        open_app.clone(),
+    );
+
+    let updates_page = updates::UpdatesPage::new(service.clone());
+
+    let view_stack = adw::ViewStack::new();
+    view_stack.add_titled_with_icon(
+        category_browser.widget(),
+        Some("apps"),
+        "Explore",
+        "view-grid-symbolic",
+    );
+    let updates_stack_page = view_stack.add_titled_with_icon(
+        updates_page.widget(),
+        Some("updates"),
+        "Updates",
+        "software-update-available-symbolic",
+    );
+    updates_stack_page.set_badge_number(service.update_snapshot().updates.len() as u32);
+    header_bar.set_title_widget(Some(
+        &adw::ViewSwitcher::builder()
+            .stack(&view_stack)
+            .policy(adw::ViewSwitcherPolicy::Wide)
+            .build(),
+    ));
+
+    let search_entry = gtk::SearchEntry::new();
+    search_entry.set_placeholder_text(Some("Search applications"));
+    search_entry.set_hexpand(true);
+    let search_clamp = adw::Clamp::builder()
+        .maximum_size(480)
+        .child(&search_entry)
+        .build();
+    let search_bar = gtk::SearchBar::new();
+    search_bar.set_child(Some(&search_clamp));
+    search_bar.connect_entry(&search_entry);
+    search_bar.set_key_capture_widget(Some(&window));
+    search_bar
+        .bind_property("search-mode-enabled", &search_button, "active")
+        .bidirectional()
+        .sync_create()
+        .build();
+
+    let search_page = search::SearchPage::new(
+        service.clone(),
+        &search_entry,
+        notices.clone(),
+        open_app.clone(),
+    );
+
+    // Results replace the views while there is a query.
+    let main_stack = gtk::Stack::new();
+    main_stack.set_vexpand(true);
+    main_stack.add_named(&view_stack, Some("browse"));
+    main_stack.add_named(search_page.widget(), Some("search"));
+    let show_search = clone!(@weak main_stack, @weak search_bar, @weak search_entry => move || {
+        let searching = search_bar.is_search_mode() && !search_entry.text().trim().is_empty();
+        main_stack.set_visible_child_name(if searching { "search" } else { "browse" });
+    });
+    search_entry.connect_changed(clone!(@strong show_search => move |_| show_search()));
+    search_bar.connect_search_mode_enabled_notify(clone!(@strong show_search => move |_| show_search()));
+
+    let search_action = gio::SimpleAction::new("search", None);
+    search_action.connect_activate(clone!(@weak search_bar, @weak search_entry => move |_, _| {
+        search_bar.set_search_mode(true);
+        search_entry.grab_focus();
+    }));
+    window.add_action(&search_action);
+    app.set_accels_for_action("win.search", &["<Control>f"]);
 
-    let content = gtk::Box::new(gtk::Orientation::Vertical, 12);
+    let content = gtk::Box::new(gtk::Orientation::Vertical, 0);
     content.append(&header_bar);
-    content.append(&status_label);
-    content.append(&warning_label);
-    content.append(&list_box);
-
-    window.set_content(Some(&content));
-
-    let (sender, receiver) =
-        glib::MainContext::channel::<Result<RefreshOutcome, String>>(Priority::default());
-
-    receiver.attach(
-        None,
-        clone!(@weak list_box, @weak status_label, @weak warning_label, @weak spinner, @weak service => @default-return ControlFlow::Break,
-            move |message| {
-                spinner.stop();
-                spinner.set_visible(false);
-                match message {
-                    Ok(outcome) => {
-                        rebuild_list(&list_box, &outcome.apps);
-                        status_label.set_text(&format!(
-                            "{} applications ({} plugins)",
-                            outcome.apps.len(),
-                            service.plugin_count()
-                        ));
+    content.append(notices.banner());
+    content.append(&search_bar);
+    content.append(&main_stack);
+
+    let root_page = adw::NavigationPage::new(&content, "InstallGrid");
+    navigation.add(&root_page);
+    window.set_content(Some(notices.widget()));
+
+    let current_refresh: Rc<RefCell<Option<CancellationHandle>>> = Rc::new(RefCell::new(None));
+
+    let trigger_refresh: Rc<dyn Fn()> = Rc::new(
+        clone!(@weak service, @weak status_label, @weak cancel_button, @weak refresh_button, @strong app_list, @strong notices, @strong current_refresh => move || {
+            let cancel = CancellationHandle::new();
+            current_refresh.replace(Some(cancel.clone()));
+
+            cancel_button.set_visible(true);
+            refresh_button.set_sensitive(false);
+            status_label.set_text("Refreshing…");
 
-                        if outcome.warnings.is_empty() {
-                            warning_label.set_text("");
-                            warning_label.set_visible(false);
-                        } else {
-                            let joined = outcome
-                                .warnings
-                                .iter()
-                                .map(|failure| format!("{}: {}", failure.plugin, failure.kind))
-                                .collect::<Vec<_>>()
-                                .join("\n");
-                            warning_label.set_text(&joined);
-                            warning_label.set_visible(true);
+            // Rows follow `StoreEvent`s; this only reports progress and errors.
+            let mut events = service.refresh_popular_stream(&cancel);
+            glib::MainContext::default().spawn_local(clone!(@weak service, @weak status_label, @weak cancel_button, @weak refresh_button, @strong app_list, @strong notices, @strong current_refresh => async move {
+                let mut finished = false;
+
+                while let Some(event) = events.next().await {
+                    match event {
+                        Ok(RefreshEvent::Apps(_)) => {
+                            status_label.set_text(&format!(
+                                "Refreshing… {} applications",
+                                app_list.len()
+                            ));
+                        }
+                        Ok(RefreshEvent::Warning(failure)) => notices.warning(&failure),
+                        Ok(RefreshEvent::Finished(_)) => finished = true,
+                        Err(HostError::Cancelled) => {
+                            status_label.set_text("Refresh cancelled");
+                            finished = true;
+                        }
+                        Err(err) => {
+                            notices.error(err);
+                            status_label.set_text("Refresh failed");
+                            finished = true;
                         }
-                    }
-                    Err(err) => {
-                        warning_label.set_text(&err);
-                        warning_label.set_visible(true);
-                        status_label.set_text("Refresh failed");
                     }
                 }
-                ControlFlow::Continue
-            }
-        ),
-    );
+                if !finished {
+                    status_label.set_text("Refresh interrupted");
+                }
+
+                cancel_button.set_visible(false);
+                refresh_button.set_sensitive(true);
+                current_refresh.borrow_mut().take();
 
-    let trigger_refresh: Rc<dyn Fn()> =
-        Rc::new(clone!(@weak service, @strong sender, @weak spinner => move || {
-            spinner.set_visible(true);
-            spinner.start();
-            glib::MainContext::default().spawn_local(clone!(@weak service, @strong sender => async move {
-                let result = service.refresh_popular().await;
-                let _ = sender.send(result.map_err(format_host_error));
+                // Only schedule background refreshes once the startup refresh
+                // is out of the way; later calls are no-ops.
+                service.start_scheduler();
             }));
-        }));
+        }),
+    );
 
+    let diagnostics_page = diagnostics::DiagnosticsPage::new(service.clone(), trigger_refresh.clone());
+    view_stack.add_titled_with_icon(
+        diagnostics_page.widget(),
+        Some("diagnostics"),
+        "Diagnostics",
+        "utilities-system-monitor-symbolic",
+    );
+    view_stack.connect_visible_child_name_notify(clone!(@strong diagnostics_page => move |stack| {
+        if stack.visible_child_name().as_deref() == Some("diagnostics") {
+            diagnostics_page.reload();
+        }
+    }));
+    let show_diagnostics = gio::SimpleAction::new("show-diagnostics", None);
+    show_diagnostics.connect_activate(clone!(@weak navigation, @weak root_page, @weak search_bar, @weak view_stack => move |_, _| {
+        navigation.pop_to_page(&root_page);
+        search_bar.set_search_mode(false);
+        view_stack.set_visible_child_name("diagnostics");
+    }));
+    window.add_action(&show_diagnostics);
+
+    // Keeps quarantine countdowns and the banner current.
+    glib::timeout_add_seconds_local(
+        5,
+        clone!(@weak service, @weak view_stack, @strong diagnostics_page, @strong notices => @default-return glib::ControlFlow::Break, move || {
+            notices.update_banner(&service.plugin_health());
+            if view_stack.visible_child_name().as_deref() == Some("diagnostics") {
+                diagnostics_page.reload();
+            }
+            glib::ControlFlow::Continue
+        }),
+    );
+
+    preferences_button.connect_clicked(clone!(@weak app, @weak window, @weak service => move |_| {
+        preferences::show(&window, &app, service);
+    }));
     refresh_button.connect_clicked(clone!(@strong trigger_refresh => move |_| trigger_refresh()));
+    cancel_button.connect_clicked(clone!(@strong current_refresh => move |_| {
+        if let Some(cancel) = current_refresh.borrow().as_ref() {
+            cancel.cancel();
+        }
+    }));
 
+    // Subscribe before reading the snapshot so no change falls in between.
+    let mut store_events = service.subscribe();
     let initial = service.cache_snapshot();
-    rebuild_list(&list_box, &initial.apps);
-    if initial.warnings.is_empty() {
-        warning_label.set_visible(false);
-    } else {
-        warning_label.set_visible(true);
-        warning_label.set_text(
-            &initial
-                .warnings
-                .iter()
-                .map(|failure| format!("{}: {}", failure.plugin, failure.kind))
-                .collect::<Vec<_>>()
-                .join("\n"),
-        );
-    }
+    app_list.upsert(&initial.apps);
+    notices.set_warnings(&initial.warnings);
     status_label.set_text(&format!(
-        "{} applications cached ({} plugins)",
+        "{} applications cached ({})",
         initial.apps.len(),
-        service.plugin_count()
+        describe_plugins(&service)
     ));
+    updated_label.set_text(&describe_age(initial.cache_age));
+
+    glib::timeout_add_seconds_local(
+        60,
+        clone!(@weak service, @weak updated_label => @default-return glib::ControlFlow::Break, move || {
+            updated_label.set_text(&describe_age(service.cache_age()));
+            glib::ControlFlow::Continue
+        }),
+    );
+
+    // Manual and scheduled refreshes may overlap.
+    let refreshes_running = Rc::new(Cell::new(0u32));
+    glib::MainContext::default().spawn_local(clone!(@weak app, @weak service, @weak status_label, @weak updated_label, @weak spinner, @weak updates_stack_page, @strong app_list, @strong updates_page, @strong diagnostics_page, @strong notices, @strong refreshes_running => async move {
+        loop {
+            match store_events.recv().await {
+                Ok(StoreEvent::RefreshStarted) => {
+                    refreshes_running.set(refreshes_running.get() + 1);
+                    spinner.set_visible(true);
+                    spinner.start();
+                }
+                Ok(StoreEvent::AppsAdded(apps)) | Ok(StoreEvent::AppsChanged(apps)) => {
+                    app_list.upsert(&apps);
+                }
+                Ok(StoreEvent::AppsRemoved(app_ids)) => {
+                    app_list.remove(&app_ids);
+                }
+                Ok(StoreEvent::WarningsChanged(warnings)) => {
+                    notices.set_warnings(&warnings);
+                }
+                Ok(StoreEvent::RepositoriesChanged(changes)) => {
+                    for change in changes {
+                        let repository = RepositoryObject::new(change.repository());
+                        app.emit_by_name::<()>("repository-changed", &[&repository]);
+                    }
+                }
+                Ok(StoreEvent::UpdatesChanged(updates)) => {
+                    updates_page.show(&updates);
+                    updates_stack_page.set_badge_number(updates.len() as u32);
+                }
+                Ok(StoreEvent::RefreshFinished(result)) => {
+                    diagnostics_page.reload();
+                    notices.update_banner(&service.plugin_health());
+                    refreshes_running.set(refreshes_running.get().saturating_sub(1));
+                    if refreshes_running.get() == 0 {
+                        spinner.stop();
+                        spinner.set_visible(false);
+                    }
+                    if result.is_ok() {
+                        status_label.set_text(&format!(
+                            "{} applications ({})",
+                            app_list.len(),
+                            describe_plugins(&service)
+                        ));
+                        updated_label.set_text(&describe_age(service.cache_age()));
+                    }
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {
+                    let snapshot = service.cache_snapshot();
+                    app_list.upsert(&snapshot.apps);
+                    app_list.retain(&snapshot.apps);
+                    notices.set_warnings(&snapshot.warnings);
+                    let updates = service.update_snapshot().updates;
+                    updates_page.show(&updates);
+                    updates_stack_page.set_badge_number(updates.len() as u32);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }));
+
+    // Apps of a repository that was added, removed or toggled only show up
+    // after another refresh. Changes found together cause a single one, and
+    // changes a refresh found itself cause none.
+    let repository_refresh_queued = Rc::new(Cell::new(false));
+    app.connect_local("repository-changed", false, clone!(@strong trigger_refresh, @strong refreshes_running, @strong repository_refresh_queued => @default-return None, move |_| {
+        if refreshes_running.get() > 0 || repository_refresh_queued.replace(true) {
+            return None;
+        }
+        glib::MainContext::default().spawn_local(clone!(@strong trigger_refresh, @strong refreshes_running, @strong repository_refresh_queued => async move {
+            repository_refresh_queued.set(false);
+            if refreshes_running.get() == 0 {
+                trigger_refresh();
+            }
+        }));
+        None
+    }));
 
-    trigger_refresh();
+    let mut scheduler_events = service.scheduler().subscribe();
+    glib::MainContext::default().spawn_local(clone!(@strong notices => async move {
+        loop {
+            match scheduler_events.recv().await {
+                Ok(SchedulerEvent::RefreshFailed(err)) => notices.error(err),
+                Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }));
+
+    // Background refreshes wait while offline or on a metered connection.
+    let monitor = gio::NetworkMonitor::default();
+    let update_pause = clone!(@weak service => move |monitor: &gio::NetworkMonitor| {
+        if monitor.is_network_available() && !monitor.is_network_metered() {
+            service.scheduler().resume();
+        } else {
+            service.scheduler().pause();
+        }
+    });
+    update_pause(&monitor);
+    monitor.connect_network_changed(clone!(@strong update_pause => move |monitor, _| update_pause(monitor)));
+    monitor.connect_network_metered_notify(move |monitor| update_pause(monitor));
+
+    // Stale-while-revalidate: the cached list is already on screen, so only
+    // go to the backends when it is too old.
+    if service.is_stale() {
+        trigger_refresh();
+    } else {
+        service.start_scheduler();
+    }
 
     window.present();
 }
 
-fn rebuild_list(list_box: &gtk::ListBox, apps: &[crate::plugins::AppSummary]) {
-    while let Some(child) = list_box.first_child() {
-        list_box.remove(&child);
+fn describe_age(age: Option<Duration>) -> String {
+    let Some(age) = age else {
+        return "Never updated".to_string();
+    };
+    let minutes = age.as_secs() / 60;
+    match minutes {
+        0 => "Updated just now".to_string(),
+        1 => "Updated 1 minute ago".to_string(),
+        2..=59 => format!("Updated {minutes} minutes ago"),
+        60..=119 => "Updated 1 hour ago".to_string(),
+        120..=1439 => format!("Updated {} hours ago", minutes / 60),
+        1440..=2879 => "Updated 1 day ago".to_string(),
+        _ => format!("Updated {} days ago", minutes / 1440),
     }
+}
+
+fn describe_plugins(service: &AppStoreService) -> String {
+    let health = service.plugin_health();
+    let quarantined = health
+        .iter()
+        .filter(|entry| entry.state == HealthState::Quarantined)
+        .count();
+    let degraded = health
+        .iter()
+        .filter(|entry| matches!(entry.state, HealthState::Degraded | HealthState::HalfOpen))
+        .count();
+    let disabled = health
+        .iter()
+        .filter(|entry| entry.state == HealthState::Disabled)
+        .count();
 
-    for app in apps {
-        let row = gtk::ListBoxRow::new();
-        let box_container = gtk::Box::new(gtk::Orientation::Vertical, 6);
-        let title = gtk::Label::new(Some(&app.name));
-        title.set_xalign(0.0);
-        title.add_css_class("title-3");
-        let subtitle = gtk::Label::new(Some(&format!("{} • {}", app.summary, app.source)));
-        subtitle.set_wrap(true);
-        subtitle.set_xalign(0.0);
-        subtitle.add_css_class("dim-label");
-
-        box_container.append(&title);
-        box_container.append(&subtitle);
-        row.set_child(Some(&box_container));
-        list_box.append(&row);
+    let mut text = format!("{} plugins", health.len());
+    if degraded > 0 {
+        text.push_str(&format!(", {degraded} degraded"));
     }
+    if quarantined > 0 {
+        text.push_str(&format!(", {quarantined} quarantined"));
+    }
+    if disabled > 0 {
+        text.push_str(&format!(", {disabled} disabled"));
+    }
+    text
 }
 
 fn format_host_error(err: HostError) -> String {
@@ -184,11 +467,24 @@ fn format_host_error(err: HostError) -> String {
             } else {
                 failures
                     .iter()
-                    .map(|failure| format!("{}: {}", failure.plugin, failure.kind))
+                    .map(|failure| notices::describe_failure(&failure.plugin, &failure.kind).title)
                     .collect::<Vec<_>>()
                     .join("\n")
             }
         }
         HostError::RuntimeUnavailable => "Background runtime unavailable".to_string(),
+        HostError::Cancelled => "Refresh cancelled".to_string(),
+        HostError::UnknownApp(app_id) => format!("Unknown application {app_id}"),
+        HostError::UnknownPlugin(plugin) => format!("Unknown plugin {plugin}"),
+        HostError::UnknownRepository(id) => format!("Unknown repository {id}"),
+        HostError::Unsupported => "No plugin supports this operation".to_string(),
+        HostError::Plugin(failure) => {
+            let problem = notices::describe_failure(&failure.plugin, &failure.kind);
+            if problem.detail.is_empty() {
+                problem.title
+            } else {
+                format!("{}: {}", problem.title, problem.detail)
+            }
+        }
     }
 }
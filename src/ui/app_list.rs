@@ -0,0 +1,281 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use adw::prelude::*;
+use gtk4 as gtk;
+use gtk::{gio, glib};
+use libadwaita as adw;
+
+use crate::app_object::AppObject;
+use crate::plugins::MergedApp;
+
+const SORT_POPULARITY: u32 = 0;
+const SORT_NAME: u32 = 1;
+
+/// Apps backed by a `gio::ListStore`, filterable by source and sortable by
+/// popularity or name.
+#[derive(Clone)]
+pub(super) struct AppList {
+    root: gtk::Box,
+    store: gio::ListStore,
+    /// Objects in `store`, by app id.
+    objects: Rc<RefCell<HashMap<String, AppObject>>>,
+    /// "All Sources" followed by every plugin seen so far.
+    sources: gtk::StringList,
+    /// Popularity given to the next new app.
+    next_popularity: Rc<Cell<u32>>,
+}
+
+impl AppList {
+    /// Activating an app calls `open_app`.
+    pub(super) fn new(open_app: Rc<dyn Fn(MergedApp)>) -> Self {
+        let store = gio::ListStore::new::<AppObject>();
+
+        let sources = gtk::StringList::new(&["All Sources"]);
+        let source_dropdown = gtk::DropDown::new(Some(sources.clone()), gtk::Expression::NONE);
+        source_dropdown.set_tooltip_text(Some("Show apps from one source"));
+        let sort_dropdown = gtk::DropDown::from_strings(&["Popularity", "Name"]);
+        sort_dropdown.set_tooltip_text(Some("Sort order"));
+
+        let plugin: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+        let filter = gtk::CustomFilter::new(glib::clone!(@strong plugin => move |object| {
+            let Some(app) = object.downcast_ref::<AppObject>() else {
+                return false;
+            };
+            plugin
+                .borrow()
+                .as_deref()
+                .is_none_or(|plugin| app.has_plugin(plugin))
+        }));
+        let sort = Rc::new(Cell::new(SORT_POPULARITY));
+        let sorter = gtk::CustomSorter::new(glib::clone!(@strong sort => move |left, right| {
+            let (Some(left), Some(right)) = (
+                left.downcast_ref::<AppObject>(),
+                right.downcast_ref::<AppObject>(),
+            ) else {
+                return gtk::Ordering::Equal;
+            };
+            let order = match sort.get() {
+                SORT_NAME => left
+                    .name()
+                    .to_lowercase()
+                    .cmp(&right.name().to_lowercase())
+                    .then_with(|| left.popularity().cmp(&right.popularity())),
+                _ => left.popularity().cmp(&right.popularity()),
+            };
+            order.into()
+        }));
+
+        source_dropdown.connect_selected_notify(glib::clone!(@strong plugin, @weak filter => move |dropdown| {
+            // Entry 0 is "All Sources".
+            let selected = (dropdown.selected() > 0)
+                .then(|| dropdown.selected_item().and_downcast::<gtk::StringObject>())
+                .flatten()
+                .map(|item| item.string().to_string());
+            plugin.replace(selected);
+            filter.changed(gtk::FilterChange::Different);
+        }));
+        sort_dropdown.connect_selected_notify(glib::clone!(@strong sort, @weak sorter => move |dropdown| {
+            sort.set(dropdown.selected());
+            sorter.changed(gtk::SorterChange::Different);
+        }));
+
+        let filtered = gtk::FilterListModel::new(Some(store.clone()), Some(filter));
+        let sorted = gtk::SortListModel::new(Some(filtered), Some(sorter));
+        let selection = gtk::NoSelection::new(Some(sorted));
+
+        let factory = gtk::SignalListItemFactory::new();
+        factory.connect_setup(|_, item| {
+            let Some(item) = item.downcast_ref::<gtk::ListItem>() else {
+                return;
+            };
+            item.set_child(Some(&row_content()));
+        });
+        factory.connect_bind(|_, item| {
+            let Some(item) = item.downcast_ref::<gtk::ListItem>() else {
+                return;
+            };
+            if let (Some(app), Some(content)) = (
+                item.item().and_downcast::<AppObject>(),
+                item.child().and_downcast::<gtk::Box>(),
+            ) {
+                bind_row_content(&content, &app.app());
+            }
+        });
+
+        let list_view = gtk::ListView::new(Some(selection), Some(factory));
+        list_view.set_single_click_activate(true);
+        list_view.add_css_class("rich-list");
+        list_view.connect_activate(move |view, position| {
+            let app = view
+                .model()
+                .and_then(|model| model.item(position))
+                .and_downcast::<AppObject>();
+            if let Some(app) = app {
+                open_app(app.app());
+            }
+        });
+
+        let toolbar = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        toolbar.set_halign(gtk::Align::End);
+        toolbar.append(&source_dropdown);
+        toolbar.append(&sort_dropdown);
+
+        let root = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        root.append(&toolbar);
+        root.append(
+            &gtk::ScrolledWindow::builder()
+                .child(&list_view)
+                .hscrollbar_policy(gtk::PolicyType::Never)
+                .vexpand(true)
+                .build(),
+        );
+
+        Self {
+            root,
+            store,
+            objects: Rc::new(RefCell::new(HashMap::new())),
+            sources,
+            next_popularity: Rc::new(Cell::new(0)),
+        }
+    }
+
+    pub(super) fn widget(&self) -> &gtk::Box {
+        &self.root
+    }
+
+    /// Number of apps, whatever the filter.
+    pub(super) fn len(&self) -> usize {
+        self.objects.borrow().len()
+    }
+
+    /// Replaces the objects of apps already listed, keeping their
+    /// popularity, and appends the others as the least popular so far.
+    pub(super) fn upsert(&self, apps: &[MergedApp]) {
+        let mut objects = self.objects.borrow_mut();
+        for app in apps {
+            match objects.get(app.app_id()) {
+                Some(old) => {
+                    let object = AppObject::new(app, old.popularity());
+                    if let Some(position) = self.store.find(old) {
+                        self.store.splice(position, 1, std::slice::from_ref(&object));
+                    }
+                    objects.insert(app.app_id().to_string(), object);
+                }
+                None => {
+                    let popularity = self.next_popularity.get();
+                    self.next_popularity.set(popularity + 1);
+                    let object = AppObject::new(app, popularity);
+                    self.store.append(&object);
+                    objects.insert(app.app_id().to_string(), object);
+                }
+            }
+            self.add_sources(app);
+        }
+    }
+
+    pub(super) fn remove(&self, app_ids: &[String]) {
+        let mut objects = self.objects.borrow_mut();
+        for app_id in app_ids {
+            if let Some(position) = objects
+                .remove(app_id)
+                .and_then(|object| self.store.find(&object))
+            {
+                self.store.remove(position);
+            }
+        }
+    }
+
+    /// Removes the apps missing from `apps`.
+    pub(super) fn retain(&self, apps: &[MergedApp]) {
+        let keep = apps.iter().map(MergedApp::app_id).collect::<HashSet<_>>();
+        let gone = self
+            .objects
+            .borrow()
+            .keys()
+            .filter(|app_id| !keep.contains(app_id.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+        self.remove(&gone);
+    }
+
+    /// Lists exactly `apps`, most popular first.
+    pub(super) fn replace(&self, apps: &[MergedApp]) {
+        self.objects.borrow_mut().clear();
+        self.store.remove_all();
+        self.next_popularity.set(0);
+        self.upsert(apps);
+    }
+
+    fn add_sources(&self, app: &MergedApp) {
+        let known = (1..self.sources.n_items())
+            .filter_map(|index| self.sources.string(index))
+            .map(|plugin| plugin.to_string())
+            .collect::<HashSet<_>>();
+        let mut added = HashSet::new();
+        for source in &app.sources {
+            if !known.contains(&source.plugin) && added.insert(source.plugin.as_str()) {
+                self.sources.append(&source.plugin);
+            }
+        }
+    }
+}
+
+fn row_content() -> gtk::Box {
+    let title = gtk::Label::new(None);
+    title.set_xalign(0.0);
+    title.add_css_class("title-3");
+    let installed = gtk::Label::new(Some("Installed"));
+    installed.add_css_class("caption");
+    installed.add_css_class("success");
+    let heading = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+    heading.append(&title);
+    heading.append(&installed);
+
+    let subtitle = gtk::Label::new(None);
+    subtitle.set_wrap(true);
+    subtitle.set_xalign(0.0);
+    subtitle.add_css_class("dim-label");
+
+    let content = gtk::Box::new(gtk::Orientation::Vertical, 6);
+    content.append(&heading);
+    content.append(&subtitle);
+    content
+}
+
+/// Fills a box made by [`row_content`].
+fn bind_row_content(content: &gtk::Box, app: &MergedApp) {
+    let heading = content.first_child().and_downcast::<gtk::Box>();
+    let title = heading
+        .as_ref()
+        .and_then(|heading| heading.first_child())
+        .and_downcast::<gtk::Label>();
+    let installed = title.as_ref().and_then(|title| title.next_sibling());
+    let subtitle = heading
+        .as_ref()
+        .and_then(|heading| heading.next_sibling())
+        .and_downcast::<gtk::Label>();
+
+    if let Some(title) = title {
+        title.set_text(&app.primary.name);
+    }
+    if let Some(installed) = installed {
+        installed.set_visible(app.primary.installed);
+    }
+    if let Some(subtitle) = subtitle {
+        subtitle.set_text(&format!(
+            "{} • {}",
+            app.primary.summary,
+            describe_sources(app)
+        ));
+    }
+}
+
+fn describe_sources(app: &MergedApp) -> String {
+    app.sources
+        .iter()
+        .map(|source| source.source.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
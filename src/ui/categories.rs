@@ -0,0 +1,213 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use adw::prelude::*;
+use gtk4 as gtk;
+use gtk::glib;
+use libadwaita as adw;
+
+use crate::cancellation::CancellationHandle;
+use crate::host::{AppStoreService, HostError};
+use crate::plugins::{Category, MergedApp};
+
+use super::app_list::AppList;
+use super::format_host_error;
+use super::notices::Notices;
+
+/// Category sidebar of the Explore view: popular apps or one category's apps.
+#[derive(Clone)]
+pub(super) struct CategoryBrowser {
+    root: gtk::Box,
+    sidebar: gtk::ListBox,
+    stack: gtk::Stack,
+    status_label: gtk::Label,
+    app_list: AppList,
+    notices: Notices,
+    /// Categories in sidebar order, after the "Popular" row.
+    categories: Rc<RefCell<Vec<Category>>>,
+    /// Set while a category's apps load; selecting another cancels it.
+    loading: Rc<RefCell<Option<CancellationHandle>>>,
+    service: Rc<AppStoreService>,
+}
+
+impl CategoryBrowser {
+    /// Wraps `popular`, shown while the "Popular" row is selected.
+    /// Activating an app of a category calls `open_app`.
+    pub(super) fn new(
+        service: Rc<AppStoreService>,
+        popular: &impl IsA<gtk::Widget>,
+        notices: Notices,
+        open_app: Rc<dyn Fn(MergedApp)>,
+    ) -> Self {
+        let sidebar = gtk::ListBox::new();
+        sidebar.add_css_class("navigation-sidebar");
+        sidebar.append(&sidebar_row("Popular", Some("starred-symbolic")));
+
+        let sidebar_scroll = gtk::ScrolledWindow::builder()
+            .child(&sidebar)
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .width_request(200)
+            .build();
+
+        let status_label = gtk::Label::new(None);
+        status_label.set_halign(gtk::Align::Start);
+
+        let app_list = AppList::new(open_app);
+
+        let category_page = gtk::Box::new(gtk::Orientation::Vertical, 12);
+        category_page.set_margin_top(12);
+        category_page.set_margin_bottom(12);
+        category_page.set_margin_start(12);
+        category_page.set_margin_end(12);
+        category_page.append(&status_label);
+        category_page.append(app_list.widget());
+
+        let stack = gtk::Stack::new();
+        stack.set_hexpand(true);
+        stack.add_named(popular, Some("popular"));
+        stack.add_named(&category_page, Some("category"));
+
+        let root = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        root.append(&sidebar_scroll);
+        root.append(&gtk::Separator::new(gtk::Orientation::Vertical));
+        root.append(&stack);
+
+        let browser = Self {
+            root,
+            sidebar,
+            stack,
+            status_label,
+            app_list,
+            notices,
+            categories: Rc::new(RefCell::new(Vec::new())),
+            loading: Rc::new(RefCell::new(None)),
+            service,
+        };
+
+        let this = browser.clone();
+        browser.sidebar.connect_row_selected(move |_, row| {
+            let Some(index) = row.map(|row| row.index()) else {
+                return;
+            };
+            let category = usize::try_from(index - 1)
+                .ok()
+                .and_then(|index| this.categories.borrow().get(index).cloned());
+            match category {
+                Some(category) => this.show_category(category),
+                None => {
+                    this.cancel_loading();
+                    this.stack.set_visible_child_name("popular");
+                }
+            }
+        });
+        browser
+            .sidebar
+            .select_row(browser.sidebar.row_at_index(0).as_ref());
+
+        browser.reload();
+        browser
+    }
+
+    pub(super) fn widget(&self) -> &gtk::Box {
+        &self.root
+    }
+
+    /// Lists categories again, served from the service while fresh.
+    pub(super) fn reload(&self) {
+        let this = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            match this.service.categories(&CancellationHandle::new()).await {
+                Ok(listing) => this.show_categories(listing.categories),
+                Err(HostError::Cancelled) => {}
+                Err(err) => eprintln!(
+                    "InstallGrid: failed to list categories: {}",
+                    format_host_error(err)
+                ),
+            }
+        });
+    }
+
+    fn show_categories(&self, categories: Vec<Category>) {
+        // Removing the selected row selects nothing, which keeps the page.
+        let selected = self.selected_category_id();
+        while let Some(row) = self.sidebar.row_at_index(1) {
+            self.sidebar.remove(&row);
+        }
+        for category in &categories {
+            self.sidebar
+                .append(&sidebar_row(&category.name, category.icon.as_deref()));
+        }
+
+        let index = selected
+            .and_then(|id| categories.iter().position(|category| category.id == id))
+            .map_or(0, |index| index as i32 + 1);
+        *self.categories.borrow_mut() = categories;
+        self.sidebar
+            .select_row(self.sidebar.row_at_index(index).as_ref());
+    }
+
+    fn selected_category_id(&self) -> Option<String> {
+        let index = self.sidebar.selected_row()?.index();
+        let index = usize::try_from(index - 1).ok()?;
+        self.categories
+            .borrow()
+            .get(index)
+            .map(|category| category.id.clone())
+    }
+
+    fn show_category(&self, category: Category) {
+        self.cancel_loading();
+        let cancel = CancellationHandle::new();
+        self.loading.replace(Some(cancel.clone()));
+
+        self.app_list.replace(&[]);
+        self.status_label
+            .set_text(&format!("Loading {}…", category.name));
+        self.stack.set_visible_child_name("category");
+
+        let this = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let result = this.service.category_apps(&category.id, &cancel).await;
+            if cancel.is_cancelled() {
+                return;
+            }
+            this.loading.borrow_mut().take();
+            match result {
+                Ok(listing) => {
+                    this.app_list.replace(&listing.apps);
+                    this.status_label.set_text(&format!(
+                        "{} · {} applications",
+                        category.name,
+                        listing.apps.len()
+                    ));
+                    this.notices.warnings(&listing.warnings);
+                }
+                Err(err) => {
+                    this.status_label
+                        .set_text(&format!("Could not load {}", category.name));
+                    this.notices.error(err);
+                }
+            }
+        });
+    }
+
+    fn cancel_loading(&self) {
+        if let Some(cancel) = self.loading.borrow_mut().take() {
+            cancel.cancel();
+        }
+    }
+}
+
+fn sidebar_row(name: &str, icon: Option<&str>) -> gtk::ListBoxRow {
+    let content = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+    content.append(&gtk::Image::from_icon_name(
+        icon.unwrap_or("applications-other-symbolic"),
+    ));
+    let label = gtk::Label::new(Some(name));
+    label.set_xalign(0.0);
+    content.append(&label);
+
+    let row = gtk::ListBoxRow::new();
+    row.set_child(Some(&content));
+    row
+}
@@ -0,0 +1,402 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use adw::prelude::*;
+use futures::StreamExt;
+use gtk4 as gtk;
+use gtk::{gio, glib};
+use libadwaita as adw;
+
+use crate::cancellation::CancellationHandle;
+use crate::host::{AppStoreService, HostError};
+use crate::plugins::{
+    AppDetails, AppSource, InstallState, JobProgress, MergedApp, PluginExecutionError,
+    PluginFailureKind,
+};
+
+use super::format_host_error;
+
+const ICON_SIZE: i32 = 96;
+const SCREENSHOT_HEIGHT: i32 = 320;
+
+#[derive(Clone)]
+struct DetailsPage {
+    app: Rc<MergedApp>,
+    icon: gtk::Image,
+    developer_label: gtk::Label,
+    source_dropdown: gtk::DropDown,
+    action_button: gtk::Button,
+    open_button: gtk::Button,
+    progress: gtk::ProgressBar,
+    error_label: gtk::Label,
+    loading: gtk::Spinner,
+    screenshots: adw::Carousel,
+    screenshot_box: gtk::Box,
+    description_label: gtk::Label,
+    info: adw::Bin,
+    installed: Rc<Cell<bool>>,
+    /// Set while an install or removal runs; clicking the button cancels it.
+    running: Rc<RefCell<Option<CancellationHandle>>>,
+    service: Rc<AppStoreService>,
+}
+
+/// Pushes a page for `app` onto `navigation`. Cached details are shown at
+/// once; full details are fetched in the background.
+pub(super) fn show(navigation: &adw::NavigationView, service: Rc<AppStoreService>, app: MergedApp) {
+    let page = DetailsPage::new(service, app);
+    let navigation_page = page.build();
+    navigation.push(&navigation_page);
+    page.load(&navigation_page);
+}
+
+impl DetailsPage {
+    fn new(service: Rc<AppStoreService>, app: MergedApp) -> Self {
+        let sources = app
+            .sources
+            .iter()
+            .map(|source| format!("{} ({})", source.source, source.plugin))
+            .collect::<Vec<_>>();
+        let source_dropdown =
+            gtk::DropDown::from_strings(&sources.iter().map(String::as_str).collect::<Vec<_>>());
+        source_dropdown.set_tooltip_text(Some("Source"));
+        source_dropdown.set_visible(sources.len() > 1);
+
+        let developer_label = gtk::Label::new(None);
+        developer_label.set_halign(gtk::Align::Start);
+        developer_label.add_css_class("dim-label");
+        developer_label.set_visible(false);
+
+        let error_label = gtk::Label::new(None);
+        error_label.set_halign(gtk::Align::Start);
+        error_label.set_wrap(true);
+        error_label.add_css_class("error");
+        error_label.set_visible(false);
+
+        let description_label = gtk::Label::new(None);
+        description_label.set_halign(gtk::Align::Start);
+        description_label.set_xalign(0.0);
+        description_label.set_wrap(true);
+        description_label.set_selectable(true);
+
+        let screenshots = adw::Carousel::new();
+        screenshots.set_height_request(SCREENSHOT_HEIGHT);
+        let dots = adw::CarouselIndicatorDots::new();
+        dots.set_carousel(Some(&screenshots));
+        let screenshot_box = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        screenshot_box.append(&screenshots);
+        screenshot_box.append(&dots);
+        screenshot_box.set_visible(false);
+
+        let progress = gtk::ProgressBar::new();
+        progress.set_visible(false);
+
+        let installed = app.primary.installed;
+        Self {
+            app: Rc::new(app),
+            icon: gtk::Image::from_icon_name("application-x-executable"),
+            developer_label,
+            source_dropdown,
+            action_button: gtk::Button::new(),
+            open_button: gtk::Button::with_label("Open"),
+            progress,
+            error_label,
+            loading: gtk::Spinner::new(),
+            screenshots,
+            screenshot_box,
+            description_label,
+            info: adw::Bin::new(),
+            installed: Rc::new(Cell::new(installed)),
+            running: Rc::new(RefCell::new(None)),
+            service,
+        }
+    }
+
+    fn build(&self) -> adw::NavigationPage {
+        self.icon.set_pixel_size(ICON_SIZE);
+
+        let name_label = gtk::Label::new(Some(&self.app.primary.name));
+        name_label.set_halign(gtk::Align::Start);
+        name_label.set_wrap(true);
+        name_label.add_css_class("title-1");
+        let summary_label = gtk::Label::new(Some(&self.app.primary.summary));
+        summary_label.set_halign(gtk::Align::Start);
+        summary_label.set_wrap(true);
+
+        let titles = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        titles.set_valign(gtk::Align::Center);
+        titles.append(&name_label);
+        titles.append(&summary_label);
+        titles.append(&self.developer_label);
+
+        let hero = gtk::Box::new(gtk::Orientation::Horizontal, 18);
+        hero.append(&self.icon);
+        hero.append(&titles);
+
+        self.action_button.set_valign(gtk::Align::Center);
+        self.open_button.set_valign(gtk::Align::Center);
+        self.source_dropdown.set_valign(gtk::Align::Center);
+        let actions = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        actions.append(&self.action_button);
+        actions.append(&self.open_button);
+        actions.append(&self.source_dropdown);
+        actions.append(&self.loading);
+
+        let content = gtk::Box::new(gtk::Orientation::Vertical, 18);
+        content.set_margin_top(24);
+        content.set_margin_bottom(24);
+        content.set_margin_start(12);
+        content.set_margin_end(12);
+        content.append(&hero);
+        content.append(&actions);
+        content.append(&self.progress);
+        content.append(&self.error_label);
+        content.append(&self.screenshot_box);
+        content.append(&self.description_label);
+        content.append(&self.info);
+
+        let clamp = adw::Clamp::builder().maximum_size(800).child(&content).build();
+        let scrolled = gtk::ScrolledWindow::builder()
+            .child(&clamp)
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .vexpand(true)
+            .build();
+
+        let root = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        root.append(&adw::HeaderBar::new());
+        root.append(&scrolled);
+
+        self.update_actions();
+        adw::NavigationPage::new(&root, &self.app.primary.name)
+    }
+
+    /// Shows whatever the store has for the app, then fetches full details
+    /// until `page` is left. Leaving does not stop a running job.
+    fn load(&self, page: &adw::NavigationPage) {
+        if let Some(details) = self.service.cached_details(self.app.app_id()) {
+            self.fill(&details);
+        }
+
+        // The handlers hold the page's widgets, so they go once it is popped.
+        let this = self.clone();
+        let action = self.action_button.connect_clicked(move |_| this.toggle_job());
+        let this = self.clone();
+        let open = self
+            .open_button
+            .connect_clicked(move |button| this.launch(button));
+        let handlers = RefCell::new(vec![
+            (self.action_button.clone(), action),
+            (self.open_button.clone(), open),
+        ]);
+
+        let cancel = CancellationHandle::new();
+        page.connect_hidden(glib::clone!(@strong cancel => move |_| {
+            cancel.cancel();
+            for (button, handler) in handlers.take() {
+                button.disconnect(handler);
+            }
+        }));
+        let this = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            this.fetch(cancel).await;
+        });
+    }
+
+    async fn fetch(&self, cancel: CancellationHandle) {
+        self.loading.set_visible(true);
+        self.loading.start();
+        let result = self.service.app_details(self.app.app_id(), &cancel).await;
+        self.loading.stop();
+        self.loading.set_visible(false);
+        match result {
+            Ok(details) => self.fill(&details),
+            Err(HostError::Cancelled) => {}
+            Err(err) => self.show_error(&format_host_error(err)),
+        }
+    }
+
+    fn fill(&self, details: &AppDetails) {
+        if let Some(icon) = details
+            .icon
+            .as_deref()
+            .and_then(|icon| gio::Icon::for_string(icon).ok())
+        {
+            self.icon.set_from_gicon(&icon);
+        }
+
+        if let Some(developer) = &details.developer {
+            self.developer_label.set_text(developer);
+            self.developer_label.set_visible(true);
+        }
+
+        self.description_label
+            .set_text(details.description.as_deref().unwrap_or(&self.app.primary.summary));
+
+        while self.screenshots.n_pages() > 0 {
+            self.screenshots.remove(&self.screenshots.nth_page(0));
+        }
+        for url in &details.screenshots {
+            let picture = gtk::Picture::for_file(&screenshot_file(url));
+            picture.set_content_fit(gtk::ContentFit::Contain);
+            picture.set_hexpand(true);
+            self.screenshots.append(&picture);
+        }
+        self.screenshot_box
+            .set_visible(!details.screenshots.is_empty());
+
+        self.info.set_child(Some(&info_group(details)));
+
+        match details.install_state {
+            InstallState::Installed | InstallState::Updatable => self.installed.set(true),
+            InstallState::Available => self.installed.set(false),
+            InstallState::Unknown => {}
+        }
+        self.update_actions();
+    }
+
+    fn selected_source(&self) -> Option<&AppSource> {
+        self.app
+            .sources
+            .get(self.source_dropdown.selected() as usize)
+            .or_else(|| self.app.sources.first())
+    }
+
+    fn update_actions(&self) {
+        let running = self.running.borrow().is_some();
+        let installed = self.installed.get();
+        for class in ["suggested-action", "destructive-action"] {
+            self.action_button.remove_css_class(class);
+        }
+        if running {
+            self.action_button.set_label("Cancel");
+        } else if installed {
+            self.action_button.set_label("Remove");
+            self.action_button.add_css_class("destructive-action");
+        } else {
+            self.action_button.set_label("Install");
+            self.action_button.add_css_class("suggested-action");
+        }
+        self.source_dropdown.set_sensitive(!running);
+        self.open_button
+            .set_visible(installed && !running && desktop_app_info(self.app.app_id()).is_some());
+    }
+
+    fn toggle_job(&self) {
+        if let Some(cancel) = self.running.borrow().as_ref() {
+            cancel.cancel();
+            return;
+        }
+        let Some(source) = self.selected_source() else {
+            return;
+        };
+
+        let cancel = CancellationHandle::new();
+        let installing = !self.installed.get();
+        let result = if installing {
+            self.service.install(self.app.app_id(), source, &cancel)
+        } else {
+            self.service.remove(self.app.app_id(), source, &cancel)
+        };
+        let mut events = match result {
+            Ok(events) => events,
+            Err(err) => {
+                self.show_error(&format_host_error(err));
+                return;
+            }
+        };
+
+        self.running.replace(Some(cancel));
+        self.error_label.set_visible(false);
+        self.progress.set_fraction(0.0);
+        self.progress.set_visible(true);
+        self.update_actions();
+
+        let this = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            while let Some(item) = events.next().await {
+                match item {
+                    Ok(JobProgress::Queued) => this.progress.set_fraction(0.0),
+                    Ok(JobProgress::Running { percent: Some(percent) }) => {
+                        this.progress.set_fraction(f64::from(percent) / 100.0)
+                    }
+                    Ok(JobProgress::Running { percent: None }) => this.progress.pulse(),
+                    Ok(JobProgress::Finished) => this.installed.set(installing),
+                    Err(failure) => {
+                        if !matches!(
+                            failure.kind,
+                            PluginFailureKind::Execution(PluginExecutionError::Cancelled)
+                        ) {
+                            this.show_error(&format!("{}: {}", failure.plugin, failure.kind));
+                        }
+                    }
+                }
+            }
+            this.running.borrow_mut().take();
+            this.progress.set_visible(false);
+            this.update_actions();
+        });
+    }
+
+    fn launch(&self, button: &gtk::Button) {
+        let Some(info) = desktop_app_info(self.app.app_id()) else {
+            return;
+        };
+        let context = button.display().app_launch_context();
+        if let Err(err) = info.launch(&[], Some(&context)) {
+            self.show_error(&format!("Could not open {}: {err}", self.app.primary.name));
+        }
+    }
+
+    fn show_error(&self, message: &str) {
+        self.error_label.set_text(message);
+        self.error_label.set_visible(true);
+    }
+}
+
+/// Version, license and the like, leaving out what the backend did not
+/// report.
+fn info_group(details: &AppDetails) -> adw::PreferencesGroup {
+    let group = adw::PreferencesGroup::builder().title("Information").build();
+    let size = |bytes: Option<u64>| bytes.map(|bytes| glib::format_size(bytes).to_string());
+    let rows = [
+        ("Version", details.version.clone()),
+        ("License", details.license.clone()),
+        ("Developer", details.developer.clone()),
+        ("Homepage", details.homepage.clone()),
+        ("Download Size", size(details.download_size)),
+        ("Installed Size", size(details.installed_size)),
+        ("Age Rating", details.content_rating.map(|age| format!("{age}+"))),
+        ("Source", Some(details.app.source.clone())),
+    ];
+    for (title, value) in rows {
+        let Some(value) = value else {
+            continue;
+        };
+        let row = adw::ActionRow::builder()
+            .title(title)
+            .subtitle(glib::markup_escape_text(&value).as_str())
+            .subtitle_selectable(true)
+            .build();
+        row.add_css_class("property");
+        group.add(&row);
+    }
+    group
+}
+
+fn screenshot_file(url: &str) -> gio::File {
+    if url.contains("://") {
+        gio::File::for_uri(url)
+    } else {
+        gio::File::for_path(url)
+    }
+}
+
+/// The installed desktop entry for `app_id`, which may or may not carry the
+/// `.desktop` suffix already.
+fn desktop_app_info(app_id: &str) -> Option<gio::DesktopAppInfo> {
+    let desktop_id = if app_id.ends_with(".desktop") {
+        app_id.to_string()
+    } else {
+        format!("{app_id}.desktop")
+    };
+    gio::DesktopAppInfo::new(&desktop_id)
+}
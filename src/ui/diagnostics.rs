@@ -0,0 +1,220 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+use std::time::Duration;
+
+use adw::prelude::*;
+use gtk4 as gtk;
+use gtk::glib;
+use libadwaita as adw;
+
+use crate::host::AppStoreService;
+use crate::plugins::PluginKind;
+use crate::registry::{HealthState, PluginHealth};
+
+use super::format_host_error;
+use super::notices::describe_failure;
+
+/// Health of every plugin, with retry and disable.
+#[derive(Clone)]
+pub(super) struct DiagnosticsPage {
+    root: adw::PreferencesPage,
+    group: adw::PreferencesGroup,
+    rows: Rc<RefCell<Vec<(String, adw::ExpanderRow)>>>,
+    /// Called after a plugin is retried or enabled, to call it again.
+    on_retry: Rc<dyn Fn()>,
+    service: Rc<AppStoreService>,
+}
+
+impl DiagnosticsPage {
+    pub(super) fn new(service: Rc<AppStoreService>, on_retry: Rc<dyn Fn()>) -> Self {
+        let group = adw::PreferencesGroup::builder()
+            .title("Plugins")
+            .description("Backends answering the application list")
+            .build();
+        let root = adw::PreferencesPage::new();
+        root.add(&group);
+
+        let page = Self {
+            root,
+            group,
+            rows: Rc::new(RefCell::new(Vec::new())),
+            on_retry,
+            service,
+        };
+        page.reload();
+        page
+    }
+
+    pub(super) fn widget(&self) -> &adw::PreferencesPage {
+        &self.root
+    }
+
+    /// Rebuilds the rows from the host's current health, keeping expanded
+    /// rows expanded.
+    pub(super) fn reload(&self) {
+        let expanded = self
+            .rows
+            .borrow()
+            .iter()
+            .filter(|(_, row)| row.is_expanded())
+            .map(|(plugin, _)| plugin.clone())
+            .collect::<HashSet<_>>();
+        for (_, row) in self.rows.borrow_mut().drain(..) {
+            self.group.remove(&row);
+        }
+
+        for health in self.service.plugin_health() {
+            let row = self.plugin_row(&health);
+            row.set_expanded(expanded.contains(&health.plugin));
+            self.group.add(&row);
+            self.rows.borrow_mut().push((health.plugin, row));
+        }
+    }
+
+    fn plugin_row(&self, health: &PluginHealth) -> adw::ExpanderRow {
+        let row = adw::ExpanderRow::builder()
+            .title(health.plugin.as_str())
+            .subtitle(format!(
+                "{} · {}",
+                describe_kind(health.plugin_kind),
+                describe_state(health)
+            ))
+            .build();
+        row.add_prefix(&gtk::Image::from_icon_name(state_icon(health.state)));
+
+        let retry_button = gtk::Button::builder()
+            .icon_name("view-refresh-symbolic")
+            .tooltip_text("Retry now")
+            .valign(gtk::Align::Center)
+            .visible(matches!(
+                health.state,
+                HealthState::Degraded | HealthState::Quarantined | HealthState::HalfOpen
+            ))
+            .build();
+        retry_button.add_css_class("flat");
+        row.add_suffix(&retry_button);
+
+        let enabled_switch = gtk::Switch::builder()
+            .active(health.state != HealthState::Disabled)
+            .tooltip_text("Call this plugin")
+            .valign(gtk::Align::Center)
+            .build();
+        row.add_suffix(&enabled_switch);
+
+        let last_error = health.last_error.as_ref().map_or_else(
+            || "None".to_string(),
+            |kind| {
+                let problem = describe_failure(&health.plugin, kind);
+                format!("{}\n{}", problem.title, problem.detail)
+                    .trim_end()
+                    .to_string()
+            },
+        );
+        row.add_row(&property_row("Last error", &last_error));
+        row.add_row(&property_row(
+            "Calls",
+            &format!("{} succeeded, {} failed", health.successes, health.failures),
+        ));
+        row.add_row(&property_row(
+            "Consecutive panics",
+            &health.consecutive_panics.to_string(),
+        ));
+        row.add_row(&property_row(
+            "Last refresh",
+            &health
+                .last_refresh_latency
+                .map_or_else(|| "Not yet".to_string(), describe_latency),
+        ));
+        if let Some(retry_in) = health.retry_in {
+            row.add_row(&property_row(
+                "Retried in",
+                &format!("{} s", retry_in.as_secs().max(1)),
+            ));
+        }
+
+        let plugin = health.plugin.clone();
+        let this = self.clone();
+        retry_button.connect_clicked(move |_| match this.service.retry_plugin(&plugin) {
+            Ok(()) => {
+                this.reload();
+                (this.on_retry)();
+            }
+            Err(err) => eprintln!(
+                "InstallGrid: failed to retry {plugin}: {}",
+                format_host_error(err)
+            ),
+        });
+
+        let plugin = health.plugin.clone();
+        let this = self.clone();
+        enabled_switch.connect_state_set(move |_, enabled| {
+            match this.service.set_plugin_enabled(&plugin, enabled) {
+                Ok(()) => {
+                    // Rebuilding inside the handler would drop this switch.
+                    let this = this.clone();
+                    glib::idle_add_local_once(move || {
+                        this.reload();
+                        if enabled {
+                            (this.on_retry)();
+                        }
+                    });
+                    glib::Propagation::Proceed
+                }
+                Err(err) => {
+                    eprintln!(
+                        "InstallGrid: failed to change {plugin}: {}",
+                        format_host_error(err)
+                    );
+                    glib::Propagation::Stop
+                }
+            }
+        });
+
+        row
+    }
+}
+
+fn property_row(title: &str, value: &str) -> adw::ActionRow {
+    let row = adw::ActionRow::builder()
+        .title(title)
+        .subtitle(value)
+        .subtitle_selectable(true)
+        .build();
+    row.add_css_class("property");
+    row
+}
+
+fn describe_kind(kind: PluginKind) -> &'static str {
+    match kind {
+        PluginKind::Legacy => "Legacy",
+        PluginKind::Native => "Native",
+    }
+}
+
+fn describe_state(health: &PluginHealth) -> &'static str {
+    match health.state {
+        HealthState::Healthy if health.successes == 0 => "Not called yet",
+        HealthState::Healthy => "Healthy",
+        HealthState::Degraded => "Degraded",
+        HealthState::Quarantined => "Quarantined",
+        HealthState::HalfOpen => "Retrying",
+        HealthState::Disabled => "Disabled",
+    }
+}
+
+fn state_icon(state: HealthState) -> &'static str {
+    match state {
+        HealthState::Healthy => "emblem-ok-symbolic",
+        HealthState::Degraded | HealthState::HalfOpen => "dialog-warning-symbolic",
+        HealthState::Quarantined => "dialog-error-symbolic",
+        HealthState::Disabled => "action-unavailable-symbolic",
+    }
+}
+
+fn describe_latency(latency: Duration) -> String {
+    match latency.as_millis() {
+        0..=999 => format!("{} ms", latency.as_millis()),
+        _ => format!("{:.1} s", latency.as_secs_f64()),
+    }
+}
@@ -0,0 +1,249 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use adw::prelude::*;
+use gtk4 as gtk;
+use gtk::glib;
+use libadwaita as adw;
+
+use crate::host::HostError;
+use crate::plugins::{PluginExecutionError, PluginFailure, PluginFailureKind};
+use crate::registry::{HealthState, PluginHealth};
+
+use super::format_host_error;
+
+/// Why a plugin call failed, as far as the user is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum FailureClass {
+    Timeout,
+    /// A panic or a crashed helper process.
+    Crash,
+    /// The backend cannot be used until something outside it changes.
+    Unavailable,
+    /// Switched off, or asked for something it does not do.
+    Skipped,
+    Error,
+}
+
+/// A plugin failure worded for the user.
+pub(super) struct Problem {
+    pub(super) class: FailureClass,
+    pub(super) title: String,
+    pub(super) detail: String,
+}
+
+pub(super) fn describe_failure(plugin: &str, kind: &PluginFailureKind) -> Problem {
+    let (class, title, detail) = match kind {
+        PluginFailureKind::Execution(PluginExecutionError::Timeout(limit)) => (
+            FailureClass::Timeout,
+            format!("{plugin} timed out"),
+            format!("No answer within {} seconds.", limit.as_secs().max(1)),
+        ),
+        PluginFailureKind::Panic
+        | PluginFailureKind::Execution(PluginExecutionError::Panicked(_)) => (
+            FailureClass::Crash,
+            format!("{plugin} crashed"),
+            "The plugin panicked. It is paused after repeated crashes.".to_string(),
+        ),
+        PluginFailureKind::Crashed(status)
+        | PluginFailureKind::Execution(PluginExecutionError::ProcessExited(status)) => (
+            FailureClass::Crash,
+            format!("{plugin} crashed"),
+            format!("The plugin process exited: {status}."),
+        ),
+        PluginFailureKind::Quarantined => (
+            FailureClass::Unavailable,
+            format!("{plugin} is paused after repeated crashes"),
+            "It is skipped until its cooldown ends or it is retried from Diagnostics."
+                .to_string(),
+        ),
+        PluginFailureKind::Execution(PluginExecutionError::BackendUnavailable(reason)) => (
+            FailureClass::Unavailable,
+            format!("{plugin} is unavailable"),
+            reason.clone(),
+        ),
+        PluginFailureKind::Execution(PluginExecutionError::LegacyUnavailable) => (
+            FailureClass::Unavailable,
+            format!("{plugin} is unavailable"),
+            "InstallGrid was built without gnome-software plugin support.".to_string(),
+        ),
+        PluginFailureKind::Disabled => (
+            FailureClass::Skipped,
+            format!("{plugin} is disabled"),
+            "It was switched off in Diagnostics.".to_string(),
+        ),
+        PluginFailureKind::Execution(PluginExecutionError::Unsupported) => (
+            FailureClass::Skipped,
+            format!("{plugin} cannot do this"),
+            "The plugin does not support this operation.".to_string(),
+        ),
+        PluginFailureKind::Execution(PluginExecutionError::Cancelled) => (
+            FailureClass::Skipped,
+            format!("{plugin} was cancelled"),
+            String::new(),
+        ),
+        PluginFailureKind::Execution(PluginExecutionError::Operation(message)) => (
+            FailureClass::Error,
+            format!("{plugin} failed"),
+            message.clone(),
+        ),
+    };
+    Problem {
+        class,
+        title,
+        detail,
+    }
+}
+
+/// Toasts for transient plugin warnings and errors, and a banner for
+/// plugins that stay unusable.
+#[derive(Clone)]
+pub(super) struct Notices {
+    overlay: adw::ToastOverlay,
+    banner: adw::Banner,
+    /// Warnings already toasted that are still current, so each refresh
+    /// does not repeat them.
+    shown: Rc<RefCell<HashSet<(String, String)>>>,
+}
+
+impl Notices {
+    /// Toasts are shown over `child`. The banner and the "Diagnostics"
+    /// buttons activate `win.show-diagnostics`.
+    pub(super) fn new(child: &impl IsA<gtk::Widget>) -> Self {
+        let overlay = adw::ToastOverlay::new();
+        overlay.set_child(Some(child));
+
+        let banner = adw::Banner::builder()
+            .button_label("Diagnostics")
+            .action_name("win.show-diagnostics")
+            .revealed(false)
+            .build();
+
+        Self {
+            overlay,
+            banner,
+            shown: Rc::new(RefCell::new(HashSet::new())),
+        }
+    }
+
+    pub(super) fn widget(&self) -> &adw::ToastOverlay {
+        &self.overlay
+    }
+
+    pub(super) fn banner(&self) -> &adw::Banner {
+        &self.banner
+    }
+
+    /// Toasts `failure` unless it is already showing or not worth a toast.
+    pub(super) fn warning(&self, failure: &PluginFailure) {
+        let problem = describe_failure(&failure.plugin, &failure.kind);
+        if problem.class == FailureClass::Skipped {
+            return;
+        }
+        let key = (failure.plugin.clone(), failure.kind.to_string());
+        if self.shown.borrow_mut().insert(key) {
+            self.toast(&problem.title, &problem.detail);
+        }
+    }
+
+    pub(super) fn warnings(&self, failures: &[PluginFailure]) {
+        for failure in failures {
+            self.warning(failure);
+        }
+    }
+
+    /// Replaces the current warnings: resolved ones are forgotten, so they
+    /// are toasted again if they come back.
+    pub(super) fn set_warnings(&self, failures: &[PluginFailure]) {
+        self.shown.borrow_mut().retain(|(plugin, kind)| {
+            failures
+                .iter()
+                .any(|failure| &failure.plugin == plugin && &failure.kind.to_string() == kind)
+        });
+        self.warnings(failures);
+    }
+
+    pub(super) fn error(&self, err: HostError) {
+        match err {
+            HostError::Cancelled => {}
+            HostError::AllFailed(failures) => {
+                let detail = failures
+                    .iter()
+                    .map(|failure| describe_failure(&failure.plugin, &failure.kind))
+                    .map(|problem| format!("{}\n{}", problem.title, problem.detail))
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
+                self.toast("No plugin answered", &detail);
+            }
+            HostError::Plugin(failure) => {
+                let problem = describe_failure(&failure.plugin, &failure.kind);
+                self.toast(&problem.title, &problem.detail);
+            }
+            other => self.toast(&format_host_error(other), ""),
+        }
+    }
+
+    /// Shows the banner while a plugin is quarantined or its backend is
+    /// unavailable. Disabled plugins are left out.
+    pub(super) fn update_banner(&self, health: &[PluginHealth]) {
+        let problems = health
+            .iter()
+            .filter_map(|entry| match entry.state {
+                HealthState::Disabled => None,
+                HealthState::Quarantined => Some(describe_failure(
+                    &entry.plugin,
+                    &PluginFailureKind::Quarantined,
+                )),
+                _ => entry
+                    .last_error
+                    .as_ref()
+                    .map(|kind| describe_failure(&entry.plugin, kind))
+                    .filter(|problem| problem.class == FailureClass::Unavailable),
+            })
+            .collect::<Vec<_>>();
+
+        match problems.as_slice() {
+            [] => self.banner.set_revealed(false),
+            [problem] => {
+                self.banner
+                    .set_title(&glib::markup_escape_text(&problem.title));
+                self.banner.set_revealed(true);
+            }
+            _ => {
+                self.banner.set_title(&format!(
+                    "{} plugins are unavailable",
+                    problems.len()
+                ));
+                self.banner.set_revealed(true);
+            }
+        }
+    }
+
+    /// A toast with a "Details" button when there is more to say.
+    fn toast(&self, title: &str, detail: &str) {
+        let toast = adw::Toast::new(&glib::markup_escape_text(title));
+        if !detail.is_empty() {
+            toast.set_button_label(Some("Details"));
+            let (title, detail) = (title.to_string(), detail.to_string());
+            toast.connect_button_clicked(glib::clone!(@weak self.overlay as overlay => move |_| {
+                show_details(&overlay, &title, &detail);
+            }));
+        }
+        self.overlay.add_toast(toast);
+    }
+}
+
+fn show_details(overlay: &adw::ToastOverlay, title: &str, detail: &str) {
+    let window = overlay.root().and_downcast::<gtk::Window>();
+    let dialog = adw::MessageDialog::new(window.as_ref(), Some(title), Some(detail));
+    dialog.add_responses(&[("diagnostics", "Diagnostics"), ("close", "Close")]);
+    dialog.set_default_response(Some("close"));
+    dialog.set_close_response("close");
+    dialog.connect_response(Some("diagnostics"), move |_, _| {
+        if let Some(window) = &window {
+            let _ = window.activate_action("win.show-diagnostics", None);
+        }
+    });
+    dialog.present();
+}
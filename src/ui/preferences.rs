@@ -0,0 +1,198 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use adw::prelude::*;
+use glib::clone;
+use gtk4 as gtk;
+use gtk::{gio, glib};
+use libadwaita as adw;
+
+use crate::application::InstallGridApplication;
+use crate::cancellation::CancellationHandle;
+use crate::host::{AppStoreService, HostError};
+use crate::plugins::{Repository, RepositoryAction};
+
+use super::format_host_error;
+
+/// Opens the preferences window with repository management.
+pub(super) fn show(
+    parent: &adw::ApplicationWindow,
+    app: &InstallGridApplication,
+    service: Rc<AppStoreService>,
+) {
+    let window = adw::PreferencesWindow::builder()
+        .transient_for(parent)
+        .modal(true)
+        .search_enabled(false)
+        .build();
+
+    let page = adw::PreferencesPage::builder()
+        .title("Repositories")
+        .icon_name("folder-remote-symbolic")
+        .build();
+    let group = adw::PreferencesGroup::builder()
+        .title("Repositories")
+        .description("Sources that applications are installed from")
+        .build();
+
+    let add_button = gtk::Button::builder()
+        .icon_name("list-add-symbolic")
+        .tooltip_text("Add .flatpakrepo file")
+        .valign(gtk::Align::Center)
+        .build();
+    add_button.add_css_class("flat");
+    group.set_header_suffix(Some(&add_button));
+
+    page.add(&group);
+    window.add(&page);
+
+    let rows: Rc<RefCell<Vec<adw::SwitchRow>>> = Rc::new(RefCell::new(Vec::new()));
+    let rebuild: Rc<dyn Fn()> = Rc::new(
+        clone!(@weak service, @weak window, @weak group, @strong rows => move || {
+            for row in rows.borrow_mut().drain(..) {
+                group.remove(&row);
+            }
+            for repository in service.repositories() {
+                let row = repository_row(&window, &service, repository);
+                group.add(&row);
+                rows.borrow_mut().push(row);
+            }
+        }),
+    );
+    rebuild();
+
+    // Changes made here and by refreshes both arrive as `repository-changed`.
+    let handler = RefCell::new(Some(app.connect_local(
+        "repository-changed",
+        false,
+        clone!(@strong rebuild => move |_| {
+            rebuild();
+            None
+        }),
+    )));
+    window.connect_destroy(clone!(@weak app => move |_| {
+        if let Some(handler) = handler.borrow_mut().take() {
+            app.disconnect(handler);
+        }
+    }));
+
+    add_button.connect_clicked(clone!(@weak service, @weak window => move |_| {
+        glib::MainContext::default().spawn_local(clone!(@weak service, @weak window => async move {
+            add_repository(&window, &service).await;
+        }));
+    }));
+
+    glib::MainContext::default().spawn_local(clone!(@weak service, @weak window => async move {
+        if let Err(err) = service.refresh_repositories(&CancellationHandle::new()).await {
+            show_error(&window, err);
+        }
+    }));
+
+    window.present();
+}
+
+fn repository_row(
+    window: &adw::PreferencesWindow,
+    service: &Rc<AppStoreService>,
+    repository: Repository,
+) -> adw::SwitchRow {
+    let subtitle = match &repository.url {
+        Some(url) => format!("{url} · {}", repository.plugin),
+        None => repository.plugin.clone(),
+    };
+    let row = adw::SwitchRow::builder()
+        .title(&repository.name)
+        .subtitle(&subtitle)
+        .active(repository.enabled)
+        .build();
+
+    let remove_button = gtk::Button::builder()
+        .icon_name("user-trash-symbolic")
+        .tooltip_text("Remove repository")
+        .valign(gtk::Align::Center)
+        .build();
+    remove_button.add_css_class("flat");
+    row.add_suffix(&remove_button);
+
+    let repository = Rc::new(repository);
+    row.connect_active_notify(clone!(@weak service, @weak window, @strong repository => move |row| {
+        // Also notified when a failed change is undone below.
+        if row.is_active() == repository.enabled {
+            return;
+        }
+        let action = if row.is_active() {
+            RepositoryAction::Enable
+        } else {
+            RepositoryAction::Disable
+        };
+        manage_repository(&window, row, &service, &repository, action);
+    }));
+    remove_button.connect_clicked(clone!(@weak service, @weak window, @weak row, @strong repository => move |_| {
+        manage_repository(&window, &row, &service, &repository, RepositoryAction::Remove);
+    }));
+
+    row
+}
+
+/// Runs `action`; the row is rebuilt from the resulting
+/// `repository-changed`, or restored when the plugin refuses.
+fn manage_repository(
+    window: &adw::PreferencesWindow,
+    row: &adw::SwitchRow,
+    service: &Rc<AppStoreService>,
+    repository: &Rc<Repository>,
+    action: RepositoryAction,
+) {
+    row.set_sensitive(false);
+    glib::MainContext::default().spawn_local(clone!(@weak service, @weak window, @weak row, @strong repository => async move {
+        let result = service
+            .manage_repository(&repository, action, &CancellationHandle::new())
+            .await;
+        if let Err(err) = result {
+            show_error(&window, err);
+            row.set_active(repository.enabled);
+            row.set_sensitive(true);
+        }
+    }));
+}
+
+async fn add_repository(window: &adw::PreferencesWindow, service: &AppStoreService) {
+    let filter = gtk::FileFilter::new();
+    filter.set_name(Some("Repository files"));
+    filter.add_pattern("*.flatpakrepo");
+    let filters = gio::ListStore::new::<gtk::FileFilter>();
+    filters.append(&filter);
+
+    let dialog = gtk::FileDialog::builder()
+        .title("Add Repository")
+        .modal(true)
+        .filters(&filters)
+        .build();
+    // Dismissing the dialog also ends up here.
+    let Ok(file) = dialog.open_future(Some(window)).await else {
+        return;
+    };
+    let Some(path) = file.path() else {
+        window.add_toast(adw::Toast::new("Only local files can be added"));
+        return;
+    };
+
+    match service
+        .add_repository(&path, None, &CancellationHandle::new())
+        .await
+    {
+        Ok(repository) => {
+            let title = format!("Added {}", repository.name);
+            window.add_toast(adw::Toast::new(&glib::markup_escape_text(&title)));
+        }
+        Err(err) => show_error(window, err),
+    }
+}
+
+fn show_error(window: &adw::PreferencesWindow, err: HostError) {
+    if matches!(err, HostError::Cancelled) {
+        return;
+    }
+    let message = format_host_error(err);
+    window.add_toast(adw::Toast::new(&glib::markup_escape_text(&message)));
+}
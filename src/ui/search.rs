@@ -0,0 +1,206 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use adw::prelude::*;
+use gtk4 as gtk;
+use gtk::glib;
+use libadwaita as adw;
+
+use crate::cancellation::CancellationHandle;
+use crate::host::AppStoreService;
+use crate::plugins::MergedApp;
+
+use super::app_list::AppList;
+use super::notices::Notices;
+
+/// Pause in typing before the backends are searched, in milliseconds.
+const SEARCH_DELAY_MS: u32 = 400;
+
+/// Cached apps matching the query at once, then the merged matches of
+/// every backend once typing pauses.
+#[derive(Clone)]
+pub(super) struct SearchPage {
+    root: gtk::Box,
+    entry: gtk::SearchEntry,
+    stack: gtk::Stack,
+    spinner: gtk::Spinner,
+    status_label: gtk::Label,
+    app_list: AppList,
+    notices: Notices,
+    /// Set while the backends are searched; a new query cancels it.
+    searching: Rc<RefCell<Option<CancellationHandle>>>,
+    service: Rc<AppStoreService>,
+}
+
+impl SearchPage {
+    /// Activating a result calls `open_app`.
+    pub(super) fn new(
+        service: Rc<AppStoreService>,
+        entry: &gtk::SearchEntry,
+        notices: Notices,
+        open_app: Rc<dyn Fn(MergedApp)>,
+    ) -> Self {
+        let spinner = gtk::Spinner::new();
+        spinner.set_visible(false);
+        let status_label = gtk::Label::new(None);
+        status_label.set_halign(gtk::Align::Start);
+        let status = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        status.append(&spinner);
+        status.append(&status_label);
+
+        let app_list = AppList::new(open_app);
+
+        let empty = adw::StatusPage::builder()
+            .icon_name("system-search-symbolic")
+            .title("No Results Found")
+            .description("Try a different search")
+            .vexpand(true)
+            .build();
+
+        let stack = gtk::Stack::new();
+        stack.add_named(app_list.widget(), Some("results"));
+        stack.add_named(&empty, Some("empty"));
+
+        let root = gtk::Box::new(gtk::Orientation::Vertical, 12);
+        root.set_margin_top(12);
+        root.set_margin_bottom(12);
+        root.set_margin_start(12);
+        root.set_margin_end(12);
+        root.append(&status);
+        root.append(&stack);
+
+        entry.set_search_delay(SEARCH_DELAY_MS);
+
+        let page = Self {
+            root,
+            entry: entry.clone(),
+            stack,
+            spinner,
+            status_label,
+            app_list,
+            notices,
+            searching: Rc::new(RefCell::new(None)),
+            service,
+        };
+
+        // `changed` fires on every keystroke, `search-changed` after the delay.
+        let this = page.clone();
+        entry.connect_changed(move |entry| this.filter_cached(&entry.text()));
+        let this = page.clone();
+        entry.connect_search_changed(move |entry| this.search(&entry.text()));
+        let this = page.clone();
+        entry.connect_stop_search(move |_| this.cancel_search());
+
+        page
+    }
+
+    pub(super) fn widget(&self) -> &gtk::Box {
+        &self.root
+    }
+
+    /// Lists the cached apps matching `query` without asking any backend.
+    fn filter_cached(&self, query: &str) {
+        self.cancel_search();
+        let terms = search_terms(query);
+        if terms.is_empty() {
+            self.app_list.replace(&[]);
+            return;
+        }
+
+        let matches = self
+            .service
+            .cache_snapshot()
+            .apps
+            .into_iter()
+            .filter(|app| matches_terms(app, &terms))
+            .collect::<Vec<_>>();
+        self.app_list.replace(&matches);
+        self.show_searching(true);
+    }
+
+    /// Searches every backend and lists its matches ahead of the cached ones.
+    fn search(&self, query: &str) {
+        self.cancel_search();
+        let query = query.trim().to_string();
+        if query.is_empty() {
+            self.show_searching(false);
+            return;
+        }
+
+        let cancel = CancellationHandle::new();
+        self.searching.replace(Some(cancel.clone()));
+        self.show_searching(true);
+
+        let this = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let result = this.service.search(&query, &cancel).await;
+            if cancel.is_cancelled() {
+                return;
+            }
+            this.searching.borrow_mut().take();
+            match result {
+                Ok(outcome) => {
+                    let terms = search_terms(&query);
+                    let found = outcome
+                        .apps
+                        .iter()
+                        .map(MergedApp::app_id)
+                        .collect::<HashSet<_>>();
+                    let cached = this
+                        .service
+                        .cache_snapshot()
+                        .apps
+                        .into_iter()
+                        .filter(|app| {
+                            !found.contains(app.app_id()) && matches_terms(app, &terms)
+                        })
+                        .collect::<Vec<_>>();
+                    let mut apps = outcome.apps;
+                    apps.extend(cached);
+                    this.app_list.replace(&apps);
+                    this.notices.warnings(&outcome.warnings);
+                }
+                Err(err) => this.notices.error(err),
+            }
+            this.show_searching(false);
+        });
+    }
+
+    fn show_searching(&self, searching: bool) {
+        let count = self.app_list.len();
+        self.spinner.set_visible(searching);
+        self.spinner.set_spinning(searching);
+        self.status_label.set_text(&if searching {
+            "Searching…".to_string()
+        } else {
+            format!("{count} results for “{}”", self.entry.text().trim())
+        });
+        // Cached matches stay visible while the backends are searched.
+        let empty = count == 0 && !searching;
+        self.stack
+            .set_visible_child_name(if empty { "empty" } else { "results" });
+    }
+
+    fn cancel_search(&self) {
+        if let Some(cancel) = self.searching.borrow_mut().take() {
+            cancel.cancel();
+        }
+    }
+}
+
+fn search_terms(query: &str) -> Vec<String> {
+    query.split_whitespace().map(str::to_lowercase).collect()
+}
+
+/// Whether every term appears in the app's name, summary or id.
+fn matches_terms(app: &MergedApp, terms: &[String]) -> bool {
+    let name = app.primary.name.to_lowercase();
+    let summary = app.primary.summary.to_lowercase();
+    let app_id = app.app_id().to_lowercase();
+    terms.iter().all(|term| {
+        name.contains(term.as_str())
+            || summary.contains(term.as_str())
+            || app_id.contains(term.as_str())
+    })
+}
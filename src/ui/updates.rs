@@ -0,0 +1,295 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use adw::prelude::*;
+use futures::StreamExt;
+use glib::clone;
+use gtk4 as gtk;
+use gtk::glib;
+use libadwaita as adw;
+
+use crate::cancellation::CancellationHandle;
+use crate::host::{AppStoreService, HostError, JobEvents, UpdateProgress};
+use crate::plugins::{AppUpdate, JobProgress};
+
+use super::format_host_error;
+
+/// Rows are keyed by plugin and app id, like updates in the service.
+type UpdateKey = (String, String);
+
+struct UpdateRow {
+    row: adw::ActionRow,
+    button: gtk::Button,
+    progress: gtk::ProgressBar,
+}
+
+/// Pending updates with update-all and per-app update buttons.
+#[derive(Clone)]
+pub(super) struct UpdatesPage {
+    root: gtk::Box,
+    status_label: gtk::Label,
+    error_label: gtk::Label,
+    list_box: gtk::ListBox,
+    update_all_button: gtk::Button,
+    rows: Rc<RefCell<HashMap<UpdateKey, UpdateRow>>>,
+    /// Set while "Update All" runs; clicking the button again cancels it.
+    running_all: Rc<RefCell<Option<CancellationHandle>>>,
+    service: Rc<AppStoreService>,
+}
+
+impl UpdatesPage {
+    pub(super) fn new(service: Rc<AppStoreService>) -> Self {
+        let status_label = gtk::Label::new(None);
+        status_label.set_halign(gtk::Align::Start);
+        status_label.set_hexpand(true);
+
+        let check_button = gtk::Button::from_icon_name("view-refresh-symbolic");
+        check_button.set_tooltip_text(Some("Check for updates"));
+        let update_all_button = gtk::Button::with_label("Update All");
+        update_all_button.add_css_class("suggested-action");
+
+        let toolbar = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        toolbar.append(&status_label);
+        toolbar.append(&check_button);
+        toolbar.append(&update_all_button);
+
+        let error_label = gtk::Label::new(None);
+        error_label.set_halign(gtk::Align::Start);
+        error_label.set_wrap(true);
+        error_label.add_css_class("dim-label");
+        error_label.set_visible(false);
+
+        let list_box = gtk::ListBox::new();
+        list_box.set_selection_mode(gtk::SelectionMode::None);
+        list_box.add_css_class("boxed-list");
+
+        let root = gtk::Box::new(gtk::Orientation::Vertical, 12);
+        root.set_margin_top(12);
+        root.set_margin_bottom(12);
+        root.set_margin_start(12);
+        root.set_margin_end(12);
+        root.append(&toolbar);
+        root.append(&error_label);
+        root.append(&list_box);
+
+        let page = Self {
+            root,
+            status_label,
+            error_label,
+            list_box,
+            update_all_button,
+            rows: Rc::new(RefCell::new(HashMap::new())),
+            running_all: Rc::new(RefCell::new(None)),
+            service,
+        };
+
+        page.show(&page.service.update_snapshot().updates);
+
+        let this = page.clone();
+        check_button.connect_clicked(move |button| {
+            button.set_sensitive(false);
+            let this = this.clone();
+            glib::MainContext::default().spawn_local(clone!(@weak button => async move {
+                let result = this.service.refresh_updates(&CancellationHandle::new()).await;
+                this.report(result.err());
+                button.set_sensitive(true);
+            }));
+        });
+
+        let this = page.clone();
+        page.update_all_button
+            .connect_clicked(move |_| this.toggle_update_all());
+
+        // Served from the cache while the last check is fresh.
+        let this = page.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let result = this.service.check_updates(&CancellationHandle::new()).await;
+            this.report(result.err());
+        });
+
+        page
+    }
+
+    pub(super) fn widget(&self) -> &gtk::Box {
+        &self.root
+    }
+
+    /// Replaces the rows with `updates`.
+    pub(super) fn show(&self, updates: &[AppUpdate]) {
+        let mut rows = self.rows.borrow_mut();
+        for (_, entry) in rows.drain() {
+            self.list_box.remove(&entry.row);
+        }
+        for update in updates {
+            let entry = self.update_row(update);
+            self.list_box.append(&entry.row);
+            rows.insert(key(update), entry);
+        }
+        drop(rows);
+
+        self.list_box.set_visible(!updates.is_empty());
+        self.status_label.set_text(&match updates.len() {
+            0 => "Everything is up to date".to_string(),
+            1 => "1 update available".to_string(),
+            count => format!("{count} updates available"),
+        });
+        self.update_all_button
+            .set_sensitive(!updates.is_empty() || self.running_all.borrow().is_some());
+        if self.running_all.borrow().is_some() {
+            self.set_buttons_sensitive(false);
+        }
+    }
+
+    fn update_row(&self, update: &AppUpdate) -> UpdateRow {
+        let row = adw::ActionRow::builder()
+            .title(glib::markup_escape_text(&update.app.name).as_str())
+            .subtitle(glib::markup_escape_text(&describe_update(update)).as_str())
+            .build();
+
+        let progress = gtk::ProgressBar::new();
+        progress.set_valign(gtk::Align::Center);
+        progress.set_visible(false);
+        let button = gtk::Button::with_label("Update");
+        button.set_valign(gtk::Align::Center);
+        row.add_suffix(&progress);
+        row.add_suffix(&button);
+
+        let this = self.clone();
+        let update = update.clone();
+        button.connect_clicked(move |_| {
+            let cancel = CancellationHandle::new();
+            match this.service.update(&update, &cancel) {
+                Ok(events) => this.follow(update.clone(), events),
+                Err(err) => this.report(Some(err)),
+            }
+        });
+
+        UpdateRow {
+            row,
+            button,
+            progress,
+        }
+    }
+
+    /// Shows the progress of a single update until its job ends.
+    fn follow(&self, update: AppUpdate, mut events: JobEvents) {
+        let this = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            while let Some(item) = events.next().await {
+                let progress = item.map_err(HostError::Plugin);
+                this.apply(UpdateProgress {
+                    update: update.clone(),
+                    progress,
+                });
+            }
+        });
+    }
+
+    fn toggle_update_all(&self) {
+        if let Some(cancel) = self.running_all.borrow().as_ref() {
+            cancel.cancel();
+            return;
+        }
+
+        let cancel = CancellationHandle::new();
+        let mut events = self.service.update_all(&cancel);
+        self.running_all.replace(Some(cancel));
+        self.update_all_button.set_label("Cancel");
+        self.update_all_button.remove_css_class("suggested-action");
+        self.set_buttons_sensitive(false);
+        self.error_label.set_visible(false);
+
+        let this = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            while let Some(progress) = events.next().await {
+                this.apply(progress);
+            }
+            this.running_all.borrow_mut().take();
+            this.update_all_button.set_label("Update All");
+            this.update_all_button.add_css_class("suggested-action");
+            this.show(&this.service.update_snapshot().updates);
+        });
+    }
+
+    /// Reflects one job step on the row of the app it belongs to. Finished
+    /// updates disappear through [`UpdatesPage::show`].
+    fn apply(&self, progress: UpdateProgress) {
+        let rows = self.rows.borrow();
+        let entry = rows.get(&key(&progress.update));
+        match progress.progress {
+            Ok(JobProgress::Queued) => {
+                if let Some(entry) = entry {
+                    entry.button.set_sensitive(false);
+                    entry.progress.set_visible(true);
+                    entry.progress.set_fraction(0.0);
+                }
+            }
+            Ok(JobProgress::Running { percent }) => {
+                if let Some(entry) = entry {
+                    entry.progress.set_visible(true);
+                    match percent {
+                        Some(percent) => entry.progress.set_fraction(f64::from(percent) / 100.0),
+                        None => entry.progress.pulse(),
+                    }
+                }
+            }
+            Ok(JobProgress::Finished) => {}
+            Err(err) => {
+                if let Some(entry) = entry {
+                    entry.progress.set_visible(false);
+                    entry
+                        .button
+                        .set_sensitive(self.running_all.borrow().is_none());
+                }
+                drop(rows);
+                if !matches!(err, HostError::Cancelled) {
+                    let name = progress.update.app.name;
+                    self.error_label
+                        .set_text(&format!("{name}: {}", format_host_error(err)));
+                    self.error_label.set_visible(true);
+                }
+            }
+        }
+    }
+
+    fn set_buttons_sensitive(&self, sensitive: bool) {
+        for entry in self.rows.borrow().values() {
+            entry.button.set_sensitive(sensitive);
+        }
+    }
+
+    fn report(&self, err: Option<HostError>) {
+        match err {
+            None | Some(HostError::Cancelled) => {}
+            Some(err) => {
+                self.error_label.set_text(&format_host_error(err));
+                self.error_label.set_visible(true);
+            }
+        }
+    }
+}
+
+fn key(update: &AppUpdate) -> UpdateKey {
+    (update.plugin.clone(), update.app.app_id.clone())
+}
+
+/// "1.0 → 2.0 · 12.3 MB", leaving out what the backend did not report.
+fn describe_update(update: &AppUpdate) -> String {
+    let versions = match (&update.version_from, &update.version_to) {
+        (Some(from), Some(to)) => Some(format!("{from} → {to}")),
+        (None, Some(to)) => Some(to.clone()),
+        (Some(from), None) => Some(format!("{from} → newer")),
+        (None, None) => None,
+    };
+    let size = update
+        .download_size
+        .map(|bytes| glib::format_size(bytes).to_string());
+
+    let parts = versions.into_iter().chain(size).collect::<Vec<_>>();
+    if parts.is_empty() {
+        update.app.summary.clone()
+    } else {
+        parts.join(" · ")
+    }
+}